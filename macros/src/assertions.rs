@@ -0,0 +1,83 @@
+//! Compile-time checks that variant structs implement the traits named in
+//! `#[assert_impl(...)]`
+
+use proc_macro2::{Span, TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, quote_spanned, ToTokens};
+use syn::{parse_quote, Generics, Path};
+
+use crate::enum_parser::ParsedVariant;
+
+/// One assertion per trait, spanned at the variant so a missing impl is
+/// reported there rather than where the variant is first used as the trait.
+/// Variant generics are assumed to implement the trait themselves, so
+/// `Cons<T>` is checked to be `Send` whenever `T` is.
+pub fn generate_impl_assertions(
+    variant: &ParsedVariant,
+    struct_generics: &Generics,
+    traits: &[Path],
+    lint_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let variant_name = &variant.ident;
+    let (_, ty_generics, _) = struct_generics.split_for_impl();
+    // rustc points at the whole checked type; generics spanned elsewhere
+    // would pull the error out to the macro call
+    let ty_generics = respanned(ty_generics.to_token_stream(), variant_name.span());
+
+    traits
+        .iter()
+        .map(|trait_path| {
+            let mut generics = struct_generics.clone();
+            let params: Vec<_> = generics
+                .type_params()
+                .map(|param| param.ident.clone())
+                .collect();
+            let where_clause = generics.make_where_clause();
+            for param in params {
+                where_clause
+                    .predicates
+                    .push(parse_quote! { #param: #trait_path });
+            }
+            let (impl_generics, _, where_clause) = generics.split_for_impl();
+
+            let trait_name = trait_path
+                .segments
+                .last()
+                .map(|segment| segment.ident.clone());
+            let check = format_ident!(
+                "{}_must_implement_{}",
+                variant_name,
+                trait_name.expect("a path has at least one segment")
+            );
+
+            quote_spanned! {variant_name.span()=>
+                #lint_attrs
+                #[allow(dead_code)]
+                const _: () = {
+                    const fn assert_impl<T: ?Sized + #trait_path>() {}
+                    const fn #check #impl_generics () #where_clause {
+                        assert_impl::<#variant_name #ty_generics>();
+                    }
+                };
+            }
+        })
+        .collect()
+}
+
+/// Move every token to `span`. Both come from the user's input, so names
+/// still resolve the same way.
+fn respanned(tokens: TokenStream2, span: Span) -> TokenStream2 {
+    tokens
+        .into_iter()
+        .map(|mut token| {
+            if let TokenTree::Group(group) = &token {
+                let mut group =
+                    proc_macro2::Group::new(group.delimiter(), respanned(group.stream(), span));
+                group.set_span(span);
+                token = TokenTree::Group(group);
+            } else {
+                token.set_span(span);
+            }
+            token
+        })
+        .collect()
+}
@@ -0,0 +1,149 @@
+//! `async fn` methods, desugared like `async-trait` so the trait stays `dyn`
+//! compatible: `async fn eval(&self) -> T` is declared as
+//! `fn eval<'__async>(&'__async self) -> Pin<Box<dyn Future<Output = T> + '__async>>`
+//! and each variant's arms run inside `Box::pin(async move { .. })`. Under
+//! `#[type_enum(send_sync)]` the future is `Send` as well.
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::visit::Visit;
+use syn::visit_mut::{self, VisitMut};
+use syn::{Ident, Lifetime, ReturnType, Signature, Type};
+
+use crate::codegen::box_path;
+
+/// Lifetime every borrow of an async method is tied to
+fn async_lifetime() -> Lifetime {
+    Lifetime::new("'__async", Span::call_site())
+}
+
+/// Gives elided references the async lifetime, so the future may hold them
+struct ElidedToAsync;
+
+impl VisitMut for ElidedToAsync {
+    fn visit_type_reference_mut(&mut self, reference: &mut syn::TypeReference) {
+        if reference.lifetime.is_none() {
+            reference.lifetime = Some(async_lifetime());
+        }
+        visit_mut::visit_type_reference_mut(self, reference);
+    }
+
+    fn visit_lifetime_mut(&mut self, lifetime: &mut Lifetime) {
+        if lifetime.ident == "_" {
+            *lifetime = async_lifetime();
+        }
+    }
+}
+
+/// Rewrite an `async fn` signature into one returning a boxed future.
+/// Signatures without `async` are left alone.
+pub fn desugar_async_signature(sig: &mut Signature) -> syn::Result<()> {
+    let box_ty = box_path();
+    let Some(asyncness) = sig.asyncness.take() else {
+        return Ok(());
+    };
+    if crate::variant_gen::is_pinned_receiver(sig) {
+        return Err(syn::Error::new_spanned(
+            asyncness,
+            "async methods cannot take `self: Pin<&mut Self>`; take `&mut self` instead",
+        ));
+    }
+
+    let lifetime = async_lifetime();
+
+    // Everything the future captures must outlive it
+    let outlives: Vec<TokenStream2> = std::iter::once(quote! { Self: #lifetime })
+        .chain(sig.generics.params.iter().map(|param| match param {
+            syn::GenericParam::Lifetime(l) => {
+                let name = &l.lifetime;
+                quote! { #name: #lifetime }
+            }
+            syn::GenericParam::Type(t) => {
+                let name = &t.ident;
+                quote! { #name: #lifetime }
+            }
+            syn::GenericParam::Const(_) => TokenStream2::new(),
+        }))
+        .filter(|predicate| !predicate.is_empty())
+        .collect();
+    sig.generics.params.insert(0, syn::parse_quote!(#lifetime));
+    let where_clause = sig.generics.make_where_clause();
+    for predicate in outlives {
+        where_clause.predicates.push(syn::parse_quote!(#predicate));
+    }
+
+    for input in sig.inputs.iter_mut() {
+        match input {
+            syn::FnArg::Receiver(receiver) => {
+                if let Some((_, elided)) = &mut receiver.reference {
+                    elided.get_or_insert_with(async_lifetime);
+                }
+                ElidedToAsync.visit_type_mut(&mut receiver.ty);
+            }
+            syn::FnArg::Typed(arg) => ElidedToAsync.visit_type_mut(&mut arg.ty),
+        }
+    }
+
+    let output: Type = match &sig.output {
+        ReturnType::Default => syn::parse_quote!(()),
+        ReturnType::Type(_, ty) => (**ty).clone(),
+    };
+    sig.output = syn::parse_quote! {
+        -> ::core::pin::Pin<
+            #box_ty<dyn ::core::future::Future<Output = #output> + #lifetime>
+        >
+    };
+    Ok(())
+}
+
+/// Require the future a desugared signature returns to be `Send`, for enums
+/// whose trait is `Send + Sync` and so can hand out futures to other threads
+pub fn require_send_future(sig: &mut Signature) {
+    struct AddSend;
+
+    impl VisitMut for AddSend {
+        fn visit_type_trait_object_mut(&mut self, object: &mut syn::TypeTraitObject) {
+            object.bounds.push(syn::parse_quote!(::core::marker::Send));
+        }
+    }
+
+    if let ReturnType::Type(_, ty) = &mut sig.output {
+        AddSend.visit_type_mut(ty);
+    }
+}
+
+/// The `Output` of the future a desugared signature returns
+fn future_output(sig: &Signature) -> Option<&Type> {
+    struct FindOutput<'a>(Option<&'a Type>);
+
+    impl<'a> Visit<'a> for FindOutput<'a> {
+        fn visit_assoc_type(&mut self, assoc: &'a syn::AssocType) {
+            if self.0.is_none() && assoc.ident == "Output" {
+                self.0 = Some(&assoc.ty);
+            }
+        }
+    }
+
+    let ReturnType::Type(_, ty) = &sig.output else {
+        return None;
+    };
+    let mut find = FindOutput(None);
+    find.visit_type(ty);
+    find.0
+}
+
+/// Wrap a method body in the boxed future an async method with the desugared
+/// signature `sig` returns. The body is bound with the declared output type,
+/// so a mismatch is reported at the body rather than at the boxed future.
+pub fn boxed_future(sig: &Signature, body: TokenStream2) -> TokenStream2 {
+    let box_ty = box_path();
+    let ret = Ident::new("__ret", Span::mixed_site());
+    let output = future_output(sig).map(|ty| quote! { : #ty });
+    quote! {
+        #box_ty::pin(async move {
+            let #ret #output = { #body };
+            #[allow(unreachable_code)]
+            #ret
+        })
+    }
+}
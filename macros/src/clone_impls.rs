@@ -0,0 +1,82 @@
+//! `clone_box`, and `Clone` for boxed trait objects, generated when every
+//! variant struct derives `Clone`
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Generics, Ident};
+
+use crate::codegen::{allocates, box_path};
+use crate::enum_parser::ParsedEnum;
+use crate::helpers::{derives_trait, variant_derives};
+
+/// The bound on the boxed trait object `clone_box` returns, `+ 'a` for an
+/// enum borrowing for `'a`. `None` when the trait objects can't be cloned:
+/// some variant isn't `Clone`, the enum borrows for several lifetimes, the
+/// trait is the user's own, or there is no `Box` without the `alloc` feature.
+pub fn clone_object_bound(parsed: &ParsedEnum) -> Option<TokenStream2> {
+    if parsed.options.implements.is_some() || !allocates() {
+        return None;
+    }
+    let all_clone = parsed
+        .variants
+        .iter()
+        .all(|variant| derives_trait(&variant_derives(variant, &parsed.options), "Clone"));
+    if !all_clone {
+        return None;
+    }
+    let lifetimes: Vec<_> = parsed.generics.lifetimes().map(|l| &l.lifetime).collect();
+    match lifetimes.as_slice() {
+        [] => Some(TokenStream2::new()),
+        [lifetime] => Some(quote! { + #lifetime }),
+        _ => None,
+    }
+}
+
+/// The `clone_box` declaration on the generated trait
+pub fn generate_clone_decl(
+    enum_name: &Ident,
+    generics_with_static: &Generics,
+    object_bound: &TokenStream2,
+) -> TokenStream2 {
+    let box_ty = box_path();
+    let (_, ty_generics, _) = generics_with_static.split_for_impl();
+    quote! {
+        /// Clone the variant into a new box, for trait objects
+        fn clone_box(&self) -> #box_ty<dyn #enum_name #ty_generics #object_bound>;
+    }
+}
+
+/// `clone_box` for one variant, boxing a clone from its derived `Clone`
+pub fn generate_clone_hook_impl(
+    trait_type: &TokenStream2,
+    object_bound: &TokenStream2,
+) -> TokenStream2 {
+    let box_ty = box_path();
+    quote! {
+        fn clone_box(&self) -> #box_ty<dyn #trait_type #object_bound> {
+            #box_ty::new(::core::clone::Clone::clone(self))
+        }
+    }
+}
+
+/// `impl Clone for Box<dyn Enum<..>>`, so trees of boxed variants deep-copy
+pub fn generate_box_clone(
+    enum_name: &Ident,
+    generics_with_static: &Generics,
+    object_bound: &TokenStream2,
+    lint_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let box_ty = box_path();
+    let (impl_generics, ty_generics, where_clause) = generics_with_static.split_for_impl();
+    quote! {
+        #[automatically_derived]
+        #lint_attrs
+        impl #impl_generics ::core::clone::Clone
+            for #box_ty<dyn #enum_name #ty_generics #object_bound> #where_clause
+        {
+            fn clone(&self) -> Self {
+                self.clone_box()
+            }
+        }
+    }
+}
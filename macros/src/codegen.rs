@@ -0,0 +1,96 @@
+//! Code generation utilities
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{Ident, Member, Pat};
+
+/// Apply type hint generics to type name if needed
+pub fn apply_type_hint_to_pattern(
+    type_name: TokenStream2,
+    hint_generics: &Option<TokenStream2>,
+) -> TokenStream2 {
+    if let Some(generics) = hint_generics {
+        let type_str = type_name.to_string();
+        // Check if type_name doesn't already have generics
+        if !type_str.contains('<') {
+            return quote! { #type_name #generics };
+        }
+    }
+    type_name
+}
+
+/// Name of the hidden trait method returning the position a variant is
+/// declared at, which `match_t!` dispatches on. Types implementing the trait
+/// outside `type_enum!` return `usize::MAX`.
+pub fn discriminant_hook_ident() -> Ident {
+    Ident::new("__enum_typer_discriminant", Span::call_site())
+}
+
+/// Name of the hidden trait method returning the `type_name` of the value
+/// behind a trait object, which `match_t!` reports when no arm matches
+pub fn type_name_hook_ident() -> Ident {
+    Ident::new("__enum_typer_type_name", Span::call_site())
+}
+
+/// The `Box` generated code names, re-exported by the `enum-typer` facade so
+/// the calling crate needn't have `std` or `alloc` in reach under either name
+pub fn box_path() -> TokenStream2 {
+    quote! { ::enum_typer::__private::Box }
+}
+
+/// The `Rc` generated code names, through the facade like [`box_path`]
+pub fn rc_path() -> TokenStream2 {
+    quote! { ::enum_typer::__private::Rc }
+}
+
+/// The `Arc` generated code names, through the facade like [`box_path`]
+pub fn arc_path() -> TokenStream2 {
+    quote! { ::enum_typer::__private::Arc }
+}
+
+/// Whether generated code may box. Without the `alloc` feature the items
+/// built on `Box`, such as `From<Variant> for Box<dyn Enum>`, are left out.
+pub fn allocates() -> bool {
+    cfg!(feature = "alloc")
+}
+
+/// Code for a branch the generated dispatch can never take, such as a failed
+/// downcast right after a successful type check. With the `unchecked` feature
+/// this becomes `unreachable_unchecked` in release builds; debug builds still
+/// panic with `message`.
+pub fn unreachable_branch(message: &str) -> TokenStream2 {
+    if cfg!(feature = "unchecked") {
+        quote! {
+            {
+                ::core::debug_assert!(false, #message);
+                unsafe { ::core::hint::unreachable_unchecked() }
+            }
+        }
+    } else {
+        quote! { ::core::panic!(#message) }
+    }
+}
+
+/// Rewrite a variant pattern into a tuple pattern over its fields in
+/// declaration order, e.g. `Point { y, .. }` becomes `(_, y,)` for fields `x, y`
+pub fn fields_tuple_pattern(pat: &Pat, members: &[Member]) -> TokenStream2 {
+    match pat {
+        Pat::TupleStruct(tuple) => {
+            let elems = tuple.elems.iter();
+            quote! { (#(#elems,)*) }
+        }
+        Pat::Struct(pat_struct) => {
+            let elems = members.iter().map(|member| {
+                match pat_struct.fields.iter().find(|f| f.member == *member) {
+                    Some(field_pat) => {
+                        let sub = &field_pat.pat;
+                        quote! { #sub }
+                    }
+                    None => quote! { _ },
+                }
+            });
+            quote! { (#(#elems,)*) }
+        }
+        _ => quote! { _ },
+    }
+}
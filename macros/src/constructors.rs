@@ -0,0 +1,414 @@
+//! Convenience constructors and accessors generated next to each variant struct
+
+use proc_macro2::{Ident, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use std::collections::HashSet;
+use syn::{GenericArgument, Generics, PathArguments, Type, TypeParamBound, Visibility};
+
+use crate::codegen::box_path;
+use crate::enum_parser::{ParsedEnum, ParsedVariant};
+use crate::helpers::{
+    add_static_bounds, cfg_attrs, deprecation_attrs, doc_attrs, field_members, merge_generics,
+    snake_case_ident,
+};
+use crate::type_analysis::{
+    collect_all_type_param_names, collect_const_param_names, collect_lifetime_names,
+};
+use crate::variant_gen::{
+    extract_type_params_from_trait, resolve_trait_type, variant_struct_generics,
+};
+
+/// The bounds of `dyn ..` inside a field typed `Box<dyn ..>`, written out
+/// literally (type aliases for boxes cannot be seen through)
+fn boxed_trait_object_bounds(ty: &Type) -> Option<Vec<&TypeParamBound>> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let last = type_path.path.segments.last()?;
+    if last.ident != "Box" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(args) = &last.arguments else {
+        return None;
+    };
+    match args.args.first()? {
+        GenericArgument::Type(Type::TraitObject(object)) => Some(object.bounds.iter().collect()),
+        _ => None,
+    }
+}
+
+/// `Variant::of(..)`, taking `impl Trait + 'static` for every `Box<dyn Trait>`
+/// field and boxing it, so trees can be built without spelling out `Box::new`
+pub fn generate_boxing_constructor(
+    variant: &ParsedVariant,
+    struct_generics: &Generics,
+    vis: &Visibility,
+    lint_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let box_ty = box_path();
+    let boxed: Vec<_> = variant
+        .fields
+        .iter()
+        .map(|field| boxed_trait_object_bounds(&field.ty))
+        .collect();
+    if boxed.iter().all(Option::is_none) {
+        return TokenStream2::new();
+    }
+
+    let variant_name = &variant.ident;
+    let members = field_members(&variant.fields);
+    let (impl_generics, ty_generics, where_clause) = struct_generics.split_for_impl();
+
+    let mut params = Vec::new();
+    let mut values = Vec::new();
+    for (index, (field, bounds)) in variant.fields.iter().zip(&boxed).enumerate() {
+        let positional_name = variant.positional_names.get(index).and_then(Option::as_ref);
+        let arg = match field.ident.as_ref().or(positional_name) {
+            Some(ident) => ident.clone(),
+            None => format_ident!("arg{}", index),
+        };
+        match bounds {
+            Some(bounds) => {
+                let has_lifetime = bounds
+                    .iter()
+                    .any(|b| matches!(b, TypeParamBound::Lifetime(_)));
+                let static_bound = (!has_lifetime).then(|| quote! { + 'static });
+                params.push(quote! { #arg: impl #(#bounds)+* #static_bound });
+                values.push(quote! { #box_ty::new(#arg) });
+            }
+            None => {
+                let ty = &field.ty;
+                params.push(quote! { #arg: #ty });
+                values.push(quote! { #arg });
+            }
+        }
+    }
+
+    let deprecated = deprecation_attrs(&variant.attrs);
+    // The variant's own docs follow, as a paragraph of their own
+    let docs = doc_attrs(&variant.attrs);
+    let separator = (!docs.is_empty()).then(|| quote! { #[doc = ""] });
+    let doc = format!(
+        "Build a `{}`, boxing the arguments that are stored as trait objects",
+        variant_name
+    );
+
+    quote! {
+        #lint_attrs
+        impl #impl_generics #variant_name #ty_generics #where_clause {
+            #[doc = #doc]
+            #separator
+            #(#docs)*
+            #(#deprecated)*
+            #vis fn of(#(#params),*) -> Self {
+                #variant_name { #(#members: #values),* }
+            }
+        }
+    }
+}
+
+/// `impl From<Variant> for Box<dyn Trait<..>>`, so APIs taking
+/// `impl Into<Box<dyn Trait<..>>>` accept bare variant values
+pub fn generate_into_box(
+    variant: &ParsedVariant,
+    impl_generics: &Generics,
+    variant_ty_generics: &TokenStream2,
+    trait_type: &TokenStream2,
+    lint_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let box_ty = box_path();
+    let variant_name = &variant.ident;
+    // A borrowing variant only lives as long as its one lifetime; with more,
+    // there is no single bound for the trait object
+    let lifetimes: Vec<_> = impl_generics.lifetimes().map(|l| &l.lifetime).collect();
+    let object_bound = match lifetimes.as_slice() {
+        [] => TokenStream2::new(),
+        [lifetime] => quote! { + #lifetime },
+        _ => return TokenStream2::new(),
+    };
+    let (impl_generics, _, where_clause) = impl_generics.split_for_impl();
+
+    quote! {
+        #[automatically_derived]
+        #lint_attrs
+        impl #impl_generics ::core::convert::From<#variant_name #variant_ty_generics>
+            for #box_ty<dyn #trait_type #object_bound> #where_clause
+        {
+            fn from(variant: #variant_name #variant_ty_generics) -> Self {
+                #box_ty::new(variant)
+            }
+        }
+    }
+}
+
+/// `variant.boxed()`, the variant as its boxed trait object. Index parameters
+/// the struct doesn't carry, like `T` for `Nil : List<T, Empty>`, become the
+/// method's own generics.
+pub fn generate_boxed_method(
+    variant: &ParsedVariant,
+    struct_generics: &Generics,
+    trait_impl_generics: &Generics,
+    trait_type: &TokenStream2,
+    vis: &Visibility,
+    lint_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let box_ty = box_path();
+    let variant_name = &variant.ident;
+    let lifetimes: Vec<_> = trait_impl_generics
+        .lifetimes()
+        .map(|l| &l.lifetime)
+        .collect();
+    let object_bound = match lifetimes.as_slice() {
+        [] => TokenStream2::new(),
+        [lifetime] => quote! { + #lifetime },
+        _ => return TokenStream2::new(),
+    };
+
+    let struct_params: HashSet<String> = struct_generics
+        .params
+        .iter()
+        .map(generic_param_name)
+        .collect();
+    let mut method_generics = Generics {
+        params: trait_impl_generics
+            .params
+            .iter()
+            .filter(|param| !struct_params.contains(&generic_param_name(param)))
+            .cloned()
+            .collect(),
+        ..Generics::default()
+    };
+    method_generics.where_clause = trait_impl_generics.where_clause.clone();
+    let (impl_generics, ty_generics, struct_where_clause) = struct_generics.split_for_impl();
+    // Bounds the struct leaves out, like `N: Expr<T>` on a struct without
+    // `T`, come in through the trait impl
+    method_generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(#variant_name #ty_generics: #trait_type));
+    let (method_generics, _, where_clause) = method_generics.split_for_impl();
+
+    let deprecated = deprecation_attrs(&variant.attrs);
+    quote! {
+        #lint_attrs
+        impl #impl_generics #variant_name #ty_generics #struct_where_clause {
+            /// Box the variant as its trait object
+            #(#deprecated)*
+            #vis fn boxed #method_generics(self) -> #box_ty<dyn #trait_type #object_bound> #where_clause {
+                #box_ty::new(self)
+            }
+        }
+    }
+}
+
+fn generic_param_name(param: &syn::GenericParam) -> String {
+    match param {
+        syn::GenericParam::Type(tp) => tp.ident.to_string(),
+        syn::GenericParam::Lifetime(lp) => lp.lifetime.to_string(),
+        syn::GenericParam::Const(cp) => cp.ident.to_string(),
+    }
+}
+
+/// `impl Default for Box<dyn Trait<..>>` building the `#[default]` variant,
+/// at that variant's index
+pub fn generate_default_box(
+    variant: &ParsedVariant,
+    impl_generics: &Generics,
+    variant_ty_generics: &TokenStream2,
+    trait_type: &TokenStream2,
+    lint_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let box_ty = box_path();
+    let variant_name = &variant.ident;
+    let lifetimes: Vec<_> = impl_generics.lifetimes().map(|l| &l.lifetime).collect();
+    let object_bound = match lifetimes.as_slice() {
+        [] => TokenStream2::new(),
+        [lifetime] => quote! { + #lifetime },
+        _ => return TokenStream2::new(),
+    };
+    let mut impl_generics = impl_generics.clone();
+    impl_generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(
+            #variant_name #variant_ty_generics: ::core::default::Default
+        ));
+    let (impl_generics, _, where_clause) = impl_generics.split_for_impl();
+
+    quote! {
+        #[automatically_derived]
+        #lint_attrs
+        impl #impl_generics ::core::default::Default for #box_ty<dyn #trait_type #object_bound>
+            #where_clause
+        {
+            fn default() -> Self {
+                #box_ty::new(<#variant_name #variant_ty_generics as ::core::default::Default>::default())
+            }
+        }
+    }
+}
+
+/// `impl TryFrom<Box<dyn Trait<..>>> for Variant`, taking the variant out of
+/// its trait object and handing the box back when it holds another
+pub fn generate_try_from_box(
+    variant: &ParsedVariant,
+    impl_generics: &Generics,
+    variant_ty_generics: &TokenStream2,
+    trait_type: &TokenStream2,
+    lint_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let box_ty = box_path();
+    let variant_name = &variant.ident;
+    let (impl_generics, _, where_clause) = impl_generics.split_for_impl();
+
+    quote! {
+        #[automatically_derived]
+        #lint_attrs
+        impl #impl_generics ::core::convert::TryFrom<#box_ty<dyn #trait_type>>
+            for #variant_name #variant_ty_generics #where_clause
+        {
+            type Error = #box_ty<dyn #trait_type>;
+
+            fn try_from(value: #box_ty<dyn #trait_type>) -> ::core::result::Result<Self, Self::Error> {
+                if !<dyn ::core::any::Any>::is::<Self>(&*value) {
+                    return ::core::result::Result::Err(value);
+                }
+                let any: #box_ty<dyn ::core::any::Any> = value;
+                ::core::result::Result::Ok(
+                    *any.downcast::<Self>().expect("checked with `is` above"),
+                )
+            }
+        }
+    }
+}
+
+/// Getters for tuple fields named as `Rect(width: f64, height: f64)`
+pub fn generate_positional_getters(
+    variant: &ParsedVariant,
+    struct_generics: &Generics,
+    vis: &Visibility,
+    lint_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let variant_name = &variant.ident;
+    let getters: Vec<_> = variant
+        .positional_names
+        .iter()
+        .zip(&variant.fields)
+        .zip(field_members(&variant.fields))
+        .filter_map(|((name, field), member)| {
+            let name = name.as_ref()?;
+            let ty = &field.ty;
+            let doc = format!("The `{}` field of `{}`", name, variant_name);
+            Some(quote! {
+                #[doc = #doc]
+                #vis fn #name(&self) -> &#ty {
+                    &self.#member
+                }
+            })
+        })
+        .collect();
+    if getters.is_empty() {
+        return TokenStream2::new();
+    }
+
+    let (impl_generics, ty_generics, where_clause) = struct_generics.split_for_impl();
+    quote! {
+        #lint_attrs
+        impl #impl_generics #variant_name #ty_generics #where_clause {
+            #(#getters)*
+        }
+    }
+}
+
+/// `mod shape { pub fn circle(arg0: f64) -> Box<dyn Shape> { .. } }` for
+/// `#[type_enum(constructors)]`, one function per variant returning it boxed
+/// at its index. The module reaches the enum's items with `use super::*`.
+pub fn generate_constructor_module(
+    parsed: &ParsedEnum,
+    module: &Ident,
+    generics_with_static: &Generics,
+    all_type_params: &HashSet<String>,
+    lint_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let box_ty = box_path();
+    let enum_name = &parsed.ident;
+    let const_params = collect_const_param_names(generics_with_static);
+
+    let functions = parsed.variants.iter().map(|variant| {
+        let variant_name = &variant.ident;
+        let struct_generics =
+            variant_struct_generics(variant, generics_with_static, all_type_params);
+        let (_, struct_ty_generics, _) = struct_generics.split_for_impl();
+        let trait_type = resolve_trait_type(variant, enum_name, generics_with_static);
+
+        // The struct's generics, and the enum parameters its index names
+        let mut used = collect_all_type_param_names(&struct_generics);
+        used.extend(struct_generics.lifetimes().map(|l| l.lifetime.to_string()));
+        used.extend(extract_type_params_from_trait(&trait_type, all_type_params));
+        used.extend(extract_type_params_from_trait(&trait_type, &const_params));
+        used.extend(collect_lifetime_names(trait_type.clone()));
+        let mut fn_generics = merge_generics(
+            &add_static_bounds(&variant.generics),
+            generics_with_static,
+            &used,
+        );
+        // Generic variants implement the trait only where derived traits'
+        // bounds on their fields hold
+        if struct_generics.type_params().next().is_some() {
+            fn_generics
+                .make_where_clause()
+                .predicates
+                .push(syn::parse_quote!(#variant_name #struct_ty_generics: #trait_type));
+        }
+        let lifetimes: Vec<_> = fn_generics.lifetimes().map(|l| &l.lifetime).collect();
+        let object_bound = match lifetimes.as_slice() {
+            [] => TokenStream2::new(),
+            [lifetime] => quote! { + #lifetime },
+            _ => return TokenStream2::new(),
+        };
+        let (fn_impl_generics, _, where_clause) = fn_generics.split_for_impl();
+
+        let members = field_members(&variant.fields);
+        let (args, params): (Vec<_>, Vec<_>) = variant
+            .fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| {
+                let positional_name = variant.positional_names.get(index).and_then(Option::as_ref);
+                let arg = match field.ident.as_ref().or(positional_name) {
+                    Some(ident) => ident.clone(),
+                    None => format_ident!("arg{}", index),
+                };
+                let ty = &field.ty;
+                (arg.clone(), quote! { #arg: #ty })
+            })
+            .unzip();
+
+        let function = snake_case_ident(&variant.name);
+        let cfgs = cfg_attrs(&variant.attrs);
+        let deprecated = deprecation_attrs(&variant.attrs);
+        let doc = format!("Build a boxed [`{}`]", variant_name);
+        quote! {
+            #(#cfgs)*
+            #[doc = #doc]
+            #(#deprecated)*
+            pub fn #function #fn_impl_generics(#(#params),*) -> #box_ty<dyn #trait_type #object_bound>
+                #where_clause
+            {
+                #box_ty::new(#variant_name { #(#members: #args),* })
+            }
+        }
+    });
+
+    let vis = &parsed.vis;
+    let doc = format!("Boxing constructors for the variants of [`{}`]", enum_name);
+    quote! {
+        #[doc = #doc]
+        #lint_attrs
+        #vis mod #module {
+            use super::*;
+
+            #(#functions)*
+        }
+    }
+}
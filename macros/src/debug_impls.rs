@@ -0,0 +1,42 @@
+//! `#[type_enum(debug)]`: `Debug` for every variant struct, reachable from
+//! trait objects through a `debug_fmt` trait method
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Generics, Ident};
+
+/// The `debug_fmt` declaration on the generated trait
+pub fn generate_debug_decl() -> TokenStream2 {
+    quote! {
+        /// Format the variant with its `Debug` impl, for trait objects
+        fn debug_fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result;
+    }
+}
+
+/// `debug_fmt` for one variant, forwarding to its derived `Debug`
+pub fn generate_debug_hook_impl() -> TokenStream2 {
+    quote! {
+        fn debug_fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+            ::core::fmt::Debug::fmt(self, f)
+        }
+    }
+}
+
+/// `impl Debug for dyn Enum<..>`, so boxed and borrowed trait objects print
+/// like the variant they hold
+pub fn generate_dyn_debug(
+    enum_name: &Ident,
+    generics_with_static: &Generics,
+    lint_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let (impl_generics, ty_generics, where_clause) = generics_with_static.split_for_impl();
+    quote! {
+        #[automatically_derived]
+        #lint_attrs
+        impl #impl_generics ::core::fmt::Debug for dyn #enum_name #ty_generics + '_ #where_clause {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                self.debug_fmt(f)
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+//! `Display` for trait objects, wired to an `impl Display { .. }` section
+//! through a `display_fmt` trait method, or to a `fn fmt` method of the enum
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Generics, Ident, Path, TypeParamBound};
+
+use crate::enum_parser::ParsedEnum;
+
+/// What `impl Display for dyn Enum<..>` forwards to
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DisplaySource {
+    /// An `impl Display { .. }` section, reached through `display_fmt`
+    Section,
+    /// A `fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result` method
+    Method,
+}
+
+fn is_display(path: &Path) -> bool {
+    path.segments.last().is_some_and(|s| s.ident == "Display")
+}
+
+/// Where trait objects get `Display` from, if anywhere. A `Display`
+/// supertrait already makes them `Display`, and a trait named with
+/// `#[implements(...)]` isn't ours to extend.
+pub fn display_source(parsed: &ParsedEnum) -> Option<DisplaySource> {
+    let display_supertrait = parsed
+        .supertraits
+        .iter()
+        .any(|bound| matches!(bound, TypeParamBound::Trait(bound) if is_display(&bound.path)));
+    if parsed.options.implements.is_some() || display_supertrait {
+        return None;
+    }
+    if parsed
+        .impls
+        .iter()
+        .any(|section| is_display(&section.trait_path))
+    {
+        return Some(DisplaySource::Section);
+    }
+    parsed
+        .methods
+        .iter()
+        .any(|method| {
+            method.sig.ident == "fmt"
+                && method.sig.inputs.len() == 2
+                && method
+                    .sig
+                    .receiver()
+                    .is_some_and(|r| r.reference.is_some() && r.mutability.is_none())
+        })
+        .then_some(DisplaySource::Method)
+}
+
+/// The `display_fmt` declaration on the generated trait
+pub fn generate_display_decl() -> TokenStream2 {
+    quote! {
+        /// Format the variant with its `Display` impl, for trait objects
+        fn display_fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result;
+    }
+}
+
+/// `display_fmt` for one variant, forwarding to its `impl Display` section
+pub fn generate_display_hook_impl() -> TokenStream2 {
+    quote! {
+        fn display_fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+            ::core::fmt::Display::fmt(self, f)
+        }
+    }
+}
+
+/// `impl Display for dyn Enum<..>`. Boxes, references and `Rc`s of trait
+/// objects are then `Display` through std's impls for them.
+pub fn generate_dyn_display(
+    source: DisplaySource,
+    enum_name: &Ident,
+    generics_with_static: &Generics,
+    lint_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let (impl_generics, ty_generics, where_clause) = generics_with_static.split_for_impl();
+    let body = match source {
+        DisplaySource::Section => quote! { self.display_fmt(f) },
+        DisplaySource::Method => quote! { <Self as #enum_name #ty_generics>::fmt(self, f) },
+    };
+    quote! {
+        #[automatically_derived]
+        #lint_attrs
+        impl #impl_generics ::core::fmt::Display for dyn #enum_name #ty_generics + '_ #where_clause {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                #body
+            }
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! Checked downcasts from trait objects to single variant structs:
+//! `is_circle()`, `as_circle()` and `as_circle_mut()` on `dyn Shape`
+//!
+//! Trait objects of borrowing enums aren't `Any`. Their trait carries two
+//! hidden view hooks per variant instead, handing out `&Variant` and
+//! `&mut Variant` when the object holds it, which the downcasts and
+//! `match_t!` go through.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, ToTokens};
+use std::collections::HashSet;
+use syn::{Generics, Ident, Type};
+
+use crate::enum_parser::{ParsedEnum, ParsedVariant};
+use crate::erasure::{mentions, own_param_mapping};
+use crate::helpers::{
+    cfg_attrs, deprecation_attrs, snake_case, substitute_in_type, substitute_type_params,
+};
+use crate::variant_gen::{resolve_trait_type, variant_struct_generics};
+
+/// A variant of a borrowing enum that trait objects can be viewed as
+pub struct ViewedVariant {
+    pub ident: Ident,
+    /// The variant's struct in terms of the trait's own parameters, e.g.
+    /// `Borrowed<'a, T>`
+    pub ty: Type,
+}
+
+/// Name of the hook returning `Option<&Variant>`
+pub fn view_hook_ident(variant: &Ident) -> Ident {
+    format_ident!("__enum_typer_view_{}", variant)
+}
+
+/// Name of the hook returning `Option<&mut Variant>`
+pub fn view_mut_hook_ident(variant: &Ident) -> Ident {
+    format_ident!("__enum_typer_view_mut_{}", variant)
+}
+
+/// The variants of a borrowing enum its trait objects can be viewed as.
+/// Empty for enums whose objects are `Any`. Variants with generics of their
+/// own, and ones whose struct uses an enum parameter their trait type doesn't
+/// pass through as-is, have no one struct type the trait can name.
+pub fn view_variants(
+    parsed: &ParsedEnum,
+    generics_with_static: &Generics,
+    all_type_params: &HashSet<String>,
+) -> Vec<ViewedVariant> {
+    if parsed.options.implements.is_some() || parsed.generics.lifetimes().next().is_none() {
+        return Vec::new();
+    }
+    parsed
+        .variants
+        .iter()
+        .filter(|variant| variant.generics.params.is_empty())
+        .filter_map(|variant| view_variant(variant, parsed, generics_with_static, all_type_params))
+        .collect()
+}
+
+fn view_variant(
+    variant: &ParsedVariant,
+    parsed: &ParsedEnum,
+    generics_with_static: &Generics,
+    all_type_params: &HashSet<String>,
+) -> Option<ViewedVariant> {
+    let trait_type = resolve_trait_type(variant, &parsed.ident, generics_with_static);
+    let (mapping, unmapped) = own_param_mapping(&trait_type, generics_with_static);
+    let struct_generics = variant_struct_generics(variant, generics_with_static, all_type_params);
+    let (_, struct_ty_generics, _) = struct_generics.split_for_impl();
+    let name = &variant.ident;
+    let ty: Type = syn::parse_quote!(#name #struct_ty_generics);
+    if mentions(ty.to_token_stream(), &unmapped) {
+        return None;
+    }
+    Some(ViewedVariant {
+        ident: variant.ident.clone(),
+        ty: substitute_in_type(&ty, &mapping),
+    })
+}
+
+/// Hook signatures in terms of the trait's own parameters
+fn view_signatures(view: &ViewedVariant) -> (syn::Signature, syn::Signature) {
+    let view_hook = view_hook_ident(&view.ident);
+    let view_mut_hook = view_mut_hook_ident(&view.ident);
+    let ty = &view.ty;
+    (
+        syn::parse_quote! {
+            fn #view_hook(&self) -> ::core::option::Option<&#ty>
+        },
+        syn::parse_quote! {
+            fn #view_mut_hook(&mut self) -> ::core::option::Option<&mut #ty>
+        },
+    )
+}
+
+/// Declarations of the hidden view hooks on the generated trait
+pub fn generate_view_decls(views: &[ViewedVariant]) -> TokenStream2 {
+    let decls = views.iter().map(|view| {
+        let (view_sig, view_mut_sig) = view_signatures(view);
+        quote! {
+            #[doc(hidden)]
+            #[allow(non_snake_case)]
+            #view_sig;
+            #[doc(hidden)]
+            #[allow(non_snake_case)]
+            #view_mut_sig;
+        }
+    });
+    quote! { #(#decls)* }
+}
+
+/// Implementations of every view hook for one variant: its own hooks hand
+/// out the variant, the hooks of other variants decline
+pub fn generate_view_impls(
+    variant: &ParsedVariant,
+    views: &[ViewedVariant],
+    trait_type: &TokenStream2,
+    all_type_params_ordered: &[String],
+) -> Vec<TokenStream2> {
+    views
+        .iter()
+        .map(|view| {
+            let (view_sig, view_mut_sig) = view_signatures(view);
+            let view_sig = substitute_type_params(&view_sig, trait_type, all_type_params_ordered);
+            let view_mut_sig =
+                substitute_type_params(&view_mut_sig, trait_type, all_type_params_ordered);
+            let body = if view.ident == variant.ident {
+                quote! { ::core::option::Option::Some(self) }
+            } else {
+                quote! { ::core::option::Option::None }
+            };
+            quote! {
+                #[allow(non_snake_case)]
+                #view_sig { #body }
+                #[allow(non_snake_case)]
+                #view_mut_sig { #body }
+            }
+        })
+        .collect()
+}
+
+/// `impl dyn Enum<..>` with a check and two borrowing downcasts per variant.
+/// Variants with generics of their own have no one struct type to downcast
+/// to, so they get none, and neither do names an enum method already takes.
+/// The downcasts of borrowing enums go through their view hooks.
+pub fn generate_downcast_helpers(
+    parsed: &ParsedEnum,
+    generics_with_static: &Generics,
+    all_type_params: &HashSet<String>,
+    views: &[ViewedVariant],
+    lint_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let borrows = parsed.generics.lifetimes().next().is_some();
+    let enum_name = &parsed.ident;
+    let method_names: HashSet<String> = parsed
+        .methods
+        .iter()
+        .map(|m| m.sig.ident.to_string())
+        .collect();
+
+    let helpers = parsed
+        .variants
+        .iter()
+        .filter(|variant| variant.generics.params.is_empty())
+        .filter_map(|variant| {
+            let name = &variant.ident;
+            let snake = snake_case(&variant.name);
+            let is = format_ident!("is_{}", snake);
+            let as_ref = format_ident!("as_{}", snake);
+            let as_mut = format_ident!("as_{}_mut", snake);
+            if [&is, &as_ref, &as_mut]
+                .iter()
+                .any(|ident| method_names.contains(&ident.to_string()))
+            {
+                return None;
+            }
+
+            let (ty, is_body, as_ref_body, as_mut_body) = if borrows {
+                let view = views.iter().find(|view| view.ident == *name)?;
+                let view_hook = view_hook_ident(name);
+                let view_mut_hook = view_mut_hook_ident(name);
+                (
+                    view.ty.to_token_stream(),
+                    quote! { self.#view_hook().is_some() },
+                    quote! { self.#view_hook() },
+                    quote! { self.#view_mut_hook() },
+                )
+            } else {
+                let struct_generics =
+                    variant_struct_generics(variant, generics_with_static, all_type_params);
+                let (_, struct_ty_generics, _) = struct_generics.split_for_impl();
+                let ty = quote! { #name #struct_ty_generics };
+                (
+                    ty.clone(),
+                    quote! { <dyn ::core::any::Any>::is::<#ty>(self) },
+                    quote! { <dyn ::core::any::Any>::downcast_ref::<#ty>(self) },
+                    quote! { <dyn ::core::any::Any>::downcast_mut::<#ty>(self) },
+                )
+            };
+            let cfgs = cfg_attrs(&variant.attrs);
+            let deprecated = deprecation_attrs(&variant.attrs);
+            let is_doc = format!("Whether this is a [`{}`]", name);
+            let as_doc = format!("This as a [`{}`], if it is one", name);
+            Some(quote! {
+                #(#cfgs)*
+                #[doc = #is_doc]
+                #(#deprecated)*
+                pub fn #is(&self) -> bool {
+                    #is_body
+                }
+
+                #(#cfgs)*
+                #[doc = #as_doc]
+                #(#deprecated)*
+                pub fn #as_ref(&self) -> ::core::option::Option<&#ty> {
+                    #as_ref_body
+                }
+
+                #(#cfgs)*
+                #[doc = #as_doc]
+                #(#deprecated)*
+                pub fn #as_mut(&mut self) -> ::core::option::Option<&mut #ty> {
+                    #as_mut_body
+                }
+            })
+        });
+
+    // Objects of borrowing enums live no longer than what they borrow
+    let object_bound = borrows.then(|| quote! { + '_ });
+    let (impl_generics, ty_generics, where_clause) = generics_with_static.split_for_impl();
+    quote! {
+        #lint_attrs
+        impl #impl_generics dyn #enum_name #ty_generics #object_bound #where_clause {
+            #(#helpers)*
+        }
+    }
+}
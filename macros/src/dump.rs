@@ -0,0 +1,62 @@
+//! Expansion snapshots for debugging (`dump` feature)
+//!
+//! With the feature enabled, every `type_enum!` and `match_t!` expansion is
+//! pretty-printed into `$ENUM_TYPER_DUMP_DIR`, or `$OUT_DIR/enum_typer` for
+//! crates with a build script. Without either variable nothing is written.
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+
+/// Write an expansion named `name`, e.g. `type_enum-Expr`. `at` is a span of
+/// the user's input, whose place in the source names the file.
+#[cfg(feature = "dump")]
+pub fn dump_expansion(name: &str, at: Span, tokens: &TokenStream2) {
+    use std::path::{Path, PathBuf};
+
+    let dir = match (
+        std::env::var_os("ENUM_TYPER_DUMP_DIR"),
+        std::env::var_os("OUT_DIR"),
+    ) {
+        (Some(dir), _) => PathBuf::from(dir),
+        (None, Some(out_dir)) => PathBuf::from(out_dir).join("enum_typer"),
+        (None, None) => return,
+    };
+
+    // Invocations are told apart by where they are written rather than the
+    // order they expand in, which changes between builds
+    let span = at.unwrap();
+    let file = span.file();
+    let source_path: String = Path::new(&file)
+        .with_extension("")
+        .to_string_lossy()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' {
+                c
+            } else {
+                '-'
+            }
+        })
+        .collect();
+    let crate_name = std::env::var("CARGO_CRATE_NAME").unwrap_or_else(|_| "crate".to_string());
+    let file_name = format!(
+        "{}-{}-{}-{}-{}.rs",
+        crate_name,
+        name,
+        source_path.trim_matches('-'),
+        span.line(),
+        span.column()
+    );
+
+    // Expressions (from `match_t!`) are wrapped in a function to get a file
+    let source = syn::parse2::<syn::File>(tokens.clone())
+        .or_else(|_| syn::parse2::<syn::File>(quote::quote! { fn __expansion() { #tokens } }))
+        .map(|file| prettyplease::unparse(&file))
+        .unwrap_or_else(|_| tokens.to_string());
+
+    // Snapshots are best effort and never fail the build
+    let _ = std::fs::create_dir_all(&dir);
+    let _ = std::fs::write(dir.join(file_name), source);
+}
+
+#[cfg(not(feature = "dump"))]
+pub fn dump_expansion(_name: &str, _at: Span, _tokens: &TokenStream2) {}
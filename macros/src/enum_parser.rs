@@ -0,0 +1,661 @@
+//! Custom enum parser for tagless final style syntax
+
+use proc_macro2::{Ident, TokenStream as TokenStream2, TokenTree};
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    visit_mut::{self, VisitMut},
+    Attribute, Block, Expr, Field, FieldMutability, Fields, FieldsUnnamed, Generics, LitStr, Path,
+    PathArguments, Signature, Token, Type, TypeParamBound, Visibility, WhereClause, WherePredicate,
+};
+
+use crate::async_methods::{desugar_async_signature, require_send_future};
+use crate::codegen::{allocates, box_path};
+use crate::helpers::{
+    add_send_sync_bounds, bind_index_generics, missing_send_sync, requires_sized,
+};
+use crate::options::EnumOptions;
+use crate::pattern_parser::{parse_arm_body, parse_arm_pattern, split_arm_guard};
+use crate::validation::rename_arm_head;
+
+/// Parsed variant with optional trait type constraint
+pub struct ParsedVariant {
+    pub attrs: Vec<Attribute>,
+    /// Name of the variant struct
+    pub ident: Ident,
+    /// Name the variant is declared with, which arms, `variant_name()` and
+    /// the other generated names use; differs from `ident` with
+    /// `#[type_enum(rename_variants = "..")]`
+    pub name: Ident,
+    pub generics: Generics,
+    pub fields: Fields,
+    /// Names given to tuple fields as `Rect(width: f64, height: f64)`, one
+    /// per field; they become getters while the struct stays a tuple struct
+    pub positional_names: Vec<Option<Ident>>,
+    pub trait_type: Option<TokenStream2>,
+}
+
+/// A single method arm (pattern if guard => body)
+#[derive(Clone)]
+pub struct MethodArm {
+    pub pattern: TokenStream2,
+    pub guard: Option<Expr>,
+    pub body: Expr,
+}
+
+/// Parsed method with signature and pattern/body arms
+#[derive(Clone)]
+pub struct ParsedMethod {
+    pub attrs: Vec<Attribute>,
+    /// The signature, with `async fn` already desugared to a boxed future
+    pub sig: Signature,
+    /// Whether the method was written as `async fn`
+    pub is_async: bool,
+    /// Body of a `default fn`, provided on the trait for variants without an
+    /// arm of their own
+    pub default_body: Option<Block>,
+    pub arms: Vec<MethodArm>,
+    /// Whether the method is generic and was kept out of the vtable without
+    /// saying so, which leaves it uncallable on trait objects
+    pub implicitly_sized: bool,
+}
+
+/// An `impl Trait { .. }` section implementing an existing trait for every
+/// variant
+pub struct ParsedImpl {
+    pub attrs: Vec<Attribute>,
+    pub trait_path: Path,
+    pub methods: Vec<ParsedMethod>,
+}
+
+/// Formatting traits, whose single method `impl Display { .. }` sections may
+/// leave out, writing the arms directly with the formatter bound to `f`
+const FORMAT_TRAITS: &[&str] = &[
+    "Display", "Debug", "LowerHex", "UpperHex", "Octal", "Binary", "LowerExp", "UpperExp",
+    "Pointer",
+];
+
+pub struct ParsedEnum {
+    #[allow(dead_code)]
+    pub attrs: Vec<Attribute>,
+    pub options: EnumOptions,
+    pub vis: Visibility,
+    pub ident: Ident,
+    pub generics: Generics,
+    /// Supertraits of the generated trait besides `Any`, from `enum E: Debug`
+    pub supertraits: Vec<TypeParamBound>,
+    pub variants: Vec<ParsedVariant>,
+    pub methods: Vec<ParsedMethod>,
+    /// `impl Trait { .. }` sections, implemented for every variant
+    pub impls: Vec<ParsedImpl>,
+}
+
+impl Parse for ParsedEnum {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let attrs = input.call(Attribute::parse_outer)?;
+        let options = EnumOptions::from_attrs(&attrs)?;
+        let vis = input.parse()?;
+
+        // Accept either 'enum' or 'trait' keyword
+        let lookahead = input.lookahead1();
+        if lookahead.peek(Token![enum]) {
+            input.parse::<Token![enum]>()?;
+        } else if lookahead.peek(Token![trait]) {
+            input.parse::<Token![trait]>()?;
+        } else {
+            return Err(lookahead.error());
+        }
+
+        let ident: Ident = input.parse()?;
+        let mut generics: Generics = input.parse()?;
+        let mut supertraits = Vec::new();
+        if let Some(colon) = input.parse::<Option<Token![:]>>()? {
+            loop {
+                supertraits.push(input.parse::<TypeParamBound>()?);
+                if input.parse::<Option<Token![+]>>()?.is_none() {
+                    break;
+                }
+            }
+            if options.implements.is_some() {
+                return Err(syn::Error::new(
+                    colon.span,
+                    "supertraits cannot be added to a trait named with `#[implements(...)]`",
+                ));
+            }
+        }
+        generics.where_clause = input.parse()?;
+        if options.send_sync {
+            add_send_sync_bounds(&mut generics);
+            let missing = missing_send_sync(&supertraits);
+            supertraits.extend(missing);
+        }
+
+        let content;
+        syn::braced!(content in input);
+
+        let mut variants = Vec::new();
+
+        while !content.is_empty() {
+            let variant_attrs = content.call(Attribute::parse_outer)?;
+            let variant_ident: Ident = content.parse()?;
+
+            // Parse variant-level generics (e.g., A<T>, B<U: Trait>)
+            let mut variant_generics: Generics = content.parse()?;
+
+            // Parse fields
+            let mut positional_names = Vec::new();
+            let mut fields = if content.peek(syn::token::Brace) {
+                Fields::Named(content.parse()?)
+            } else if content.peek(syn::token::Paren) {
+                let (fields, names) = parse_positional_fields(&content)?;
+                positional_names = names;
+                Fields::Unnamed(fields)
+            } else {
+                Fields::Unit
+            };
+
+            // Variant where clause, ending before `: Type` or the next variant
+            if content.peek(Token![where]) {
+                variant_generics.where_clause =
+                    Some(parse_variant_where_clause(&content, &variant_generics)?);
+            }
+
+            // Check for trait type constraint (: Type)
+            let trait_type = if content.peek(Token![:]) {
+                content.parse::<Token![:]>()?;
+
+                // Parse everything until comma or end, respecting angle brackets
+                let mut type_tokens = Vec::new();
+                let mut angle_depth: i32 = 0;
+                while !content.is_empty() {
+                    // Check if we're at a comma at depth 0
+                    if angle_depth == 0 && content.peek(Token![,]) {
+                        break;
+                    }
+
+                    let token = content.parse::<TokenTree>()?;
+
+                    // Track angle bracket depth
+                    if let TokenTree::Punct(ref punct) = token {
+                        match punct.as_char() {
+                            '<' => angle_depth += 1,
+                            '>' => angle_depth = angle_depth.saturating_sub(1),
+                            _ => {}
+                        }
+                    }
+
+                    type_tokens.push(token);
+                }
+
+                Some(type_tokens.into_iter().collect::<TokenStream2>())
+            } else {
+                None
+            };
+
+            if options.send_sync {
+                add_send_sync_bounds(&mut variant_generics);
+            }
+
+            let own_trait = match &trait_type {
+                Some(trait_type) => trait_type.clone(),
+                None => {
+                    let (_, ty_generics, _) = generics.split_for_impl();
+                    quote! { #ident #ty_generics }
+                }
+            };
+            expand_self_fields(&mut fields, &ident, &own_trait)?;
+
+            variants.push(ParsedVariant {
+                attrs: variant_attrs,
+                name: variant_ident.clone(),
+                ident: variant_ident,
+                generics: variant_generics,
+                fields,
+                positional_names,
+                trait_type,
+            });
+
+            // Optional trailing comma
+            if !content.is_empty() {
+                content.parse::<Token![,]>()?;
+            }
+        }
+
+        // Now parse method definitions and impl sections (if present) from
+        // remaining input
+        let mut methods = Vec::new();
+        let mut impls = Vec::new();
+        while !input.is_empty() {
+            if starts_impl_section(input) {
+                impls.push(parse_impl_section(input)?);
+                continue;
+            }
+            let mut method = parse_method(input)?;
+            if method.is_async && options.send_sync {
+                require_send_future(&mut method.sig);
+            }
+            if method.default_body.is_some() && options.implements.is_some() {
+                return Err(syn::Error::new_spanned(
+                    method.sig.fn_token,
+                    "`default fn` provides a method on the generated trait, which \
+                     `#[implements(...)]` replaces; give every variant an arm instead",
+                ));
+            }
+            // Functions without `self` are called on the variant structs, as
+            // `Num::arity()`, and stay out of the vtable
+            if method.sig.receiver().is_none() && !requires_sized(&method.sig) {
+                method
+                    .sig
+                    .generics
+                    .make_where_clause()
+                    .predicates
+                    .push(syn::parse_quote!(Self: Sized));
+            }
+            let sized = bind_index_generics(&mut method.sig, &ident, &generics);
+            method.implicitly_sized = sized && method.sig.receiver().is_some();
+            methods.push(method);
+        }
+
+        if let Some(format) = &options.rename_variants {
+            rename_variants(format, &mut variants, &mut methods, &mut impls)?;
+        }
+
+        Ok(ParsedEnum {
+            attrs,
+            options,
+            vis,
+            ident,
+            generics,
+            supertraits,
+            variants,
+            methods,
+            impls,
+        })
+    }
+}
+
+/// Name the variant structs after `format`, and the arms naming them by
+/// their declared names after the structs
+fn rename_variants(
+    format: &LitStr,
+    variants: &mut [ParsedVariant],
+    methods: &mut [ParsedMethod],
+    impls: &mut [ParsedImpl],
+) -> syn::Result<()> {
+    for variant in variants.iter_mut() {
+        let renamed = format.value().replace("{}", &variant.name.to_string());
+        let ident = syn::parse_str::<Ident>(&renamed).map_err(|_| {
+            syn::Error::new_spanned(
+                format,
+                format!(
+                    "`{}` is not a valid name for the `{}` struct",
+                    renamed, variant.name
+                ),
+            )
+        })?;
+        variant.ident = Ident::new(&ident.to_string(), variant.name.span());
+    }
+
+    let section_methods = impls.iter_mut().flat_map(|section| &mut section.methods);
+    for method in methods.iter_mut().chain(section_methods) {
+        for arm in &mut method.arms {
+            arm.pattern = rename_arm_head(&arm.pattern, variants);
+        }
+    }
+    Ok(())
+}
+
+/// Check that a method signature has a receiver the generated impls can
+/// dispatch on. Functions without one are fine once kept out of the vtable.
+fn validate_signature(sig: &Signature) -> syn::Result<()> {
+    if sig.asyncness.is_some() && !allocates() {
+        return Err(syn::Error::new_spanned(
+            sig.asyncness,
+            "async methods return boxed futures, which need the `alloc` feature of enum-typer",
+        ));
+    }
+    let Some(receiver) = sig.receiver() else {
+        if sig.asyncness.is_some() {
+            return Err(syn::Error::new_spanned(
+                sig.asyncness,
+                format!("async function `{}` needs a `self` receiver", sig.ident),
+            ));
+        }
+        return Ok(());
+    };
+
+    let by_value = receiver.reference.is_none()
+        && matches!(&*receiver.ty, Type::Path(p) if p.path.is_ident("Self"));
+    // By-value receivers are fine once the method is kept out of the vtable
+    if by_value && !requires_sized(sig) {
+        return Err(syn::Error::new_spanned(
+            receiver,
+            "by-value `self` is not object safe; add `where Self: Sized` to call it on \
+             concrete variants only, or use `self: Box<Self>` instead",
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn parse_method(input: ParseStream) -> syn::Result<ParsedMethod> {
+    // Parse the method signature: fn name(...) -> ReturnType
+    let attrs = input.call(Attribute::parse_outer)?;
+    let default_token: Option<Token![default]> = input.parse()?;
+    let mut sig: Signature = input.parse()?;
+    validate_signature(&sig)?;
+    let is_async = sig.asyncness.is_some();
+    desugar_async_signature(&mut sig)?;
+
+    // A `default fn` may have a plain body instead of arms
+    if default_token.is_some() && !has_top_level_arrow(input) {
+        let block: Block = input.parse()?;
+        return Ok(ParsedMethod {
+            attrs,
+            sig,
+            is_async,
+            default_body: Some(block),
+            arms: Vec::new(),
+            implicitly_sized: false,
+        });
+    }
+
+    // Parse the method body (pattern => body pairs)
+    let content;
+    syn::braced!(content in input);
+    let mut arms = parse_method_arms(&content)?;
+
+    // The `_` arm of a `default fn` is the provided body; the other arms
+    // override it for their variants
+    let mut default_body = None;
+    if let Some(default_token) = default_token {
+        let position = arms.iter().position(|arm| {
+            arm.guard.is_none() && syn::parse2::<Token![_]>(arm.pattern.clone()).is_ok()
+        });
+        let Some(position) = position else {
+            return Err(syn::Error::new_spanned(
+                default_token,
+                format!(
+                    "`default fn {}` needs a `_ => ..` arm for the variants it does not list",
+                    sig.ident
+                ),
+            ));
+        };
+        let body = arms.remove(position).body;
+        default_body = Some(syn::parse_quote!({ #body }));
+    }
+
+    Ok(ParsedMethod {
+        attrs,
+        sig,
+        is_async,
+        default_body,
+        arms,
+        implicitly_sized: false,
+    })
+}
+
+/// `pattern if guard => body` arms up to the end of `input`
+fn parse_method_arms(input: ParseStream) -> syn::Result<Vec<MethodArm>> {
+    let mut arms = Vec::new();
+    while !input.is_empty() {
+        let (pattern, guard) = split_arm_guard(parse_arm_pattern(input)?)?;
+        input.parse::<Token![=>]>()?;
+        let body = parse_arm_body(input)?;
+
+        arms.push(MethodArm {
+            pattern,
+            guard,
+            body,
+        });
+    }
+    Ok(arms)
+}
+
+/// Whether `input` continues with `impl Trait { .. }` rather than a method
+fn starts_impl_section(input: ParseStream) -> bool {
+    let fork = input.fork();
+    fork.call(Attribute::parse_outer).is_ok() && fork.peek(Token![impl])
+}
+
+/// Parse `impl Trait { fn .. }`, or `impl Display { arms }` for the
+/// formatting traits
+fn parse_impl_section(input: ParseStream) -> syn::Result<ParsedImpl> {
+    let attrs = input.call(Attribute::parse_outer)?;
+    input.parse::<Token![impl]>()?;
+    let trait_path: Path = input.parse()?;
+    let content;
+    syn::braced!(content in input);
+
+    let starts_method = {
+        let fork = content.fork();
+        fork.call(Attribute::parse_outer)?;
+        fork.peek(Token![fn])
+            || fork.peek(Token![async])
+            || fork.peek(Token![default])
+            || fork.peek(Token![unsafe])
+            || fork.peek(Token![const])
+    };
+    if content.is_empty() || starts_method {
+        let mut methods = Vec::new();
+        while !content.is_empty() {
+            let method = parse_method(&content)?;
+            if method.default_body.is_some() {
+                return Err(syn::Error::new_spanned(
+                    method.sig.fn_token,
+                    "`default fn` provides a method on the generated trait; in an `impl` \
+                     section, use a `_ => ..` arm instead",
+                ));
+            }
+            methods.push(method);
+        }
+        return Ok(ParsedImpl {
+            attrs,
+            trait_path,
+            methods,
+        });
+    }
+
+    let is_format_trait = trait_path
+        .segments
+        .last()
+        .is_some_and(|segment| FORMAT_TRAITS.iter().any(|name| segment.ident == name));
+    if !is_format_trait {
+        return Err(syn::Error::new_spanned(
+            &trait_path,
+            "arms directly inside `impl` are only supported for formatting traits such as \
+             `Display`; write the trait's methods as `fn method(..) { arms }`",
+        ));
+    }
+    let sig: Signature = syn::parse_quote! {
+        fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result
+    };
+    Ok(ParsedImpl {
+        attrs,
+        trait_path,
+        methods: vec![ParsedMethod {
+            attrs: Vec::new(),
+            sig,
+            is_async: false,
+            default_body: None,
+            arms: parse_method_arms(&content)?,
+            implicitly_sized: false,
+        }],
+    })
+}
+
+/// Whether the braced group at the head of `input` holds `=>` at its top
+/// level, i.e. arms rather than a plain block
+fn has_top_level_arrow(input: ParseStream) -> bool {
+    let Ok(TokenTree::Group(group)) = input.fork().parse::<TokenTree>() else {
+        return false;
+    };
+    let scan = |content: ParseStream| -> syn::Result<bool> {
+        let mut found = false;
+        while !content.is_empty() {
+            found |= content.peek(Token![=>]);
+            content.parse::<TokenTree>()?;
+        }
+        Ok(found)
+    };
+    syn::parse::Parser::parse2(scan, group.stream()).unwrap_or(false)
+}
+
+/// Parse `where ..` after a variant's fields. Commas also separate variants,
+/// so a comma only continues the clause when it is followed by a bound on one
+/// of the variant's own parameters, as in `where U: Clone, 'a: 'b`.
+fn parse_variant_where_clause(
+    input: ParseStream,
+    variant_generics: &Generics,
+) -> syn::Result<WhereClause> {
+    let where_token: Token![where] = input.parse()?;
+    let mut predicates = Punctuated::new();
+    loop {
+        predicates.push_value(input.parse::<WherePredicate>()?);
+        if !input.peek(Token![,]) || !bounds_own_param(input, variant_generics) {
+            break;
+        }
+        predicates.push_punct(input.parse()?);
+    }
+    Ok(WhereClause {
+        where_token,
+        predicates,
+    })
+}
+
+/// Whether the tokens after the comma at the head of `input` start a bound on
+/// one of the variant's own parameters
+fn bounds_own_param(input: ParseStream, variant_generics: &Generics) -> bool {
+    let fork = input.fork();
+    if fork.parse::<Token![,]>().is_err() {
+        return false;
+    }
+    if let Ok(lifetime) = fork.parse::<syn::Lifetime>() {
+        return variant_generics
+            .lifetimes()
+            .any(|param| param.lifetime == lifetime);
+    }
+    let Ok(ident) = fork.parse::<Ident>() else {
+        return false;
+    };
+    fork.peek(Token![:])
+        && !fork.peek(Token![::])
+        && variant_generics
+            .type_params()
+            .any(|param| param.ident == ident)
+}
+
+/// Expands `Self` in field types to the boxed trait object: `Self<i32>` to
+/// `Box<dyn Expr<i32>>`, and a bare `Self` to the variant's own index
+struct SelfExpansion<'a> {
+    enum_name: &'a Ident,
+    own_trait: &'a TokenStream2,
+    error: Option<syn::Error>,
+}
+
+impl VisitMut for SelfExpansion<'_> {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        visit_mut::visit_type_mut(self, ty);
+        let Type::Path(type_path) = ty else {
+            return;
+        };
+        if type_path.qself.is_some() || type_path.path.segments.len() != 1 {
+            return;
+        }
+        let segment = &type_path.path.segments[0];
+        if segment.ident != "Self" {
+            return;
+        }
+        let object = match &segment.arguments {
+            PathArguments::None => self.own_trait.clone(),
+            PathArguments::AngleBracketed(args) => {
+                let enum_name = self.enum_name;
+                quote! { #enum_name #args }
+            }
+            PathArguments::Parenthesized(args) => {
+                self.error.get_or_insert(syn::Error::new_spanned(
+                    args,
+                    "`Self` in a field stands for a boxed trait object and takes the \
+                     enum's arguments in angle brackets, as in `Self<i32>`",
+                ));
+                return;
+            }
+        };
+        if !allocates() {
+            self.error.get_or_insert(syn::Error::new_spanned(
+                &segment.ident,
+                "`Self` in a field stands for a boxed trait object, which needs the \
+                 `alloc` feature of enum-typer",
+            ));
+            return;
+        }
+        let box_ty = box_path();
+        *ty = syn::parse_quote!(#box_ty<dyn #object>);
+    }
+}
+
+/// Replace `Self` in a variant's field types with boxed trait objects
+fn expand_self_fields(
+    fields: &mut Fields,
+    enum_name: &Ident,
+    own_trait: &TokenStream2,
+) -> syn::Result<()> {
+    let mut expansion = SelfExpansion {
+        enum_name,
+        own_trait,
+        error: None,
+    };
+    for field in fields.iter_mut() {
+        expansion.visit_type_mut(&mut field.ty);
+    }
+    match expansion.error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Parse tuple fields, each optionally named as `width: f64`
+fn parse_positional_fields(input: ParseStream) -> syn::Result<(FieldsUnnamed, Vec<Option<Ident>>)> {
+    let content;
+    let paren_token = syn::parenthesized!(content in input);
+
+    let mut unnamed = Punctuated::new();
+    let mut names = Vec::new();
+    while !content.is_empty() {
+        let attrs = content.call(Attribute::parse_outer)?;
+        let vis: Visibility = content.parse()?;
+        let name =
+            if content.peek(syn::Ident) && content.peek2(Token![:]) && !content.peek2(Token![::]) {
+                let name: Ident = content.parse()?;
+                content.parse::<Token![:]>()?;
+                Some(name)
+            } else {
+                None
+            };
+        let ty: Type = content.parse()?;
+
+        unnamed.push(Field {
+            attrs,
+            vis,
+            mutability: FieldMutability::None,
+            ident: None,
+            colon_token: None,
+            ty,
+        });
+        names.push(name);
+
+        if content.is_empty() {
+            break;
+        }
+        unnamed.push_punct(content.parse()?);
+    }
+
+    Ok((
+        FieldsUnnamed {
+            paren_token,
+            unnamed,
+        },
+        names,
+    ))
+}
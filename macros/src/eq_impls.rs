@@ -0,0 +1,100 @@
+//! `eq_dyn`, and `PartialEq` for trait objects, generated when every variant
+//! struct derives `PartialEq`
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Generics, Ident};
+
+use crate::codegen::{allocates, box_path};
+use crate::enum_parser::ParsedEnum;
+use crate::helpers::{derives_trait, variant_derives};
+
+/// Whether trait objects can be compared: every variant is `PartialEq`, and
+/// the trait is generated and extends `Any`, so the other side can be
+/// downcast
+pub fn compares_dyn(parsed: &ParsedEnum) -> bool {
+    parsed.options.implements.is_none()
+        && parsed.generics.lifetimes().next().is_none()
+        && parsed
+            .variants
+            .iter()
+            .all(|variant| derives_trait(&variant_derives(variant, &parsed.options), "PartialEq"))
+}
+
+/// Whether trait objects are also `Eq`: every variant derives it
+pub fn derives_eq(parsed: &ParsedEnum) -> bool {
+    parsed
+        .variants
+        .iter()
+        .all(|variant| derives_trait(&variant_derives(variant, &parsed.options), "Eq"))
+}
+
+/// The `eq_dyn` declaration on the generated trait
+pub fn generate_eq_decl(enum_name: &Ident, generics_with_static: &Generics) -> TokenStream2 {
+    let (_, ty_generics, _) = generics_with_static.split_for_impl();
+    quote! {
+        /// Whether `other` is the same variant with equal fields
+        fn eq_dyn(&self, other: &dyn #enum_name #ty_generics) -> bool;
+    }
+}
+
+/// `eq_dyn` for one variant, downcasting `other` and comparing with the
+/// derived `PartialEq`
+pub fn generate_eq_hook_impl(trait_type: &TokenStream2) -> TokenStream2 {
+    quote! {
+        fn eq_dyn(&self, other: &dyn #trait_type) -> bool {
+            let other: &dyn ::core::any::Any = other;
+            other
+                .downcast_ref::<Self>()
+                .is_some_and(|other| ::core::cmp::PartialEq::eq(self, other))
+        }
+    }
+}
+
+/// `impl PartialEq for dyn Enum<..>`, and `Eq` when every variant derives it,
+/// which `Box<dyn Enum<..>>` picks up.
+/// Derived `PartialEq` on a struct with a boxed trait object field compares
+/// `&Box<dyn ..>` against `&&Box<dyn ..>` (rust-lang/rust#31740), so boxes
+/// are also comparable with references to boxes.
+pub fn generate_dyn_eq(
+    enum_name: &Ident,
+    generics_with_static: &Generics,
+    eq: bool,
+    lint_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let box_ty = box_path();
+    let (impl_generics, ty_generics, where_clause) = generics_with_static.split_for_impl();
+    let total = eq.then(|| {
+        quote! {
+            #[automatically_derived]
+            #lint_attrs
+            impl #impl_generics ::core::cmp::Eq for dyn #enum_name #ty_generics #where_clause {}
+        }
+    });
+    let box_eq = allocates().then(|| {
+        quote! {
+            #[automatically_derived]
+            #lint_attrs
+            impl #impl_generics ::core::cmp::PartialEq<&Self> for #box_ty<dyn #enum_name #ty_generics>
+                #where_clause
+            {
+                fn eq(&self, other: &&Self) -> bool {
+                    self.eq_dyn(&***other)
+                }
+            }
+        }
+    });
+    quote! {
+        #total
+
+        #[automatically_derived]
+        #lint_attrs
+        impl #impl_generics ::core::cmp::PartialEq for dyn #enum_name #ty_generics #where_clause {
+            fn eq(&self, other: &Self) -> bool {
+                self.eq_dyn(other)
+            }
+        }
+
+        #box_eq
+    }
+}
@@ -0,0 +1,324 @@
+//! Erased views of variants with their own generic parameters
+//!
+//! A variant like `Succ<N: Nat>(N)` cannot be downcast to without naming `N`.
+//! For such variants the generated trait carries two hidden hooks that hand out
+//! the fields with every variant-level generic erased to a trait object built
+//! from its bound: `&dyn Nat` by reference and `Box<dyn Nat>` by value.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, ToTokens};
+use std::collections::{HashMap, HashSet};
+use syn::{
+    Fields, Generics, Ident, Member, TraitBoundModifier, Type, TypeParamBound, WherePredicate,
+};
+
+use crate::codegen::{allocates, box_path};
+use crate::enum_parser::{ParsedEnum, ParsedVariant};
+use crate::helpers::{extract_trait_type_args, substitute_in_type, substitute_type_params};
+use crate::type_analysis::collect_all_type_param_names;
+use crate::variant_gen::{extract_type_params_from_trait, resolve_trait_type};
+
+/// A field as handed out by the hooks of an erased variant
+pub struct ErasedField {
+    pub member: Member,
+    /// Type returned by the by-reference hook, e.g. `&dyn Nat` or `&i32`
+    pub by_ref: Type,
+    /// Type returned by the by-value hook, e.g. `Box<dyn Nat>` or `i32`
+    pub owned: Type,
+    /// Whether the owned value has to be boxed to erase it
+    pub boxed: bool,
+}
+
+/// A variant whose fields can be handed out without naming its own generics
+pub struct ErasedVariant {
+    pub ident: Ident,
+    pub fields: Vec<ErasedField>,
+}
+
+/// Name of the hook returning `Option<(&F0, &F1, ...)>`
+pub fn ref_hook_ident(variant: &Ident) -> Ident {
+    format_ident!("__enum_typer_ref_{}", variant)
+}
+
+/// Name of the hook returning `Result<(F0, F1, ...), Box<dyn Trait>>`
+pub fn into_hook_ident(variant: &Ident) -> Ident {
+    format_ident!("__enum_typer_into_{}", variant)
+}
+
+pub fn mentions(tokens: TokenStream2, names: &HashSet<String>) -> bool {
+    !extract_type_params_from_trait(&tokens, names).is_empty()
+}
+
+/// Map the enum parameters a variant's trait type passes through unchanged to
+/// the trait's own parameter at that position, e.g. `T -> A` for
+/// `Pair<T, i32>` when the enum is `Pair<A, B>`. Also returns the enum
+/// parameters that cannot be mapped this way.
+pub fn own_param_mapping(
+    trait_type: &TokenStream2,
+    enum_generics: &Generics,
+) -> (HashMap<String, Type>, HashSet<String>) {
+    let enum_params: Vec<_> = enum_generics.type_params().map(|tp| &tp.ident).collect();
+    let enum_param_names: HashSet<String> = enum_params.iter().map(|i| i.to_string()).collect();
+    let mut mapping = HashMap::new();
+    for (position, arg) in extract_trait_type_args(trait_type).iter().enumerate() {
+        if let Type::Path(path) = arg {
+            if let (Some(ident), Some(own)) = (path.path.get_ident(), enum_params.get(position)) {
+                if enum_param_names.contains(&ident.to_string()) {
+                    mapping.insert(ident.to_string(), syn::parse_quote!(#own));
+                }
+            }
+        }
+    }
+    let unmapped = enum_param_names
+        .into_iter()
+        .filter(|name| !mapping.contains_key(name))
+        .collect();
+    (mapping, unmapped)
+}
+
+/// Whether a type is the bare type parameter `param`
+fn is_param(ty: &Type, param: &Ident) -> bool {
+    matches!(ty, Type::Path(path) if path.qself.is_none() && path.path.is_ident(param))
+}
+
+/// Whether a bound is `Send` or `Sync`, which trait objects can carry on
+/// top of their one trait
+fn is_auto_trait(bound: &TypeParamBound) -> bool {
+    matches!(bound, TypeParamBound::Trait(t)
+        if t.path.segments.last().is_some_and(|s| s.ident == "Send" || s.ident == "Sync"))
+}
+
+/// Work out the erased field types of a variant with variant-level generics.
+///
+/// Returns `None` when the variant has no generics of its own, or when some
+/// field cannot be expressed in terms of the trait's own parameters: a generic
+/// nested inside another type (`Box<U>`), a generic with several bounds
+/// besides `Send` and `Sync`, or an
+/// enum parameter that the variant's trait type does not pass through as-is.
+pub fn erase_variant(
+    variant: &ParsedVariant,
+    trait_type: &TokenStream2,
+    enum_generics: &Generics,
+) -> Option<ErasedVariant> {
+    let box_ty = box_path();
+    let variant_params = collect_all_type_param_names(&variant.generics);
+    if variant_params.is_empty() || matches!(variant.fields, Fields::Unit) {
+        return None;
+    }
+
+    let (mapping, unmapped) = own_param_mapping(trait_type, enum_generics);
+
+    let expressible = |tokens: TokenStream2| {
+        !mentions(tokens.clone(), &variant_params) && !mentions(tokens, &unmapped)
+    };
+
+    let mut fields = Vec::new();
+    for (index, field) in variant.fields.iter().enumerate() {
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(index.into()),
+        };
+
+        let generic_param = match &field.ty {
+            Type::Path(path) => path
+                .path
+                .get_ident()
+                .and_then(|ident| variant.generics.type_params().find(|tp| tp.ident == *ident)),
+            _ => None,
+        };
+
+        if let Some(param) = generic_param {
+            // Bounds written inline and in the variant's where clause alike
+            let where_bounds = variant
+                .generics
+                .where_clause
+                .iter()
+                .flat_map(|where_clause| &where_clause.predicates)
+                .filter_map(|predicate| match predicate {
+                    WherePredicate::Type(pt) if is_param(&pt.bounded_ty, &param.ident) => {
+                        Some(&pt.bounds)
+                    }
+                    _ => None,
+                })
+                .flatten();
+            let trait_bounds: Vec<_> = param
+                .bounds
+                .iter()
+                .chain(where_bounds)
+                .filter(|bound| {
+                    matches!(bound, TypeParamBound::Trait(t) if matches!(t.modifier, TraitBoundModifier::None))
+                })
+                .collect();
+            // `Send` and `Sync` ride along on any trait object
+            let (auto_traits, trait_bounds): (Vec<_>, Vec<_>) = trait_bounds
+                .into_iter()
+                .partition(|bound| is_auto_trait(bound));
+            let object: Type = match trait_bounds.as_slice() {
+                [] => syn::parse_quote!(dyn ::core::any::Any #(+ #auto_traits)*),
+                [bound] if expressible(bound.to_token_stream()) => {
+                    let bound = substitute_in_type(
+                        &syn::parse_quote!(dyn #bound #(+ #auto_traits)*),
+                        &mapping,
+                    );
+                    syn::parse_quote!(#bound)
+                }
+                _ => return None,
+            };
+            fields.push(ErasedField {
+                member,
+                by_ref: if auto_traits.is_empty() {
+                    syn::parse_quote!(&#object)
+                } else {
+                    syn::parse_quote!(&(#object))
+                },
+                owned: syn::parse_quote!(#box_ty<#object>),
+                boxed: true,
+            });
+        } else {
+            if !expressible(field.ty.to_token_stream()) {
+                return None;
+            }
+            let ty = substitute_in_type(&field.ty, &mapping);
+            fields.push(ErasedField {
+                member,
+                by_ref: syn::parse_quote!(&#ty),
+                owned: ty,
+                boxed: false,
+            });
+        }
+    }
+
+    Some(ErasedVariant {
+        ident: variant.ident.clone(),
+        fields,
+    })
+}
+
+/// Hook signatures in terms of the trait's own parameters
+fn hook_signatures(
+    erased: &ErasedVariant,
+    enum_name: &Ident,
+    enum_generics: &Generics,
+) -> (syn::Signature, syn::Signature) {
+    let box_ty = box_path();
+    let ref_hook = ref_hook_ident(&erased.ident);
+    let into_hook = into_hook_ident(&erased.ident);
+    let by_ref = erased.fields.iter().map(|f| &f.by_ref);
+    let owned = erased.fields.iter().map(|f| &f.owned);
+    let (_, ty_generics, _) = enum_generics.split_for_impl();
+
+    (
+        syn::parse_quote! {
+            fn #ref_hook(&self) -> ::core::option::Option<( #(#by_ref,)* )>
+        },
+        syn::parse_quote! {
+            fn #into_hook(self: #box_ty<Self>)
+                -> ::core::result::Result<( #(#owned,)* ), #box_ty<dyn #enum_name #ty_generics>>
+        },
+    )
+}
+
+/// Declarations of the hidden hooks on the generated trait
+pub fn generate_hook_decls(
+    erased: &[ErasedVariant],
+    enum_name: &Ident,
+    enum_generics: &Generics,
+) -> TokenStream2 {
+    let decls = erased.iter().map(|erased| {
+        let (ref_sig, into_sig) = hook_signatures(erased, enum_name, enum_generics);
+        // Moving fields out takes the variant's box
+        let into_decl = allocates().then(|| {
+            quote! {
+                #[doc(hidden)]
+                #[allow(non_snake_case)]
+                #into_sig;
+            }
+        });
+        quote! {
+            #[doc(hidden)]
+            #[allow(non_snake_case)]
+            #ref_sig;
+            #into_decl
+        }
+    });
+    quote! { #(#decls)* }
+}
+
+/// Implementations of every hook for one variant: the variant's own hooks hand
+/// out its fields, the hooks of other variants decline
+pub fn generate_hook_impls(
+    variant: &ParsedVariant,
+    erased: &[ErasedVariant],
+    enum_name: &Ident,
+    enum_generics: &Generics,
+    trait_type: &TokenStream2,
+    all_type_params_ordered: &[String],
+) -> Vec<TokenStream2> {
+    let box_ty = box_path();
+    erased
+        .iter()
+        .map(|erased| {
+            let (ref_sig, into_sig) = hook_signatures(erased, enum_name, enum_generics);
+            let ref_sig = substitute_type_params(&ref_sig, trait_type, all_type_params_ordered);
+            let into_sig = substitute_type_params(&into_sig, trait_type, all_type_params_ordered);
+
+            let (ref_body, into_body) = if erased.ident == variant.ident {
+                let members: Vec<_> = erased.fields.iter().map(|f| &f.member).collect();
+                let bindings: Vec<_> = (0..erased.fields.len())
+                    .map(|i| format_ident!("__field{}", i))
+                    .collect();
+                let owned_values = erased.fields.iter().zip(&bindings).map(|(f, b)| {
+                    if f.boxed {
+                        quote! { #box_ty::new(#b) }
+                    } else {
+                        quote! { #b }
+                    }
+                });
+                (
+                    quote! {
+                        let Self { #(#members: #bindings),* } = self;
+                        ::core::option::Option::Some(( #(#bindings,)* ))
+                    },
+                    quote! {
+                        let Self { #(#members: #bindings),* } = *self;
+                        ::core::result::Result::Ok(( #(#owned_values,)* ))
+                    },
+                )
+            } else {
+                (
+                    quote! { ::core::option::Option::None },
+                    quote! { ::core::result::Result::Err(self) },
+                )
+            };
+
+            let into_impl = allocates().then(|| {
+                quote! {
+                    #[allow(non_snake_case)]
+                    #into_sig { #into_body }
+                }
+            });
+            quote! {
+                #[allow(non_snake_case)]
+                #ref_sig { #ref_body }
+                #into_impl
+            }
+        })
+        .collect()
+}
+
+/// Erased views of every variant of an enum that needs one
+pub fn erase_variants(parsed: &ParsedEnum, generics_with_static: &Generics) -> Vec<ErasedVariant> {
+    // Hooks live on the generated trait; a user's trait has no room for them.
+    // Borrowing enums have no `Any` to hand fields out through.
+    if parsed.options.implements.is_some() || parsed.generics.lifetimes().next().is_some() {
+        return Vec::new();
+    }
+    parsed
+        .variants
+        .iter()
+        .filter_map(|variant| {
+            let trait_type = resolve_trait_type(variant, &parsed.ident, generics_with_static);
+            erase_variant(variant, &trait_type, generics_with_static)
+        })
+        .collect()
+}
@@ -0,0 +1,229 @@
+//! `extend_enum!`, adding variants to an enum defined by `type_enum!`,
+//! possibly in another crate. The new variant structs implement the enum's
+//! trait with the arms given for them, along with the hidden methods the
+//! trait declares for its own variants. Like `type_enum_impl!`, it reaches
+//! the definition through the enum's companion macro, which hands it to
+//! `__extend_enum_with!`.
+
+use proc_macro2::{Group, Span, TokenStream as TokenStream2, TokenTree};
+use quote::quote;
+use std::collections::HashSet;
+use syn::{
+    parse::{Parse, ParseStream},
+    Item, Path, Visibility,
+};
+
+use crate::clone_impls::clone_object_bound;
+use crate::display_impls::{display_source, DisplaySource};
+use crate::downcasts::view_variants;
+use crate::enum_parser::ParsedEnum;
+use crate::eq_impls::compares_dyn;
+use crate::erasure::erase_variants;
+use crate::hash_impls::hashes_dyn;
+use crate::helpers::{add_static_bounds, collect_ordered_type_params, is_default_variant};
+use crate::validation::{
+    validate_lifetimes, validate_method_arms, validate_names, validate_trait_types,
+};
+use crate::variant_gen::{generate_variant_code, EnumContext};
+
+/// Input of `extend_enum! { pub path::Enum { Variant(..), .. } fn .. }`
+pub struct ExtendEnumInput {
+    /// Visibility of the new variant structs
+    pub vis: Visibility,
+    pub enum_path: Path,
+    /// The braced variants, then methods and `impl` sections, parsed once
+    /// the enum's generics are known
+    pub body: TokenStream2,
+}
+
+impl Parse for ExtendEnumInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let vis = input.parse()?;
+        let enum_path = input.call(Path::parse_mod_style)?;
+        if !input.peek(syn::token::Brace) {
+            return Err(input.error("expected the new variants in braces after the enum"));
+        }
+        Ok(ExtendEnumInput {
+            vis,
+            enum_path,
+            body: input.parse()?,
+        })
+    }
+}
+
+/// Generate the new variant structs and their impls of the enum's trait
+pub fn expand_extend_enum(parsed: ParsedEnum, input: ExtendEnumInput) -> syn::Result<TokenStream2> {
+    let enum_name = &parsed.ident;
+    if parsed.options.iter {
+        return Err(syn::Error::new_spanned(
+            &input.enum_path,
+            format!(
+                "`{}` is walked as a list by `#[type_enum(iter)]`, whose cons and nil \
+                 variants are fixed; `extend_enum!` can't add variants to it",
+                enum_name
+            ),
+        ));
+    }
+
+    // The new variants parse like the enum's own, under its generics, struct
+    // naming and bounds
+    let vis = &input.vis;
+    let generics = &parsed.generics;
+    let where_clause = &parsed.generics.where_clause;
+    let body = &input.body;
+    let rename = parsed
+        .options
+        .rename_variants
+        .as_ref()
+        .map(|format| quote! { #[type_enum(rename_variants = #format)] });
+    let send_sync = parsed
+        .options
+        .send_sync
+        .then(|| quote! { #[type_enum(send_sync)] });
+    let extension: ParsedEnum = syn::parse2(quote! {
+        #rename
+        #send_sync
+        #vis enum #enum_name #generics #where_clause #body
+    })?;
+    validate_names(&extension)?;
+    validate_trait_types(&extension)?;
+    validate_lifetimes(&extension)?;
+    validate_method_arms(&extension)?;
+    check_extension(&parsed, &extension)?;
+
+    let generics_with_static = add_static_bounds(&parsed.generics);
+    let all_type_params_ordered = collect_ordered_type_params(&parsed.generics);
+    let all_type_params: HashSet<String> = all_type_params_ordered.iter().cloned().collect();
+
+    // The trait's hidden methods follow from the enum's own variants and
+    // options
+    let erased = erase_variants(&parsed, &generics_with_static);
+    let views = view_variants(&parsed, &generics_with_static, &all_type_params);
+    let clone_bound = clone_object_bound(&parsed);
+    let ctx = EnumContext {
+        enum_name,
+        vis,
+        methods: &extension.methods,
+        impls: &extension.impls,
+        generics_with_static: &generics_with_static,
+        all_type_params: &all_type_params,
+        all_type_params_ordered: &all_type_params_ordered,
+        erased: &erased,
+        views: &views,
+        options: &parsed.options,
+        list: None,
+        clone_bound: clone_bound.as_ref(),
+        compares_dyn: compares_dyn(&parsed),
+        hashes_dyn: hashes_dyn(&parsed),
+        displays_dyn: display_source(&parsed) == Some(DisplaySource::Section),
+        names_variants: parsed.options.implements.is_none()
+            && !extension
+                .methods
+                .iter()
+                .any(|m| m.sig.ident == "variant_name"),
+        discriminants: parsed
+            .options
+            .implements
+            .is_none()
+            .then_some(parsed.variants.as_slice()),
+    };
+
+    // The structs stay where they are declared; the impls go in a block
+    // that brings the trait in under the enum's name, which the generated
+    // code refers to it by
+    let mut structs = Vec::new();
+    let mut impls = Vec::new();
+    for variant in &extension.variants {
+        let file: syn::File = syn::parse2(generate_variant_code(variant, &ctx))?;
+        for item in file.items {
+            match item {
+                Item::Struct(_) => structs.push(item),
+                _ => impls.push(item),
+            }
+        }
+    }
+
+    let enum_path = &input.enum_path;
+    let expanded = quote! {
+        #(#structs)*
+
+        const _: () = {
+            #[allow(unused_imports)]
+            use #enum_path;
+
+            #(#impls)*
+        };
+    };
+    let user_span = enum_path.segments[0].ident.span();
+    Ok(resolve_at(expanded, user_span))
+}
+
+/// Resolve every name in the expansion where the user wrote the enum's path.
+/// The companion macro hands the input to us from inside a `macro_rules!`
+/// expansion, whose hygiene would otherwise keep the `self` and `f` of the
+/// generated signatures apart from the ones in the user's arms.
+fn resolve_at(tokens: TokenStream2, user_span: Span) -> TokenStream2 {
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            TokenTree::Group(group) => {
+                let mut respanned =
+                    Group::new(group.delimiter(), resolve_at(group.stream(), user_span));
+                respanned.set_span(group.span().resolved_at(user_span));
+                TokenTree::Group(respanned)
+            }
+            mut other => {
+                other.set_span(other.span().resolved_at(user_span));
+                other
+            }
+        })
+        .collect()
+}
+
+/// Reject what an extension can't add: names the enum's own variants take,
+/// a second `#[default]`, and trait methods with bodies, which belong to the
+/// trait `type_enum!` declares
+fn check_extension(parsed: &ParsedEnum, extension: &ParsedEnum) -> syn::Result<()> {
+    let enum_name = &parsed.ident;
+    let mut errors: Option<syn::Error> = None;
+    let mut push = |e: syn::Error| match &mut errors {
+        Some(existing) => existing.combine(e),
+        None => errors = Some(e),
+    };
+
+    for variant in &extension.variants {
+        if parsed.variants.iter().any(|v| v.name == variant.name) {
+            push(syn::Error::new(
+                variant.ident.span(),
+                format!(
+                    "`{}` is already a variant of `{}`; give the new variant another name",
+                    variant.name, enum_name
+                ),
+            ));
+        }
+        if is_default_variant(variant) {
+            push(syn::Error::new(
+                variant.ident.span(),
+                format!(
+                    "`Box<dyn {}>` takes its default from `type_enum!`; \
+                     variants added by `extend_enum!` can't be `#[default]`",
+                    enum_name
+                ),
+            ));
+        }
+    }
+    for method in &extension.methods {
+        if method.default_body.is_some() {
+            push(syn::Error::new_spanned(
+                method.sig.fn_token,
+                "`default fn` provides a method on the trait, which `type_enum!` declares; \
+                 give the new variants arms instead",
+            ));
+        }
+    }
+
+    match errors {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
@@ -0,0 +1,57 @@
+//! `hash_dyn`, and `Hash` for trait objects, generated when every variant
+//! struct derives `Hash`
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{Generics, Ident};
+
+use crate::enum_parser::ParsedEnum;
+use crate::helpers::{derives_trait, variant_derives};
+
+/// Whether trait objects can be hashed: every variant is `Hash` and the
+/// trait is generated
+pub fn hashes_dyn(parsed: &ParsedEnum) -> bool {
+    parsed.options.implements.is_none()
+        && parsed
+            .variants
+            .iter()
+            .all(|variant| derives_trait(&variant_derives(variant, &parsed.options), "Hash"))
+}
+
+/// The `hash_dyn` declaration on the generated trait
+pub fn generate_hash_decl() -> TokenStream2 {
+    quote! {
+        /// Feed the variant's name and fields into `state`, for trait objects
+        fn hash_dyn(&self, state: &mut dyn ::core::hash::Hasher);
+    }
+}
+
+/// `hash_dyn` for one variant: its name, so variants with equal fields
+/// differ, then the derived `Hash`
+pub fn generate_hash_hook_impl(variant_name: &Ident) -> TokenStream2 {
+    let name = variant_name.to_string();
+    quote! {
+        fn hash_dyn(&self, mut state: &mut dyn ::core::hash::Hasher) {
+            ::core::hash::Hash::hash(#name, &mut state);
+            ::core::hash::Hash::hash(self, &mut state);
+        }
+    }
+}
+
+/// `impl Hash for dyn Enum<..>`, which `Box<dyn Enum<..>>` picks up
+pub fn generate_dyn_hash(
+    enum_name: &Ident,
+    generics_with_static: &Generics,
+    lint_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let (impl_generics, ty_generics, where_clause) = generics_with_static.split_for_impl();
+    quote! {
+        #[automatically_derived]
+        #lint_attrs
+        impl #impl_generics ::core::hash::Hash for dyn #enum_name #ty_generics + '_ #where_clause {
+            fn hash<__H: ::core::hash::Hasher>(&self, state: &mut __H) {
+                self.hash_dyn(state)
+            }
+        }
+    }
+}
@@ -0,0 +1,551 @@
+//! Helper functions for type parameter handling and code generation
+
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, ToTokens};
+use std::collections::{HashMap, HashSet};
+use syn::visit_mut::{self, VisitMut};
+use syn::{
+    punctuated::Punctuated, GenericArgument, GenericParam, Generics, Ident, Path, PathArguments,
+    Signature, Token, Type, TypeParamBound, TypePath,
+};
+
+use crate::enum_parser::ParsedVariant;
+use crate::options::EnumOptions;
+
+/// Collect type parameter names in order
+pub fn collect_ordered_type_params(generics: &Generics) -> Vec<String> {
+    generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(t) => Some(t.ident.to_string()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Add 'static bounds to all generic type parameters
+pub fn add_static_bounds(generics: &Generics) -> Generics {
+    let mut generics_with_static = generics.clone();
+    for param in generics_with_static.type_params_mut() {
+        let has_static = param
+            .bounds
+            .iter()
+            .any(|bound| matches!(bound, TypeParamBound::Lifetime(l) if l.ident == "static"));
+        if !has_static {
+            param.bounds.push(syn::parse_quote!('static));
+        }
+    }
+    generics_with_static
+}
+
+/// `Send + Sync` for `#[type_enum(send_sync)]`, leaving out whichever of the
+/// two `bounds` already has
+pub fn missing_send_sync<'a>(
+    bounds: impl IntoIterator<Item = &'a TypeParamBound>,
+) -> Vec<TypeParamBound> {
+    let named: HashSet<String> = bounds
+        .into_iter()
+        .filter_map(|bound| match bound {
+            TypeParamBound::Trait(t) => t.path.segments.last().map(|s| s.ident.to_string()),
+            _ => None,
+        })
+        .collect();
+    let mut missing = Vec::new();
+    if !named.contains("Send") {
+        missing.push(syn::parse_quote!(::core::marker::Send));
+    }
+    if !named.contains("Sync") {
+        missing.push(syn::parse_quote!(::core::marker::Sync));
+    }
+    missing
+}
+
+/// Bound every type parameter by `Send + Sync`, which a variant struct
+/// holding one needs to implement a `send_sync` trait
+pub fn add_send_sync_bounds(generics: &mut Generics) {
+    for param in generics.type_params_mut() {
+        let missing = missing_send_sync(&param.bounds);
+        param.bounds.extend(missing);
+    }
+}
+
+/// Strip generic type parameters from a pattern (e.g., "Lift<i32>(x)" -> "Lift(x)")
+pub fn strip_pattern_generics(pattern: &TokenStream2) -> TokenStream2 {
+    let mut result_tokens = Vec::new();
+    let mut skip_until_gt = false;
+
+    for tt in pattern.clone() {
+        match &tt {
+            TokenTree::Punct(p) if p.as_char() == '<' => {
+                skip_until_gt = true;
+            }
+            TokenTree::Punct(p) if p.as_char() == '>' && skip_until_gt => {
+                skip_until_gt = false;
+                continue;
+            }
+            _ if skip_until_gt => continue,
+            _ => result_tokens.push(tt),
+        }
+    }
+
+    result_tokens.into_iter().collect()
+}
+
+/// Extract type arguments from a trait type TokenStream (e.g., "Pair<B, A>" -> [B, A])
+pub fn extract_trait_type_args(trait_type: &TokenStream2) -> Vec<Type> {
+    let Ok(path) = syn::parse2::<Path>(trait_type.clone()) else {
+        return Vec::new();
+    };
+
+    match path.segments.last().map(|s| &s.arguments) {
+        Some(PathArguments::AngleBracketed(args)) => args
+            .args
+            .iter()
+            .filter_map(|arg| match arg {
+                GenericArgument::Type(ty) => Some(ty.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Spell out defaulted type arguments omitted from a trait type, so that
+/// `Expr` becomes `Expr<i32>` for `enum Expr<T = i32>`
+pub fn fill_default_type_args(trait_type: &TokenStream2, generics: &Generics) -> TokenStream2 {
+    let Ok(mut path) = syn::parse2::<Path>(trait_type.clone()) else {
+        return trait_type.clone();
+    };
+    let Some(last) = path.segments.last_mut() else {
+        return trait_type.clone();
+    };
+
+    let given = match &last.arguments {
+        PathArguments::AngleBracketed(args) => args
+            .args
+            .iter()
+            .filter(|arg| matches!(arg, GenericArgument::Type(_)))
+            .count(),
+        PathArguments::None => 0,
+        PathArguments::Parenthesized(_) => return trait_type.clone(),
+    };
+
+    let defaults: Vec<Type> = generics
+        .type_params()
+        .skip(given)
+        .map_while(|tp| tp.default.clone())
+        .collect();
+    if defaults.is_empty() {
+        return trait_type.clone();
+    }
+
+    if let PathArguments::None = last.arguments {
+        last.arguments = PathArguments::AngleBracketed(syn::parse_quote!(<>));
+    }
+    if let PathArguments::AngleBracketed(args) = &mut last.arguments {
+        for default in defaults {
+            args.args.push(GenericArgument::Type(default));
+        }
+    }
+
+    path.to_token_stream()
+}
+
+/// Replaces bare type parameter paths (`T`) with the types they are mapped to
+struct TypeParamSubstitution<'a> {
+    mapping: &'a HashMap<String, Type>,
+}
+
+impl VisitMut for TypeParamSubstitution<'_> {
+    fn visit_type_mut(&mut self, ty: &mut Type) {
+        if let Type::Path(TypePath { qself: None, path }) = ty {
+            if let Some(ident) = path.get_ident() {
+                if let Some(replacement) = self.mapping.get(&ident.to_string()) {
+                    *ty = replacement.clone();
+                    return;
+                }
+            }
+        }
+        visit_mut::visit_type_mut(self, ty);
+    }
+}
+
+/// Replace type parameters named in `mapping` inside a single type
+pub fn substitute_in_type(ty: &Type, mapping: &HashMap<String, Type>) -> Type {
+    let mut ty = ty.clone();
+    TypeParamSubstitution { mapping }.visit_type_mut(&mut ty);
+    ty
+}
+
+/// Substitute type parameters in a signature based on trait type mapping
+/// For example, if trait_type is "Pair<B, A>" and enum params are [A, B],
+/// it will replace A->B and B->A in the signature
+pub fn substitute_type_params(
+    sig: &Signature,
+    trait_type: &TokenStream2,
+    enum_params: &[String],
+) -> Signature {
+    let trait_type_args = extract_trait_type_args(trait_type);
+
+    let mapping: HashMap<String, Type> = enum_params.iter().cloned().zip(trait_type_args).collect();
+
+    let mut sig = sig.clone();
+    TypeParamSubstitution { mapping: &mapping }.visit_signature_mut(&mut sig);
+    sig
+}
+
+/// Collects method generics that are passed as type arguments to the enum's own
+/// trait, together with the position they are passed at
+struct IndexUses<'a> {
+    enum_name: &'a syn::Ident,
+    method_params: HashSet<String>,
+    uses: Vec<(syn::Ident, usize)>,
+}
+
+impl IndexUses<'_> {
+    fn record(&mut self, path: &Path) {
+        let Some(last) = path.segments.last() else {
+            return;
+        };
+        if last.ident != *self.enum_name {
+            return;
+        }
+        let PathArguments::AngleBracketed(args) = &last.arguments else {
+            return;
+        };
+        let type_args = args.args.iter().filter_map(|arg| match arg {
+            GenericArgument::Type(Type::Path(p)) => Some(p.path.get_ident()),
+            GenericArgument::Type(_) => Some(None),
+            _ => None,
+        });
+        for (position, ident) in type_args.enumerate() {
+            if let Some(ident) = ident {
+                if self.method_params.contains(&ident.to_string()) {
+                    self.uses.push((ident.clone(), position));
+                }
+            }
+        }
+    }
+}
+
+impl<'ast> syn::visit::Visit<'ast> for IndexUses<'_> {
+    fn visit_path(&mut self, path: &'ast Path) {
+        self.record(path);
+        syn::visit::visit_path(self, path);
+    }
+}
+
+/// Whether a method signature opts out of the vtable with `where Self: Sized`
+pub fn requires_sized(sig: &Signature) -> bool {
+    let Some(where_clause) = &sig.generics.where_clause else {
+        return false;
+    };
+    where_clause
+        .predicates
+        .iter()
+        .any(|predicate| match predicate {
+            syn::WherePredicate::Type(pt) => {
+                matches!(&pt.bounded_ty, Type::Path(p) if p.path.is_ident("Self"))
+                    && pt.bounds.iter().any(|bound| {
+                        matches!(bound, syn::TypeParamBound::Trait(t)
+                        if matches!(t.modifier, syn::TraitBoundModifier::None)
+                            && t.path.is_ident("Sized"))
+                    })
+            }
+            _ => false,
+        })
+}
+
+/// Finds `impl Trait` in argument position, which makes a method generic
+struct ImplTraitArgs {
+    found: bool,
+}
+
+impl<'ast> syn::visit::Visit<'ast> for ImplTraitArgs {
+    fn visit_type_impl_trait(&mut self, _: &'ast syn::TypeImplTrait) {
+        self.found = true;
+    }
+}
+
+/// Whether a type contains `impl Trait`
+fn mentions_impl_trait(ty: &Type) -> bool {
+    let mut finder = ImplTraitArgs { found: false };
+    syn::visit::Visit::visit_type(&mut finder, ty);
+    finder.found
+}
+
+/// Prepare a method signature for use in a `dyn`-compatible trait.
+///
+/// Method generics used as an index of the enum's trait (`U` in
+/// `-> Box<dyn Expr<U>>`) inherit the bounds of the enum parameter at that
+/// position plus `'static`, and generic methods, including those taking
+/// `impl Trait` arguments, are excluded from the vtable with
+/// `where Self: Sized` so the trait stays object safe. Returns whether it
+/// added that bound.
+pub fn bind_index_generics(
+    sig: &mut Signature,
+    enum_name: &syn::Ident,
+    enum_generics: &Generics,
+) -> bool {
+    let method_params: HashSet<String> = sig
+        .generics
+        .type_params()
+        .map(|tp| tp.ident.to_string())
+        .collect();
+    let impl_trait_args = sig.inputs.iter().any(|input| match input {
+        syn::FnArg::Typed(arg) => mentions_impl_trait(&arg.ty),
+        syn::FnArg::Receiver(_) => false,
+    });
+    if method_params.is_empty() && !impl_trait_args {
+        return false;
+    }
+
+    let mut index_uses = IndexUses {
+        enum_name,
+        method_params,
+        uses: Vec::new(),
+    };
+    syn::visit::Visit::visit_signature(&mut index_uses, sig);
+
+    let enum_params: Vec<_> = enum_generics.type_params().collect();
+    for (ident, position) in index_uses.uses {
+        let Some(enum_param) = enum_params.get(position) else {
+            continue;
+        };
+        if let Some(param) = sig.generics.type_params_mut().find(|tp| tp.ident == ident) {
+            let static_bound: syn::TypeParamBound = syn::parse_quote!('static);
+            for bound in enum_param.bounds.iter().chain(Some(&static_bound)) {
+                let bound_str = bound.to_token_stream().to_string();
+                if !param
+                    .bounds
+                    .iter()
+                    .any(|b| b.to_token_stream().to_string() == bound_str)
+                {
+                    param.bounds.push(bound.clone());
+                }
+            }
+        }
+    }
+
+    if requires_sized(sig) {
+        return false;
+    }
+    sig.generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(Self: Sized));
+    true
+}
+
+/// Members of a variant's fields in declaration order (`name` or `0`, `1`, ...)
+pub fn field_members(fields: &syn::Fields) -> Vec<syn::Member> {
+    fields
+        .iter()
+        .enumerate()
+        .map(|(index, field)| match &field.ident {
+            Some(ident) => syn::Member::Named(ident.clone()),
+            None => syn::Member::Unnamed(index.into()),
+        })
+        .collect()
+}
+
+/// Merge variant-level generics with enum-level generics
+/// Variant-level generics take precedence and are placed first
+pub fn merge_generics(
+    variant_generics: &Generics,
+    enum_generics: &Generics,
+    used_enum_params: &HashSet<String>,
+) -> Generics {
+    let mut merged = variant_generics.clone();
+
+    // Get names of variant-level type and const params to avoid duplicates
+    let variant_param_names: HashSet<String> = variant_generics
+        .type_params()
+        .map(|tp| tp.ident.to_string())
+        .chain(
+            variant_generics
+                .const_params()
+                .map(|cp| cp.ident.to_string()),
+        )
+        .collect();
+
+    // Add enum-level params that are used and not already in variant params;
+    // lifetimes are named as `'a` among the used params, const params by name
+    for param in enum_generics.params.iter() {
+        let param_name = generic_param_name(param);
+        if used_enum_params.contains(&param_name) && !variant_param_names.contains(&param_name) {
+            merged.params.push(param.clone());
+        }
+    }
+
+    // Keep the enum's where predicates over the params that made it in; the
+    // others are checked against the trait's own where clause wherever the
+    // trait is implemented at concrete arguments. The variant's own bounds
+    // naming enum params left out, like `N: Expr<T>` on a struct without
+    // `T`, are checked by the impls that have them.
+    let enum_param_names: HashSet<String> = enum_generics
+        .params
+        .iter()
+        .map(generic_param_name)
+        .filter(|name| !variant_param_names.contains(name))
+        .collect();
+    let kept: HashSet<String> = merged.params.iter().map(generic_param_name).collect();
+    let in_scope = |tokens: TokenStream2| {
+        mentioned_names(tokens)
+            .iter()
+            .filter(|name| enum_param_names.contains(*name))
+            .all(|name| kept.contains(name))
+    };
+    for param in merged.type_params_mut() {
+        param.bounds = std::mem::take(&mut param.bounds)
+            .into_pairs()
+            .filter(|pair| in_scope(pair.value().to_token_stream()))
+            .collect();
+    }
+    if let Some(where_clause) = &mut merged.where_clause {
+        where_clause.predicates = std::mem::take(&mut where_clause.predicates)
+            .into_pairs()
+            .filter(|pair| in_scope(pair.value().to_token_stream()))
+            .collect();
+    }
+    if let Some(enum_where) = &enum_generics.where_clause {
+        for predicate in &enum_where.predicates {
+            if in_scope(predicate.to_token_stream()) {
+                merged
+                    .make_where_clause()
+                    .predicates
+                    .push(predicate.clone());
+            }
+        }
+    }
+
+    merged
+}
+
+/// Name of a generic parameter, with lifetimes written as `'a`
+fn generic_param_name(param: &GenericParam) -> String {
+    match param {
+        GenericParam::Type(t) => t.ident.to_string(),
+        GenericParam::Lifetime(l) => l.lifetime.to_string(),
+        GenericParam::Const(c) => c.ident.to_string(),
+    }
+}
+
+/// Every identifier and lifetime (as `'a`) appearing in tokens
+fn mentioned_names(tokens: TokenStream2) -> HashSet<String> {
+    let mut names = HashSet::new();
+    let mut after_quote = false;
+    for token in tokens {
+        match token {
+            TokenTree::Punct(punct) if punct.as_char() == '\'' => {
+                after_quote = true;
+                continue;
+            }
+            TokenTree::Ident(ident) if after_quote => {
+                names.insert(format!("'{}", ident));
+            }
+            TokenTree::Ident(ident) => {
+                names.insert(ident.to_string());
+            }
+            TokenTree::Group(group) => names.extend(mentioned_names(group.stream())),
+            _ => {}
+        }
+        after_quote = false;
+    }
+    names
+}
+
+/// The `#[derive(...)]` attributes of a variant struct: the variant's own,
+/// which replace the enum's, or else the enum's
+pub fn variant_derives<'a>(
+    variant: &'a ParsedVariant,
+    options: &'a EnumOptions,
+) -> Vec<&'a syn::Attribute> {
+    let own_derives: Vec<_> = variant
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("derive"))
+        .collect();
+    if own_derives.is_empty() {
+        options.derives.iter().collect()
+    } else {
+        own_derives
+    }
+}
+
+/// Whether any of the `#[derive(...)]` attributes derives the trait `name`
+pub fn derives_trait(derives: &[&syn::Attribute], name: &str) -> bool {
+    derives.iter().any(|attr| {
+        attr.parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated)
+            .is_ok_and(|paths| {
+                paths
+                    .iter()
+                    .any(|path| path.segments.last().is_some_and(|s| s.ident == name))
+            })
+    })
+}
+
+/// `#[cfg]` attributes of a variant, carried onto every item generated for it
+pub fn cfg_attrs(attrs: &[syn::Attribute]) -> Vec<&syn::Attribute> {
+    attrs.iter().filter(|a| a.path().is_ident("cfg")).collect()
+}
+
+/// `#[cfg_attr]` attributes of a variant, carried onto its struct
+pub fn cfg_attr_attrs(attrs: &[syn::Attribute]) -> Vec<&syn::Attribute> {
+    attrs
+        .iter()
+        .filter(|a| a.path().is_ident("cfg_attr"))
+        .collect()
+}
+
+/// Doc comments, carried from a variant onto its struct and constructor
+pub fn doc_attrs(attrs: &[syn::Attribute]) -> Vec<&syn::Attribute> {
+    attrs.iter().filter(|a| a.path().is_ident("doc")).collect()
+}
+
+/// Whether the variant is marked `#[default]`, making it what
+/// `Box<dyn Enum<..>>::default()` builds
+pub fn is_default_variant(variant: &ParsedVariant) -> bool {
+    variant.attrs.iter().any(|a| a.path().is_ident("default"))
+}
+
+/// `#[deprecated]` attributes, carried from a variant or method onto the items
+/// generated for it
+pub fn deprecation_attrs(attrs: &[syn::Attribute]) -> Vec<&syn::Attribute> {
+    attrs
+        .iter()
+        .filter(|a| a.path().is_ident("deprecated"))
+        .collect()
+}
+
+/// `BinOp` -> `bin_op`, for names derived from variants
+pub fn snake_case(name: &Ident) -> String {
+    let name = name.to_string();
+    let chars: Vec<char> = name.chars().collect();
+    let mut snake = String::new();
+    for (index, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && index > 0 {
+            let prev = chars[index - 1];
+            let next_lower = chars.get(index + 1).is_some_and(|n| n.is_lowercase());
+            if prev.is_lowercase() || prev.is_ascii_digit() || (prev.is_uppercase() && next_lower) {
+                snake.push('_');
+            }
+        }
+        snake.extend(c.to_lowercase());
+    }
+    snake
+}
+
+/// [`snake_case`] as an identifier, raw or suffixed where it is a keyword
+pub fn snake_case_ident(name: &Ident) -> Ident {
+    let snake = snake_case(name);
+    match snake.as_str() {
+        // Keywords that can't be raw identifiers
+        "self" | "super" | "crate" => format_ident!("{}_", snake),
+        _ if syn::parse_str::<Ident>(&snake).is_err() => {
+            Ident::new_raw(&snake, proc_macro2::Span::call_site())
+        }
+        _ => format_ident!("{}", snake),
+    }
+}
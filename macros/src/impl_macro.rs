@@ -0,0 +1,407 @@
+//! `type_enum_impl!`, adding methods to the variant structs of an enum defined
+//! elsewhere. Like `match_t!`, it reaches the definition through the enum's
+//! companion macro, which hands it to `__type_enum_impl_with!`.
+
+use proc_macro2::{Group, TokenStream as TokenStream2, TokenTree};
+use quote::quote;
+use std::collections::HashSet;
+use syn::{
+    parse::{Parse, ParseStream},
+    Attribute, FnArg, Pat, Path, Signature, Token, Visibility,
+};
+
+use crate::async_methods::require_send_future;
+use crate::codegen::{box_path, unreachable_branch};
+use crate::enum_parser::{parse_method, ParsedEnum, ParsedMethod, ParsedVariant};
+use crate::helpers::{
+    add_static_bounds, bind_index_generics, collect_ordered_type_params, requires_sized,
+    substitute_type_params,
+};
+use crate::namespace::namespace_ident;
+use crate::validation::{qualify_arm_head, rename_arm_head, validate_method_arms};
+use crate::variant_gen::{
+    arm_names_variant, extract_type_params_from_trait, generate_method_body, is_boxed_receiver,
+    is_pinned_receiver, is_wildcard_arm, resolve_trait_type, variant_struct_generics,
+};
+
+/// A method of `type_enum_impl!`, with the visibility it is given on the
+/// variant structs
+pub struct ImplMethod {
+    pub attrs: Vec<Attribute>,
+    pub vis: Visibility,
+    pub method: ParsedMethod,
+}
+
+/// Input of `type_enum_impl! { path::Enum => fn ... }`
+pub struct TypeEnumImplInput {
+    pub enum_path: Path,
+    pub methods: Vec<ImplMethod>,
+}
+
+impl Parse for TypeEnumImplInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let enum_path: Path = input.parse()?;
+        input.parse::<Token![=>]>()?;
+
+        let mut methods = Vec::new();
+        while !input.is_empty() {
+            let attrs = input.call(Attribute::parse_outer)?;
+            let vis: Visibility = input.parse()?;
+            let method = parse_method(input)?;
+            if method.default_body.is_some() {
+                return Err(syn::Error::new_spanned(
+                    method.sig.fn_token,
+                    "`default fn` provides a trait method; `type_enum_impl!` adds inherent \
+                     methods, so give the variants arms instead",
+                ));
+            }
+            // Pin projection is only sound on structs `type_enum!` guards
+            // against `Drop` and `Unpin` impls when it generates them
+            if is_pinned_receiver(&method.sig) {
+                return Err(syn::Error::new_spanned(
+                    &method.sig.inputs[0],
+                    "`type_enum_impl!` can't take `self: Pin<&mut Self>`, since the \
+                     variant structs aren't guarded for pin projection; declare the \
+                     method in `type_enum!` instead",
+                ));
+            }
+            methods.push(ImplMethod { attrs, vis, method });
+        }
+
+        Ok(TypeEnumImplInput { enum_path, methods })
+    }
+}
+
+/// Generate the inherent methods of every variant struct, and a dispatching
+/// method on the trait object where every variant can be reached by downcast
+pub fn expand_type_enum_impl(
+    mut parsed: ParsedEnum,
+    input: TypeEnumImplInput,
+) -> syn::Result<TokenStream2> {
+    let mut methods = input.methods;
+    for impl_method in &mut methods {
+        if impl_method.method.is_async && parsed.options.send_sync {
+            require_send_future(&mut impl_method.method.sig);
+        }
+        for arm in &mut impl_method.method.arms {
+            arm.pattern = rename_arm_head(&arm.pattern, &parsed.variants);
+        }
+    }
+    parsed.methods = methods.iter().map(|m| m.method.clone()).collect();
+    validate_method_arms(&parsed)?;
+
+    let enum_name = &parsed.ident;
+    let generics_with_static = add_static_bounds(&parsed.generics);
+    let all_type_params_ordered = collect_ordered_type_params(&parsed.generics);
+    let all_type_params: HashSet<String> = all_type_params_ordered.iter().cloned().collect();
+    // The impls hold the user's method bodies, which are linted as usual
+    let lint_attrs = parsed.options.configured_lint_attrs();
+
+    // Variant structs live next to the enum's trait, or in its namespace
+    // module
+    let mut module = input.enum_path.clone();
+    module.segments.pop();
+    if let Some(namespace) = namespace_ident(&parsed) {
+        module.segments.push_value(namespace.into());
+        module.segments.push_punct(Default::default());
+    }
+    let module = quote! { #module };
+
+    for impl_method in &mut methods {
+        bind_index_generics(&mut impl_method.method.sig, enum_name, &parsed.generics);
+        if !module.is_empty() {
+            qualify_arm_heads(&mut impl_method.method, &module);
+        }
+    }
+
+    let mut items = Vec::new();
+    for variant in &parsed.variants {
+        let struct_generics =
+            variant_struct_generics(variant, &generics_with_static, &all_type_params);
+        let struct_params: HashSet<String> = struct_generics
+            .params
+            .iter()
+            .map(|param| match param {
+                syn::GenericParam::Type(t) => t.ident.to_string(),
+                syn::GenericParam::Lifetime(l) => l.lifetime.to_string(),
+                syn::GenericParam::Const(c) => c.ident.to_string(),
+            })
+            .collect();
+        let trait_type = resolve_trait_type(variant, enum_name, &generics_with_static);
+
+        let method_impls: Vec<_> = methods
+            .iter()
+            .filter_map(|impl_method| {
+                let method = with_index_params(
+                    &impl_method.method,
+                    &trait_type,
+                    &all_type_params_ordered,
+                    &generics_with_static,
+                    &struct_params,
+                );
+                let (body, _) =
+                    generate_method_body(variant, &method, &trait_type, &all_type_params_ordered)?;
+                let body = resolve_self_at(body, &impl_method.method.sig);
+                let attrs = &impl_method.attrs;
+                let vis = &impl_method.vis;
+                Some(quote! { #(#attrs)* #vis #body })
+            })
+            .collect();
+        if method_impls.is_empty() {
+            continue;
+        }
+
+        let variant_name = &variant.ident;
+        let (impl_generics, ty_generics, where_clause) = struct_generics.split_for_impl();
+        items.push(quote! {
+            #lint_attrs
+            impl #impl_generics #module #variant_name #ty_generics #where_clause {
+                #(#method_impls)*
+            }
+        });
+    }
+
+    let dispatch = generate_dyn_dispatch(&parsed, &methods, &input.enum_path, &module);
+
+    Ok(quote! {
+        #(#items)*
+        #dispatch
+    })
+}
+
+/// Prefix the variant each arm names with the module of the enum, so
+/// `Circle(r)` matches `shapes::Circle` from wherever `type_enum_impl!` is
+/// called
+fn qualify_arm_heads(method: &mut ParsedMethod, module: &TokenStream2) {
+    if method.sig.receiver().is_none() {
+        return;
+    }
+    for arm in &mut method.arms {
+        arm.pattern = qualify_arm_head(&arm.pattern, module);
+    }
+}
+
+/// Resolve the `self` the generated code refers to where the user wrote the
+/// receiver. The companion macro hands the method to us from inside a
+/// `macro_rules!` expansion, whose hygiene would otherwise keep our `self`
+/// apart from the user's.
+fn resolve_self_at(tokens: TokenStream2, sig: &Signature) -> TokenStream2 {
+    let Some(receiver) = sig.receiver() else {
+        return tokens;
+    };
+    let user_span = receiver.self_token.span;
+    tokens
+        .into_iter()
+        .map(|token| match token {
+            TokenTree::Ident(mut ident) if ident == "self" => {
+                ident.set_span(ident.span().resolved_at(user_span));
+                TokenTree::Ident(ident)
+            }
+            TokenTree::Group(group) => {
+                let mut respanned =
+                    Group::new(group.delimiter(), resolve_self_at(group.stream(), sig));
+                respanned.set_span(group.span());
+                TokenTree::Group(respanned)
+            }
+            other => other,
+        })
+        .collect()
+}
+
+/// A copy of `method` whose signature also declares the enum parameters the
+/// variant's substituted signature mentions but its struct does not carry,
+/// such as `T` for `Nil : List<T>`; an inherent impl cannot leave them
+/// unconstrained
+fn with_index_params(
+    method: &ParsedMethod,
+    trait_type: &TokenStream2,
+    all_type_params_ordered: &[String],
+    generics_with_static: &syn::Generics,
+    struct_params: &HashSet<String>,
+) -> ParsedMethod {
+    let substituted = substitute_type_params(&method.sig, trait_type, all_type_params_ordered);
+    let missing: HashSet<String> = all_type_params_ordered
+        .iter()
+        .filter(|param| !struct_params.contains(*param))
+        .cloned()
+        .collect();
+    let mentioned = extract_type_params_from_trait(&quote! { #substituted }, &missing);
+
+    let mut method = method.clone();
+    // The attributes, docs included, are emitted next to the method's visibility
+    method.attrs.clear();
+    for param in generics_with_static.type_params() {
+        if mentioned.contains(&param.ident.to_string()) {
+            method.sig.generics.params.push(param.clone().into());
+        }
+    }
+    method
+}
+
+/// Whether the trait object can forward a method to every variant: each
+/// variant has an arm, can be named without generics of its own, and sees
+/// the same signature
+fn dispatchable(parsed: &ParsedEnum, method: &ParsedMethod, variants: &[&ParsedVariant]) -> bool {
+    // `&self`, `&mut self` and `Box<Self>` can be downcast through `Any`
+    let Some(receiver) = method.sig.receiver() else {
+        return false;
+    };
+    if requires_sized(&method.sig)
+        || (receiver.reference.is_none() && !is_boxed_receiver(&method.sig))
+    {
+        return false;
+    }
+    let simple_args = method.sig.inputs.iter().all(|input| match input {
+        FnArg::Receiver(_) => true,
+        FnArg::Typed(arg) => matches!(&*arg.pat, Pat::Ident(binding) if binding.subpat.is_none()),
+    });
+    if !simple_args || variants.len() != parsed.variants.len() {
+        return false;
+    }
+
+    let generics_with_static = add_static_bounds(&parsed.generics);
+    let all_type_params_ordered = collect_ordered_type_params(&parsed.generics);
+    let sig = &method.sig;
+    let original = quote! { #sig }.to_string();
+    variants.iter().all(|variant| {
+        let trait_type = resolve_trait_type(variant, &parsed.ident, &generics_with_static);
+        let substituted = substitute_type_params(sig, &trait_type, &all_type_params_ordered);
+        variant.generics.params.is_empty() && quote! { #substituted }.to_string() == original
+    })
+}
+
+/// `impl dyn Enum<..> { fn method(..) }`, downcasting to each variant struct
+/// and calling its inherent method. Left out for traits named with
+/// `#[implements(...)]`, which may live in another crate, and for enums with
+/// lifetime parameters, whose trait objects are not `Any`.
+fn generate_dyn_dispatch(
+    parsed: &ParsedEnum,
+    methods: &[ImplMethod],
+    enum_path: &Path,
+    module: &TokenStream2,
+) -> TokenStream2 {
+    let box_ty = box_path();
+    if parsed.options.implements.is_some() || parsed.generics.lifetimes().next().is_some() {
+        return TokenStream2::new();
+    }
+
+    let generics_with_static = add_static_bounds(&parsed.generics);
+    let all_type_params: HashSet<String> = collect_ordered_type_params(&parsed.generics)
+        .into_iter()
+        .collect();
+    let downcast_failed = unreachable_branch("a value that is a variant downcasts to it");
+
+    let forwards: Vec<_> = methods
+        .iter()
+        .filter_map(|impl_method| {
+            let method = &impl_method.method;
+            let variants: Vec<_> = parsed
+                .variants
+                .iter()
+                .filter(|variant| {
+                    method.arms.iter().any(|arm| {
+                        is_wildcard_arm(arm) || arm_names_variant(arm, &variant.ident)
+                    })
+                })
+                .collect();
+            if !dispatchable(parsed, method, &variants) {
+                return None;
+            }
+
+            let method_name = &method.sig.ident;
+            let args: Vec<_> = method
+                .sig
+                .inputs
+                .iter()
+                .filter_map(|input| match input {
+                    FnArg::Typed(arg) => match &*arg.pat {
+                        Pat::Ident(binding) => Some(&binding.ident),
+                        _ => None,
+                    },
+                    FnArg::Receiver(_) => None,
+                })
+                .collect();
+            let boxed = is_boxed_receiver(&method.sig);
+            let mutable = method
+                .sig
+                .receiver()
+                .is_some_and(|receiver| receiver.mutability.is_some());
+
+            let steps = variants.iter().map(|variant| {
+                let variant_name = &variant.ident;
+                let ty_generics = variant_struct_generics(variant, &generics_with_static, &all_type_params);
+                let (_, ty_generics, _) = ty_generics.split_for_impl();
+                let ty = quote! { #module #variant_name #ty_generics };
+                if boxed {
+                    quote! {
+                        let __any = match __any.downcast::<#ty>() {
+                            ::core::result::Result::Ok(__variant) => {
+                                return __variant.#method_name(#(#args),*)
+                            }
+                            ::core::result::Result::Err(__any) => __any,
+                        };
+                    }
+                } else if mutable {
+                    quote! {
+                        if __any.is::<#ty>() {
+                            return match __any.downcast_mut::<#ty>() {
+                                ::core::option::Option::Some(__variant) => {
+                                    __variant.#method_name(#(#args),*)
+                                }
+                                ::core::option::Option::None => #downcast_failed,
+                            };
+                        }
+                    }
+                } else {
+                    quote! {
+                        if let ::core::option::Option::Some(__variant) = __any.downcast_ref::<#ty>() {
+                            return __variant.#method_name(#(#args),*);
+                        }
+                    }
+                }
+            });
+
+            let any = if boxed {
+                quote! { let __any: #box_ty<dyn ::core::any::Any> = self; }
+            } else if mutable {
+                quote! { let __any: &mut dyn ::core::any::Any = self; }
+            } else {
+                quote! { let __any: &dyn ::core::any::Any = self; }
+            };
+            let attrs = &impl_method.attrs;
+            let vis = &impl_method.vis;
+            let sig = &method.sig;
+            let not_a_variant = format!(
+                "`{}` is only implemented by the variants of `{}`",
+                parsed.ident, parsed.ident
+            );
+            let forward = quote! {
+                #sig {
+                    #any
+                    #(#steps)*
+                    let _ = __any;
+                    ::core::unreachable!(#not_a_variant)
+                }
+            };
+            let forward = resolve_self_at(forward, &method.sig);
+            Some(quote! { #(#attrs)* #vis #forward })
+        })
+        .collect();
+
+    if forwards.is_empty() {
+        return TokenStream2::new();
+    }
+
+    let lint_attrs = parsed.options.lint_attrs();
+    let (impl_generics, ty_generics, where_clause) = generics_with_static.split_for_impl();
+    let mut trait_path = enum_path.clone();
+    if let Some(last) = trait_path.segments.last_mut() {
+        last.arguments = syn::PathArguments::None;
+    }
+    quote! {
+        #lint_attrs
+        impl #impl_generics dyn #trait_path #ty_generics #where_clause {
+            #(#forwards)*
+        }
+    }
+}
@@ -0,0 +1,369 @@
+//! Iterators over cons-list shaped enums (`#[type_enum(iter)]`)
+//!
+//! A list-like enum has one variant with two fields, an element and the rest
+//! of the list, and otherwise only fieldless variants ending the list. The
+//! tail may sit at a different index (`SafeList<T, E>`), so walking it goes
+//! through a hidden node trait that only knows the element type: every
+//! variant struct implements it, and the enum's trait hands itself out as one.
+//! The iterator types are hidden and prefixed like the node trait, so they
+//! take no names from the enum's module; callers only go through `Iterator`.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, ToTokens};
+use std::collections::HashSet;
+use syn::{Generics, Ident, Member, Type};
+
+use crate::codegen::box_path;
+use crate::enum_parser::{ParsedEnum, ParsedVariant};
+use crate::erasure::{mentions, own_param_mapping};
+use crate::helpers::{
+    add_static_bounds, extract_trait_type_args, field_members, merge_generics, substitute_in_type,
+};
+use crate::type_analysis::collect_all_type_param_names;
+use crate::variant_gen::{
+    extract_type_params_from_trait, resolve_trait_type, variant_struct_generics,
+};
+
+/// The shape of a list-like enum
+pub struct ListShape {
+    /// The element type, in terms of the trait's own parameters
+    elem: Type,
+    /// The variant holding an element and the rest of the list
+    cons: Ident,
+    head: Member,
+    tail: Member,
+    /// Whether the tail is a variant-level generic rather than a boxed trait object
+    tail_is_generic: bool,
+}
+
+fn node_trait_ident(enum_name: &Ident) -> Ident {
+    format_ident!("__enum_typer_node_{}", enum_name)
+}
+
+fn iter_ident(enum_name: &Ident) -> Ident {
+    format_ident!("__enum_typer_iter_{}", enum_name)
+}
+
+fn into_iter_ident(enum_name: &Ident) -> Ident {
+    format_ident!("__enum_typer_into_iter_{}", enum_name)
+}
+
+/// Recognize the cons and nil variants of an enum marked `#[type_enum(iter)]`
+pub fn list_shape(parsed: &ParsedEnum, generics_with_static: &Generics) -> syn::Result<ListShape> {
+    let shape_error = |span: proc_macro2::Span, problem: &str| {
+        syn::Error::new(
+            span,
+            format!(
+                "`#[type_enum(iter)]` needs a list-shaped enum: one variant holding an element \
+                 and the rest of the list, e.g. `Cons(T, Box<dyn {}<T>>)`, and fieldless variants \
+                 ending it; {}",
+                parsed.ident, problem
+            ),
+        )
+    };
+
+    if let Some(method) = parsed.methods.iter().find(|m| m.sig.ident == "iter") {
+        return Err(syn::Error::new(
+            method.sig.ident.span(),
+            format!(
+                "`#[type_enum(iter)]` adds `iter()` to `{}`; rename this method or drop the option",
+                parsed.ident
+            ),
+        ));
+    }
+
+    if let Some(lifetime) = parsed.generics.lifetimes().next() {
+        return Err(syn::Error::new(
+            lifetime.lifetime.span(),
+            "`#[type_enum(iter)]` boxes the rest of the list, so it needs an enum without \
+             lifetime parameters",
+        ));
+    }
+
+    let mut cons = None;
+    for variant in &parsed.variants {
+        match variant.fields.len() {
+            0 => {}
+            2 if cons.is_none() => cons = Some(variant),
+            2 => {
+                return Err(shape_error(
+                    variant.ident.span(),
+                    "found a second variant with two fields",
+                ))
+            }
+            _ => {
+                return Err(shape_error(
+                    variant.ident.span(),
+                    &format!("`{}` is neither", variant.ident),
+                ))
+            }
+        }
+    }
+    let Some(cons) = cons else {
+        return Err(shape_error(
+            parsed.ident.span(),
+            "found no variant with two fields",
+        ));
+    };
+    if parsed.variants.len() < 2 {
+        return Err(shape_error(
+            parsed.ident.span(),
+            "found no variant ending the list",
+        ));
+    }
+
+    let members = field_members(&cons.fields);
+    let fields: Vec<_> = cons.fields.iter().collect();
+    let variant_params = collect_all_type_param_names(&cons.generics);
+
+    let trait_type = resolve_trait_type(cons, &parsed.ident, generics_with_static);
+    let (mapping, unmapped) = own_param_mapping(&trait_type, generics_with_static);
+    let head_ty = fields[0].ty.to_token_stream();
+    if mentions(head_ty.clone(), &variant_params) || mentions(head_ty, &unmapped) {
+        return Err(syn::Error::new_spanned(
+            &fields[0].ty,
+            format!(
+                "the element type of `{}` must be expressible with the parameters of `{}`",
+                cons.ident, parsed.ident
+            ),
+        ));
+    }
+
+    let tail_is_generic = matches!(&fields[1].ty, Type::Path(path)
+        if path.path.get_ident().is_some_and(|ident| variant_params.contains(&ident.to_string())));
+
+    Ok(ListShape {
+        elem: substitute_in_type(&fields[0].ty, &mapping),
+        cons: cons.ident.clone(),
+        head: members[0].clone(),
+        tail: members[1].clone(),
+        tail_is_generic,
+    })
+}
+
+/// The element type as seen by a variant implementing `trait_type`
+fn elem_for(
+    shape: &ListShape,
+    trait_type: &TokenStream2,
+    all_type_params_ordered: &[String],
+) -> Type {
+    let mapping = all_type_params_ordered
+        .iter()
+        .cloned()
+        .zip(extract_trait_type_args(trait_type))
+        .collect();
+    substitute_in_type(&shape.elem, &mapping)
+}
+
+/// Items added to the enum's trait: the hidden node hooks and `iter()`
+pub fn generate_iter_decls(shape: &ListShape, enum_name: &Ident) -> TokenStream2 {
+    let box_ty = box_path();
+    let node_trait = node_trait_ident(enum_name);
+    let iter = iter_ident(enum_name);
+    let elem = &shape.elem;
+
+    quote! {
+        #[doc(hidden)]
+        fn __enum_typer_as_node(&self) -> &dyn #node_trait<#elem>;
+        #[doc(hidden)]
+        fn __enum_typer_into_node(self: #box_ty<Self>) -> #box_ty<dyn #node_trait<#elem>>;
+
+        /// Iterate over the elements of the list by reference
+        fn iter(&self) -> #iter<'_, #elem> {
+            #iter { node: ::core::option::Option::Some(self.__enum_typer_as_node()) }
+        }
+    }
+}
+
+/// The node hooks in the trait impl of one variant
+pub fn generate_iter_hook_impls(
+    shape: &ListShape,
+    enum_name: &Ident,
+    trait_type: &TokenStream2,
+    all_type_params_ordered: &[String],
+) -> TokenStream2 {
+    let box_ty = box_path();
+    let node_trait = node_trait_ident(enum_name);
+    let elem = elem_for(shape, trait_type, all_type_params_ordered);
+
+    quote! {
+        fn __enum_typer_as_node(&self) -> &dyn #node_trait<#elem> {
+            self
+        }
+        fn __enum_typer_into_node(self: #box_ty<Self>) -> #box_ty<dyn #node_trait<#elem>> {
+            self
+        }
+    }
+}
+
+/// The node impl of one variant: the cons variant splits into its element and
+/// the node of its tail, every other variant ends the list
+fn generate_node_impl(
+    shape: &ListShape,
+    variant: &ParsedVariant,
+    enum_name: &Ident,
+    generics_with_static: &Generics,
+    all_type_params_ordered: &[String],
+) -> TokenStream2 {
+    let box_ty = box_path();
+    let node_trait = node_trait_ident(enum_name);
+    let variant_name = &variant.ident;
+    let all_type_params: HashSet<String> = all_type_params_ordered.iter().cloned().collect();
+
+    let trait_type = resolve_trait_type(variant, enum_name, generics_with_static);
+    let elem = elem_for(shape, &trait_type, all_type_params_ordered);
+
+    // The struct's own parameters plus those of the element; other parameters
+    // of the trait impl would be unconstrained here
+    let struct_generics = variant_struct_generics(variant, generics_with_static, &all_type_params);
+    let (_, struct_ty_generics, _) = struct_generics.split_for_impl();
+    let mut used = collect_all_type_param_names(&struct_generics);
+    used.extend(extract_type_params_from_trait(
+        &elem.to_token_stream(),
+        &all_type_params,
+    ));
+    let impl_generics = merge_generics(
+        &add_static_bounds(&variant.generics),
+        generics_with_static,
+        &used,
+    );
+    let (impl_generics, _, where_clause) = impl_generics.split_for_impl();
+
+    let (split, split_owned) = if *variant_name == shape.cons {
+        let (head, tail) = (&shape.head, &shape.tail);
+        let owned_tail = if shape.tail_is_generic {
+            quote! { #box_ty::new(__tail).__enum_typer_into_node() }
+        } else {
+            quote! { __tail.__enum_typer_into_node() }
+        };
+        (
+            quote! {
+                ::core::option::Option::Some((&self.#head, self.#tail.__enum_typer_as_node()))
+            },
+            quote! {
+                let Self { #head: __head, #tail: __tail } = *self;
+                ::core::option::Option::Some((__head, #owned_tail))
+            },
+        )
+    } else {
+        (
+            quote! { ::core::option::Option::None },
+            quote! { ::core::option::Option::None },
+        )
+    };
+
+    quote! {
+        impl #impl_generics #node_trait<#elem> for #variant_name #struct_ty_generics #where_clause {
+            fn __split(&self) -> ::core::option::Option<(&#elem, &dyn #node_trait<#elem>)> {
+                #split
+            }
+            fn __split_owned(
+                self: #box_ty<Self>,
+            ) -> ::core::option::Option<(#elem, #box_ty<dyn #node_trait<#elem>>)> {
+                #split_owned
+            }
+        }
+    }
+}
+
+/// The node trait, its impls, the iterator types and `IntoIterator` for the
+/// boxed trait object
+pub fn generate_iter_items(
+    shape: &ListShape,
+    parsed: &ParsedEnum,
+    generics_with_static: &Generics,
+    all_type_params_ordered: &[String],
+) -> TokenStream2 {
+    let box_ty = box_path();
+    let enum_name = &parsed.ident;
+    let vis = &parsed.vis;
+    let node_trait = node_trait_ident(enum_name);
+    let iter = iter_ident(enum_name);
+    let into_iter = into_iter_ident(enum_name);
+    let elem = &shape.elem;
+    let lint_attrs = parsed.options.lint_attrs();
+
+    let node_impls = parsed.variants.iter().map(|variant| {
+        let node_impl = generate_node_impl(
+            shape,
+            variant,
+            enum_name,
+            generics_with_static,
+            all_type_params_ordered,
+        );
+        quote! {
+            #[automatically_derived]
+            #lint_attrs
+            #node_impl
+        }
+    });
+
+    let (impl_generics, ty_generics, where_clause) = generics_with_static.split_for_impl();
+    let iter_doc = format!(
+        "Iterator over the elements of a `{}` by reference",
+        enum_name
+    );
+    let into_iter_doc = format!("Iterator over the elements of a boxed `{}`", enum_name);
+
+    quote! {
+        #[doc(hidden)]
+        #lint_attrs
+        #vis trait #node_trait<__Item> {
+            fn __split(&self) -> ::core::option::Option<(&__Item, &dyn #node_trait<__Item>)>;
+            fn __split_owned(
+                self: #box_ty<Self>,
+            ) -> ::core::option::Option<(__Item, #box_ty<dyn #node_trait<__Item>>)>;
+        }
+
+        #(#node_impls)*
+
+        #[doc = #iter_doc]
+        #[doc(hidden)]
+        #lint_attrs
+        #vis struct #iter<'a, __Item> {
+            node: ::core::option::Option<&'a dyn #node_trait<__Item>>,
+        }
+
+        #[automatically_derived]
+        #lint_attrs
+        impl<'a, __Item> ::core::iter::Iterator for #iter<'a, __Item> {
+            type Item = &'a __Item;
+
+            fn next(&mut self) -> ::core::option::Option<&'a __Item> {
+                let (head, tail) = self.node.take()?.__split()?;
+                self.node = ::core::option::Option::Some(tail);
+                ::core::option::Option::Some(head)
+            }
+        }
+
+        #[doc = #into_iter_doc]
+        #[doc(hidden)]
+        #lint_attrs
+        #vis struct #into_iter<__Item> {
+            node: ::core::option::Option<#box_ty<dyn #node_trait<__Item>>>,
+        }
+
+        #[automatically_derived]
+        #lint_attrs
+        impl<__Item> ::core::iter::Iterator for #into_iter<__Item> {
+            type Item = __Item;
+
+            fn next(&mut self) -> ::core::option::Option<__Item> {
+                let (head, tail) = self.node.take()?.__split_owned()?;
+                self.node = ::core::option::Option::Some(tail);
+                ::core::option::Option::Some(head)
+            }
+        }
+
+        #[automatically_derived]
+        #lint_attrs
+        impl #impl_generics ::core::iter::IntoIterator for #box_ty<dyn #enum_name #ty_generics> #where_clause {
+            type Item = #elem;
+            type IntoIter = #into_iter<#elem>;
+
+            fn into_iter(self) -> #into_iter<#elem> {
+                #into_iter { node: ::core::option::Option::Some(self.__enum_typer_into_node()) }
+            }
+        }
+    }
+}
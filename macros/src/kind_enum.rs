@@ -0,0 +1,182 @@
+//! `#[type_enum(kind)]`: a plain Rust enum, `ShapeKind`, with one variant per
+//! variant struct and the same fields, converting to and from the boxed trait
+//! object. It can be matched exhaustively and takes the enum's derives.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use std::collections::HashSet;
+use syn::{Generics, Visibility};
+
+use crate::codegen::box_path;
+use crate::enum_parser::ParsedEnum;
+use crate::helpers::{cfg_attrs, collect_ordered_type_params, doc_attrs, field_members};
+use crate::variant_gen::{resolve_trait_type, variant_struct_generics};
+
+/// Reject enums whose variants don't all fit one `ShapeKind<..>`: variants
+/// at another index than the enum's own parameters, variants with generics
+/// of their own, and borrowing enums, which can't downcast
+pub fn check_kind(parsed: &ParsedEnum, generics_with_static: &Generics) -> syn::Result<()> {
+    if let Some(lifetime) = parsed.generics.lifetimes().next() {
+        return Err(syn::Error::new_spanned(
+            lifetime,
+            "`#[type_enum(kind)]` needs `'static` variants to downcast; \
+             enums with lifetime parameters can't use it",
+        ));
+    }
+    let enum_name = &parsed.ident;
+    let (_, ty_generics, _) = generics_with_static.split_for_impl();
+    let own_type = quote! { #enum_name #ty_generics }.to_string();
+    let mut errors: Option<syn::Error> = None;
+    for variant in &parsed.variants {
+        let e = if !variant.generics.params.is_empty() {
+            syn::Error::new_spanned(
+                &variant.generics,
+                format!(
+                    "`#[type_enum(kind)]` can't hold `{}` in `{}Kind`, as it has \
+                     generics of its own",
+                    variant.ident, enum_name
+                ),
+            )
+        } else if resolve_trait_type(variant, enum_name, generics_with_static).to_string()
+            != own_type
+        {
+            syn::Error::new_spanned(
+                &variant.trait_type,
+                format!(
+                    "`#[type_enum(kind)]` needs every variant at `{}`, as `{}Kind` \
+                     converts to a single trait object type",
+                    own_type.replace(' ', ""),
+                    enum_name
+                ),
+            )
+        } else {
+            continue;
+        };
+        match &mut errors {
+            Some(existing) => existing.combine(e),
+            None => errors = Some(e),
+        }
+    }
+    match errors {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// The `<Enum>Kind` enum and its conversions
+pub fn generate_kind_enum(
+    parsed: &ParsedEnum,
+    vis: &Visibility,
+    generics_with_static: &Generics,
+    lint_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let box_ty = box_path();
+    let enum_name = &parsed.ident;
+    let kind_name = format_ident!("{}Kind", enum_name);
+    let all_type_params: HashSet<String> = collect_ordered_type_params(&parsed.generics)
+        .into_iter()
+        .collect();
+    let (impl_generics, ty_generics, where_clause) = generics_with_static.split_for_impl();
+    // Generic variants implement the trait only where derived traits'
+    // bounds on their fields hold
+    let mut into_generics = generics_with_static.clone();
+    let derives = &parsed.options.derives;
+
+    let mut kind_variants = Vec::new();
+    let mut into_arms = Vec::new();
+    let mut from_checks = Vec::new();
+    for variant in &parsed.variants {
+        let name = &variant.ident;
+        let kind_variant = &variant.name;
+        let cfgs = cfg_attrs(&variant.attrs);
+        let docs = doc_attrs(&variant.attrs);
+        let struct_generics =
+            variant_struct_generics(variant, generics_with_static, &all_type_params);
+        let (_, struct_ty_generics, _) = struct_generics.split_for_impl();
+        if struct_generics.type_params().next().is_some() {
+            into_generics
+                .make_where_clause()
+                .predicates
+                .push(syn::parse_quote!(#name #struct_ty_generics: #enum_name #ty_generics));
+        }
+
+        // Enum variant fields take no visibility
+        let mut fields = variant.fields.clone();
+        for field in fields.iter_mut() {
+            field.vis = Visibility::Inherited;
+        }
+        let fields = match &fields {
+            syn::Fields::Named(named) => quote! { #named },
+            syn::Fields::Unnamed(unnamed) => quote! { #unnamed },
+            syn::Fields::Unit => TokenStream2::new(),
+        };
+        kind_variants.push(quote! {
+            #(#cfgs)*
+            #(#docs)*
+            #kind_variant #fields
+        });
+
+        let members = field_members(&variant.fields);
+        let bindings: Vec<_> = (0..members.len())
+            .map(|index| format_ident!("__field{}", index))
+            .collect();
+        into_arms.push(quote! {
+            #(#cfgs)*
+            #kind_name::#kind_variant { #(#members: #bindings),* } => {
+                #box_ty::new(#name { #(#members: #bindings),* })
+            }
+        });
+        from_checks.push(quote! {
+            #(#cfgs)*
+            if <dyn ::core::any::Any>::is::<#name #struct_ty_generics>(&*value) {
+                let any: #box_ty<dyn ::core::any::Any> = value;
+                let #name { #(#members: #bindings),* } = *any
+                    .downcast::<#name #struct_ty_generics>()
+                    .expect("checked with `is` above");
+                return ::core::result::Result::Ok(#kind_name::#kind_variant { #(#members: #bindings),* });
+            }
+        });
+    }
+
+    let kind_doc = format!(
+        "Every variant of [`{}`] as a plain enum, converting to and from \
+         `Box<dyn {}>`",
+        enum_name, enum_name
+    );
+    let (into_impl_generics, _, into_where_clause) = into_generics.split_for_impl();
+    quote! {
+        #[doc = #kind_doc]
+        #(#derives)*
+        #lint_attrs
+        #vis enum #kind_name #generics_with_static #where_clause {
+            #(#kind_variants,)*
+        }
+
+        #[automatically_derived]
+        #lint_attrs
+        impl #into_impl_generics ::core::convert::From<#kind_name #ty_generics>
+            for #box_ty<dyn #enum_name #ty_generics> #into_where_clause
+        {
+            fn from(kind: #kind_name #ty_generics) -> Self {
+                match kind {
+                    #(#into_arms)*
+                }
+            }
+        }
+
+        /// Hands the trait object back when it holds a type that isn't one
+        /// of the enum's variants
+        #[automatically_derived]
+        #lint_attrs
+        impl #impl_generics ::core::convert::TryFrom<#box_ty<dyn #enum_name #ty_generics>>
+            for #kind_name #ty_generics #where_clause
+        {
+            type Error = #box_ty<dyn #enum_name #ty_generics>;
+
+            fn try_from(value: #box_ty<dyn #enum_name #ty_generics>) -> ::core::result::Result<Self, Self::Error> {
+                #(#from_checks)*
+                ::core::result::Result::Err(value)
+            }
+        }
+    }
+}
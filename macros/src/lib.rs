@@ -0,0 +1,938 @@
+mod assertions;
+mod async_methods;
+mod clone_impls;
+mod codegen;
+mod constructors;
+mod debug_impls;
+mod display_impls;
+mod downcasts;
+mod dump;
+mod enum_parser;
+mod eq_impls;
+mod erasure;
+mod extend;
+mod hash_impls;
+mod helpers;
+mod impl_macro;
+mod iteration;
+mod kind_enum;
+mod match_macro;
+mod metadata;
+mod namespace;
+mod ops;
+mod options;
+mod pattern_parser;
+mod serde_impls;
+mod type_analysis;
+mod validation;
+mod variant_gen;
+
+use proc_macro::TokenStream;
+use proc_macro2::{Delimiter, Ident, Span, TokenStream as TokenStream2, TokenTree};
+use quote::quote;
+use std::collections::HashSet;
+
+use codegen::{allocates, box_path, discriminant_hook_ident, type_name_hook_ident};
+use enum_parser::ParsedEnum;
+use erasure::{erase_variants, generate_hook_decls};
+use helpers::{add_static_bounds, cfg_attrs, collect_ordered_type_params};
+use match_macro::{expand_match_t, EnumInfo};
+use metadata::{companion_call, generate_metadata_macro, metadata_macro_path};
+use pattern_parser::parse_match_t;
+use variant_gen::{generate_variant_code, EnumContext};
+
+/// Function-like macro for converting enums to traits with struct variants.
+/// It supports optional type indexing per variant and method definitions with
+/// pattern/body arms and existential return types.
+///
+/// # Example
+///
+/// Lift an enum definition into a trait with struct variants.
+///
+/// ```ignore
+/// type_enum! {
+///     pub enum Either<A, E> {
+///         Right(A),
+///         Left(E),
+///     }
+/// }
+/// ```
+///
+/// Or with indexed types. It is a feature similar to GADTs in other languages,
+/// where each variant can refine the overall type with specific type arguments.
+///
+/// ```ignore
+/// type_enum! {
+///    enum Expr<T> {
+///       LitInt(i32) : Expr<i32>,
+///       LitBool(bool) : Expr<bool>,
+///       Add(Box<Expr<i32>>, Box<Expr<i32>>) : Expr<i32>,
+///       Or(Box<Expr<bool>>, Box<Expr<bool>>) : Expr<bool>,
+///    }
+/// }
+/// ```
+///
+/// Or with functions using existential return types
+///
+/// ```ignore
+/// type_enum! {
+///    enum Expr<T> { ... }
+///
+///    fn eval(&self) -> T {
+///       LitInt(i) => *i,
+///       LitBool(b) => *b,
+///       Add(lhs, rhs) => lhs.eval() + rhs.eval(),
+///       Or(lhs, rhs) => lhs.eval() || rhs.eval(),
+///    }
+/// }
+/// ```
+#[proc_macro]
+pub fn type_enum(input: TokenStream) -> TokenStream {
+    let mut parsed = match syn::parse::<ParsedEnum>(input) {
+        Ok(p) => p,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    if let Err(e) = validation::validate_names(&parsed) {
+        return e.to_compile_error().into();
+    }
+
+    if let Err(e) = validation::validate_trait_types(&parsed) {
+        return e.to_compile_error().into();
+    }
+
+    if let Err(e) = validation::validate_default_variant(&parsed) {
+        return e.to_compile_error().into();
+    }
+
+    if let Err(e) = validation::validate_lifetimes(&parsed) {
+        return e.to_compile_error().into();
+    }
+
+    if let Err(e) = validation::validate_method_arms(&parsed) {
+        return e.to_compile_error().into();
+    }
+
+    if parsed.options.serde {
+        if let Err(e) = serde_impls::check_serde(&parsed) {
+            return e.to_compile_error().into();
+        }
+    }
+
+    if parsed.options.typetag {
+        if let Err(e) = serde_impls::check_typetag(&parsed) {
+            return e.to_compile_error().into();
+        }
+    }
+
+    if parsed.options.extensible {
+        if let Err(e) = metadata::check_extensible(&parsed) {
+            return e.to_compile_error().into();
+        }
+    }
+
+    // Items generated inside the namespace module see the enum's visibility
+    // one module further down
+    let namespace = namespace::namespace_ident(&parsed);
+    let outer_vis = parsed.vis.clone();
+    if let Some(module) = &namespace {
+        if let Err(e) = namespace::check_namespace(&parsed, module) {
+            return e.to_compile_error().into();
+        }
+        namespace::move_into_namespace(&mut parsed);
+    }
+
+    let enum_name = &parsed.ident;
+    let vis = &parsed.vis;
+    let generics = &parsed.generics;
+
+    let all_type_params_ordered = collect_ordered_type_params(generics);
+    let all_type_params: HashSet<String> = all_type_params_ordered.iter().cloned().collect();
+
+    let generics_with_static = add_static_bounds(generics);
+    let (_impl_generics_static, _, where_clause_static) = generics_with_static.split_for_impl();
+
+    let erased = erase_variants(&parsed, &generics_with_static);
+    let views = downcasts::view_variants(&parsed, &generics_with_static, &all_type_params);
+    let list = if parsed.options.iter {
+        match iteration::list_shape(&parsed, &generics_with_static) {
+            Ok(shape) => Some(shape),
+            Err(e) => return e.to_compile_error().into(),
+        }
+    } else {
+        None
+    };
+
+    let clone_bound = clone_impls::clone_object_bound(&parsed);
+    let compares_dyn = eq_impls::compares_dyn(&parsed);
+    let hashes_dyn = hash_impls::hashes_dyn(&parsed);
+    let display_source = display_impls::display_source(&parsed);
+
+    let ctx = EnumContext {
+        enum_name,
+        vis,
+        methods: &parsed.methods,
+        impls: &parsed.impls,
+        generics_with_static: &generics_with_static,
+        all_type_params: &all_type_params,
+        all_type_params_ordered: &all_type_params_ordered,
+        erased: &erased,
+        views: &views,
+        options: &parsed.options,
+        list: list.as_ref(),
+        clone_bound: clone_bound.as_ref(),
+        compares_dyn,
+        hashes_dyn,
+        displays_dyn: display_source == Some(display_impls::DisplaySource::Section),
+        // An enum method of the same name takes its place
+        names_variants: parsed.options.implements.is_none()
+            && !parsed.methods.iter().any(|m| m.sig.ident == "variant_name"),
+        discriminants: parsed
+            .options
+            .implements
+            .is_none()
+            .then_some(parsed.variants.as_slice()),
+    };
+
+    let structs_and_impls: Vec<_> = parsed
+        .variants
+        .iter()
+        .map(|variant| generate_variant_code(variant, &ctx))
+        .collect();
+
+    let method_decls = parsed.methods.iter().map(|m| {
+        let attrs = m
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("doc") || a.path().is_ident("deprecated"));
+        let sig = &m.sig;
+        match &m.default_body {
+            Some(body) if m.is_async => {
+                let body = async_methods::boxed_future(sig, quote! { #body });
+                quote! { #(#attrs)* #sig { #body } }
+            }
+            Some(body) => quote! { #(#attrs)* #sig #body },
+            None => quote! { #(#attrs)* #sig; },
+        }
+    });
+    let hook_decls = generate_hook_decls(&erased, enum_name, &generics_with_static);
+    let view_decls = downcasts::generate_view_decls(&views);
+    let (iter_decls, iter_items) = match &list {
+        Some(shape) => (
+            iteration::generate_iter_decls(shape, enum_name),
+            iteration::generate_iter_items(
+                shape,
+                &parsed,
+                &generics_with_static,
+                &all_type_params_ordered,
+            ),
+        ),
+        None => (TokenStream2::new(), TokenStream2::new()),
+    };
+
+    let index_type_id_decl = if parsed.options.index_type_id {
+        match generate_index_type_id_decl(&parsed) {
+            Ok(decl) => decl,
+            Err(e) => return e.to_compile_error().into(),
+        }
+    } else {
+        TokenStream2::new()
+    };
+    let variant_name_decl = ctx.names_variants.then(|| {
+        quote! {
+            /// The name of the variant this value is, as declared in the enum.
+            /// Types implementing the trait outside the enum give their type
+            /// name.
+            fn variant_name(&self) -> &'static str {
+                ::core::any::type_name::<Self>()
+            }
+        }
+    });
+    let discriminant_decl = ctx.discriminants.is_some().then(|| {
+        let discriminant = discriminant_hook_ident();
+        let type_name = type_name_hook_ident();
+        // Left to the default, which names each implementing type
+        quote! {
+            #[doc(hidden)]
+            fn #discriminant(&self) -> usize {
+                usize::MAX
+            }
+            #[doc(hidden)]
+            fn #type_name(&self) -> &'static str {
+                ::core::any::type_name::<Self>()
+            }
+        }
+    });
+    let clone_decl = clone_bound.as_ref().map(|object_bound| {
+        clone_impls::generate_clone_decl(enum_name, &generics_with_static, object_bound)
+    });
+    let eq_decl =
+        compares_dyn.then(|| eq_impls::generate_eq_decl(enum_name, &generics_with_static));
+    let hash_decl = hashes_dyn.then(hash_impls::generate_hash_decl);
+    let debug_decl = parsed.options.debug.then(debug_impls::generate_debug_decl);
+    let display_decl = ctx.displays_dyn.then(display_impls::generate_display_decl);
+
+    // Variants borrowing for an enum lifetime are not `'static`, so not `Any`
+    let borrows = generics.lifetimes().next().is_some();
+    let any = (!borrows).then(|| quote! { ::core::any::Any });
+    let supertraits = any
+        .into_iter()
+        .chain(parsed.supertraits.iter().map(|bound| quote! { #bound }));
+    let supertrait = quote! { #(#supertraits)+* };
+
+    let lint_attrs = parsed.options.lint_attrs();
+    let typetag = parsed
+        .options
+        .typetag
+        .then(|| quote! { #[::typetag::serde] });
+    // The user's trait stands in for the generated one under the enum's
+    // name. The alias stays outside a namespace module, where the trait's
+    // path was written, and the module reaches it through `use super::*`.
+    let trait_alias = match &parsed.options.implements {
+        Some(path) if !path.is_ident(enum_name) => quote! {
+            #[allow(unused_imports)]
+            #outer_vis use #path as #enum_name;
+        },
+        _ => TokenStream2::new(),
+    };
+    let trait_def = match &parsed.options.implements {
+        Some(_) => TokenStream2::new(),
+        None => quote! {
+            #lint_attrs
+            #typetag
+            #vis trait #enum_name #generics_with_static: #supertrait #where_clause_static {
+                #(#method_decls)*
+                #index_type_id_decl
+                #variant_name_decl
+                #discriminant_decl
+                #debug_decl
+                #display_decl
+                #clone_decl
+                #eq_decl
+                #hash_decl
+                #hook_decls
+                #view_decls
+                #iter_decls
+            }
+        },
+    };
+
+    let variant_consts = if parsed.options.implements.is_some() {
+        TokenStream2::new()
+    } else {
+        generate_variant_consts(&parsed, &generics_with_static, &lint_attrs)
+    };
+    let unused_index_warnings = validation::unused_index_warnings(&parsed, &generics_with_static);
+    let unsized_method_warnings = validation::unsized_method_warnings(&parsed);
+    let index_cast = if parsed.options.implements.is_some() || borrows || !allocates() {
+        TokenStream2::new()
+    } else {
+        generate_index_cast(enum_name, &generics_with_static, &lint_attrs)
+    };
+    let downcast_helpers = if parsed.options.implements.is_some() {
+        TokenStream2::new()
+    } else {
+        downcasts::generate_downcast_helpers(
+            &parsed,
+            &generics_with_static,
+            &all_type_params,
+            &views,
+            &lint_attrs,
+        )
+    };
+    let dyn_debug = if parsed.options.debug {
+        debug_impls::generate_dyn_debug(enum_name, &generics_with_static, &lint_attrs)
+    } else {
+        TokenStream2::new()
+    };
+    let dyn_display = match display_source {
+        Some(source) => display_impls::generate_dyn_display(
+            source,
+            enum_name,
+            &generics_with_static,
+            &lint_attrs,
+        ),
+        None => TokenStream2::new(),
+    };
+    let box_clone = match &clone_bound {
+        Some(object_bound) => clone_impls::generate_box_clone(
+            enum_name,
+            &generics_with_static,
+            object_bound,
+            &lint_attrs,
+        ),
+        None => TokenStream2::new(),
+    };
+    let dyn_eq = if compares_dyn {
+        let eq = eq_impls::derives_eq(&parsed);
+        eq_impls::generate_dyn_eq(enum_name, &generics_with_static, eq, &lint_attrs)
+    } else {
+        TokenStream2::new()
+    };
+    let dyn_hash = if hashes_dyn {
+        hash_impls::generate_dyn_hash(enum_name, &generics_with_static, &lint_attrs)
+    } else {
+        TokenStream2::new()
+    };
+    let kind_enum = if parsed.options.kind {
+        match kind_enum::check_kind(&parsed, &generics_with_static) {
+            Ok(()) => {
+                kind_enum::generate_kind_enum(&parsed, vis, &generics_with_static, &lint_attrs)
+            }
+            Err(e) => return e.to_compile_error().into(),
+        }
+    } else {
+        TokenStream2::new()
+    };
+    let constructor_module = match &parsed.options.constructors {
+        Some(module) => {
+            let module = module
+                .clone()
+                .unwrap_or_else(|| helpers::snake_case_ident(enum_name));
+            constructors::generate_constructor_module(
+                &parsed,
+                &module,
+                &generics_with_static,
+                &all_type_params,
+                &lint_attrs,
+            )
+        }
+        None => TokenStream2::new(),
+    };
+    let serde_impls = if parsed.options.serde {
+        serde_impls::generate_serde_impls(&parsed, &generics_with_static, &lint_attrs)
+    } else {
+        TokenStream2::new()
+    };
+    let metadata_macro = generate_metadata_macro(&parsed, &generics_with_static);
+    let operator_impls = match ops::generate_operator_impls(&parsed, &generics_with_static) {
+        Ok(impls) => impls,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let expanded = quote! {
+        #trait_def
+        #index_cast
+        #variant_consts
+        #downcast_helpers
+        #dyn_debug
+        #dyn_display
+        #box_clone
+        #dyn_eq
+        #dyn_hash
+        #serde_impls
+        #kind_enum
+        #constructor_module
+        #(#structs_and_impls)*
+        #operator_impls
+        #iter_items
+        #metadata_macro
+        #unused_index_warnings
+        #unsized_method_warnings
+    };
+    let expanded = match &namespace {
+        Some(module) => match namespace::wrap_in_namespace(expanded, &parsed, module, &outer_vis) {
+            Ok(wrapped) => quote! { #trait_alias #wrapped },
+            Err(e) => return e.to_compile_error().into(),
+        },
+        None => quote! { #trait_alias #expanded },
+    };
+
+    dump::dump_expansion(
+        &format!("type_enum-{}", enum_name),
+        enum_name.span(),
+        &expanded,
+    );
+
+    TokenStream::from(expanded)
+}
+
+/// `index_type_id()`, the `TypeId` of the index a value implements the trait
+/// at: `T` for one parameter, `(A, B, ..)` for several. A const parameter `N`
+/// stands in as `[(); N]`.
+fn generate_index_type_id_decl(parsed: &ParsedEnum) -> syn::Result<TokenStream2> {
+    let params: Vec<_> = parsed
+        .generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            syn::GenericParam::Type(tp) => {
+                let ident = &tp.ident;
+                Some(quote! { #ident })
+            }
+            syn::GenericParam::Const(cp) => {
+                let ident = &cp.ident;
+                Some(quote! { [(); #ident] })
+            }
+            syn::GenericParam::Lifetime(_) => None,
+        })
+        .collect();
+    let index = match params.as_slice() {
+        [] => {
+            return Err(syn::Error::new(
+                parsed.ident.span(),
+                format!(
+                    "`#[type_enum(index_type_id)]` reports the index `{}` is implemented at, \
+                     so it needs an enum with type or const parameters",
+                    parsed.ident
+                ),
+            ))
+        }
+        [param] => quote! { #param },
+        params => quote! { (#(#params),*) },
+    };
+
+    Ok(quote! {
+        /// The `TypeId` of the index this value implements the trait at, for
+        /// routing trait objects by index at runtime
+        fn index_type_id(&self) -> ::core::any::TypeId {
+            ::core::any::TypeId::of::<#index>()
+        }
+    })
+}
+
+/// `VARIANT_NAMES` and `VARIANT_COUNT` on the trait object type, counting
+/// only the variants compiled in
+fn generate_variant_consts(
+    parsed: &ParsedEnum,
+    generics_with_static: &syn::Generics,
+    lint_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let enum_name = &parsed.ident;
+    let names = parsed.variants.iter().map(|variant| {
+        let cfgs = cfg_attrs(&variant.attrs);
+        let name = variant.name.to_string();
+        quote! { #(#cfgs)* #name }
+    });
+    let (impl_generics, ty_generics, where_clause) = generics_with_static.split_for_impl();
+    quote! {
+        #lint_attrs
+        impl #impl_generics dyn #enum_name #ty_generics + '_ #where_clause {
+            /// The names of the enum's variants, in declaration order
+            pub const VARIANT_NAMES: &'static [&'static str] = &[#(#names),*];
+            /// The number of variants in the enum
+            pub const VARIANT_COUNT: usize = Self::VARIANT_NAMES.len();
+        }
+    }
+}
+
+/// `try_cast_index`, narrowing a boxed trait object to another index when it
+/// is in fact at that index. Boxing the box as `dyn Any` makes the check a
+/// plain downcast to `Box<dyn Enum<U>>`.
+fn generate_index_cast(
+    enum_name: &syn::Ident,
+    generics_with_static: &syn::Generics,
+    lint_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let box_ty = box_path();
+    let targets: Vec<_> = generics_with_static
+        .type_params()
+        .map(|param| quote::format_ident!("__Cast{}", param.ident))
+        .collect();
+    if targets.is_empty() {
+        return TokenStream2::new();
+    }
+    // Const parameters stay as they are; only the type index is cast
+    let mut cast_targets = targets.iter();
+    let target_args: Vec<_> = generics_with_static
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            syn::GenericParam::Type(_) => cast_targets.next().map(|target| quote! { #target }),
+            syn::GenericParam::Const(cp) => {
+                let ident = &cp.ident;
+                Some(quote! { #ident })
+            }
+            syn::GenericParam::Lifetime(_) => None,
+        })
+        .collect();
+
+    let (impl_generics, ty_generics, where_clause) = generics_with_static.split_for_impl();
+    quote! {
+        #lint_attrs
+        impl #impl_generics dyn #enum_name #ty_generics #where_clause {
+            /// Narrow to the trait at another index, succeeding exactly when
+            /// that is the index this value implements it at, and handing the
+            /// value back otherwise
+            pub fn try_cast_index<#(#targets: 'static),*>(
+                self: #box_ty<Self>,
+            ) -> ::core::result::Result<#box_ty<dyn #enum_name<#(#target_args),*>>, #box_ty<Self>> {
+                let any: #box_ty<dyn ::core::any::Any> = #box_ty::new(self);
+                match any.downcast::<#box_ty<dyn #enum_name<#(#target_args),*>>>() {
+                    ::core::result::Result::Ok(cast) => ::core::result::Result::Ok(*cast),
+                    ::core::result::Result::Err(any) => ::core::result::Result::Err(
+                        *any.downcast::<#box_ty<Self>>()
+                            .expect("a value handed back by a failed downcast keeps its type"),
+                    ),
+                }
+            }
+        }
+    }
+}
+
+/// Pattern match on trait objects based on their concrete types.
+/// It supports both reference (`&dyn Trait`) and boxed (`Box<dyn Trait>`)
+/// trait objects.
+///
+/// Use `move` keyword to indicate ownership transfer when matching on `Box<dyn Trait>`,
+/// `Rc<dyn Trait>` or `Arc<dyn Trait>`, cloning the value out of a shared `Rc`
+/// or `Arc`, or `mut` to match a `&mut dyn Trait` and bind fields by mutable
+/// reference.
+/// Arms are tried in order, and the box is only consumed by an arm whose
+/// whole pattern matches. An arm may be an or-pattern over several variants,
+/// as in `Rectangle(w, _) | Square(w) => ..`, each case tested for its own type.
+/// `c @ Circle(_) => ..` binds the variant's struct, and `Add(box Num(0), e)`
+/// downcasts the boxed field and matches it against the nested pattern.
+/// `match_t!((lhs, rhs) { (Num(a), Num(b)) => .. })` matches several values at
+/// once, with `_` elements matching any value.
+/// `match_t!(sum: Box<dyn Sum<A, B>> { .. })` writes out the scrutinee's type,
+/// which hints its enum like `as Sum<A, B>` when there is no `as`.
+/// Arm bodies run in the enclosing function, so `?` and `return` leave it as
+/// they would in a `match`.
+///
+/// A value no arm matches panics with its type name and the types the arms
+/// tried, unless the last arm is `_ => ..`. With
+/// `try` in front, `match_t!(try x { .. })` yields `Option<R>` and
+/// `match_t!(try move x { .. })` yields `Result<R, Box<..>>`, handing back the
+/// box no arm matched. An `else |value| { .. }` clause after the arms runs
+/// instead with the value no arm matched, the box itself under `move`.
+///
+/// # Example
+///
+/// ```ignore
+/// type_enum! {
+///     enum Tree<T: Display> {
+///         Leaf(T),
+///         Node(Box<Tree<T>>, Box<Tree<T>>),
+///     }
+/// }
+///
+/// let tree: Box<dyn Tree<i32>> = Box::new(...);
+/// let tree_ref: &dyn Tree<i32> = &...;
+/// let describe = match_t! {
+///     move tree {
+///         Leaf(value) => format!("Leaf: {}", value),
+///         Node(left, right) => format!("Node with left and right"),
+///     }
+/// }
+/// let describe_ref = match_t! {
+///     tree_ref {
+///         Leaf(value) => format!("Leaf: {}", value),
+///         Node(left, right) => format!("Node with left and right"),
+///     }
+/// }
+/// ```
+///
+/// # Type hints
+///
+/// Naming the enum with `as` gives `match_t!` access to its definition. The
+/// hint must be a path through which the `type_enum!` trait is reachable, e.g.
+/// `as Nat`, `as shapes::Shape<i32>` or `as Box<dyn Shape>`. The hidden
+/// companion macro shares the trait's name, so importing the trait brings it
+/// along. A hint naming a trait without a companion in reach, such as a plain
+/// trait or an enum of another crate that isn't `extensible`, matches by
+/// downcasting alone, as without a hint.
+///
+/// With a hint, variants that have generics of their own can be matched
+/// without naming them. Fields of such a generic type are handed out as trait
+/// objects of its bound, e.g. `Box<dyn Nat>` when moving out of
+/// `Succ<N: Nat>(N)` and `&dyn Nat` when matching by reference:
+///
+/// ```ignore
+/// fn to_u32(n: Box<dyn Nat>) -> u32 {
+///     match_t!(move n as Nat {
+///         Zero => 0,
+///         Succ(pred) => 1 + to_u32(pred),
+///     })
+/// }
+/// ```
+///
+/// A hinted match is checked for exhaustiveness: every variant a value of the
+/// hinted type can hold needs an arm matching all of its values, unless the
+/// last arm is `_ => ..`, which takes whatever no other arm matched.
+///
+/// An enum with lifetime parameters has no `Any` supertrait, so its values
+/// are only matched with a hint naming it alone, by reference or under `mut`.
+/// `move` and hints naming several enums are rejected.
+#[proc_macro]
+pub fn match_t(input: TokenStream) -> TokenStream {
+    let tokens = TokenStream2::from(input);
+    let input_parsed = match parse_match_t(tokens.clone()) {
+        Ok(parsed) => parsed,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    // With type hints, let the enums' companion macros hand us their
+    // definitions, starting with the first
+    let calls: Option<Vec<_>> = input_parsed
+        .type_hints
+        .iter()
+        .map(|hint| companion_call(hint, tokens.clone()))
+        .collect();
+    if let Some(call) = calls.and_then(|calls| calls.into_iter().next()) {
+        return call.into();
+    }
+
+    match expand_match_t(&input_parsed, &[]) {
+        Ok(expanded) => {
+            dump::dump_expansion("match_t", first_span(&input_parsed.expr), &expanded);
+            expanded.into()
+        }
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Implementation detail of `match_t!`: the enum definitions replayed by the
+/// companion macros of `type_enum!`, most recent first, followed by the
+/// original `match_t!` input. A hint whose trait has no companion in reach
+/// stands as `{ @opaque Enum }` instead, and is matched without a definition.
+/// While a type hint still lacks its definition, this hands everything to
+/// that enum's companion macro in turn.
+#[doc(hidden)]
+#[proc_macro]
+pub fn __match_t_with(input: TokenStream) -> TokenStream {
+    let mut tokens = TokenStream2::from(input).into_iter().peekable();
+    let mut definitions = Vec::new();
+    let mut opaque = Vec::new();
+    while let Some(TokenTree::Group(group)) = tokens.peek() {
+        if group.delimiter() != Delimiter::Brace {
+            break;
+        }
+        if let Some(enum_name) = opaque_hint(&group.stream()) {
+            opaque.push(enum_name);
+        } else if !is_enum_definition(&group.stream()) {
+            break;
+        }
+        definitions.push(group.clone());
+        tokens.next();
+    }
+    if definitions.is_empty() {
+        return syn::Error::new(Span::call_site(), "expected an enum definition")
+            .to_compile_error()
+            .into();
+    }
+
+    let mut parsed = Vec::new();
+    for definition in &definitions {
+        if opaque_hint(&definition.stream()).is_some() {
+            continue;
+        }
+        match syn::parse2::<ParsedEnum>(definition.stream()) {
+            Ok(p) => parsed.push(p),
+            Err(e) => return e.to_compile_error().into(),
+        }
+    }
+    let rest: TokenStream2 = tokens.collect();
+    let mut input_parsed = match parse_match_t(rest.clone()) {
+        Ok(parsed) => parsed,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let missing = input_parsed.type_hints.iter().find(|hint| {
+        let named = metadata::hint_enum_ident(hint);
+        !parsed.iter().any(|p| Some(&p.ident) == named.as_ref())
+            && !opaque.iter().any(|o| Some(o) == named.as_ref())
+    });
+    if let Some(call) =
+        missing.and_then(|hint| companion_call(hint, quote! { #(#definitions)* #rest }))
+    {
+        return call.into();
+    }
+
+    let mut enum_names: Vec<_> = parsed.iter().rev().map(|p| p.ident.to_string()).collect();
+    enum_names.insert(0, "match_t".to_string());
+    let infos: Vec<_> = parsed
+        .into_iter()
+        .rev()
+        .map(|p| EnumInfo::new(p, &input_parsed.type_hints))
+        .collect();
+    match_macro::resolve_arm_heads(&mut input_parsed.arms, &infos);
+    match expand_match_t(&input_parsed, &infos) {
+        Ok(expanded) => {
+            dump::dump_expansion(
+                &enum_names.join("-"),
+                first_span(&input_parsed.expr),
+                &expanded,
+            );
+            expanded.into()
+        }
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Add methods to the variant structs of an enum defined by `type_enum!`
+/// elsewhere, in the same arm syntax `type_enum!` uses for its methods. Each
+/// variant with an arm gets an inherent method; when every variant has one
+/// and sees the same signature, the trait object gets a method of the same
+/// name that forwards to the right variant.
+///
+/// # Example
+///
+/// ```ignore
+/// type_enum_impl! {
+///     shapes::Shape =>
+///
+///     pub fn area(&self) -> f64 {
+///         Circle(r) => 3.14 * r * r,
+///         Square(side) => side * side,
+///     }
+/// }
+///
+/// let shape: Box<dyn shapes::Shape> = Box::new(shapes::Circle(1.0));
+/// assert!(shape.area() > 3.0);
+/// ```
+#[proc_macro]
+pub fn type_enum_impl(input: TokenStream) -> TokenStream {
+    let tokens = TokenStream2::from(input);
+    let enum_path = match syn::parse2::<impl_macro::TypeEnumImplInput>(tokens.clone()) {
+        Ok(parsed) => parsed.enum_path,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    match metadata_macro_path(&quote! { #enum_path }) {
+        Some(metadata_macro) => quote! { #metadata_macro! { @impl #tokens } }.into(),
+        None => syn::Error::new_spanned(enum_path, "expected the path of a `type_enum!` enum")
+            .to_compile_error()
+            .into(),
+    }
+}
+
+/// Implementation detail of `type_enum_impl!`: the enum definition replayed
+/// by its companion macro, followed by the original `type_enum_impl!` input
+#[doc(hidden)]
+#[proc_macro]
+pub fn __type_enum_impl_with(input: TokenStream) -> TokenStream {
+    let mut tokens = TokenStream2::from(input).into_iter();
+    let definition = match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => group,
+        _ => {
+            return syn::Error::new(Span::call_site(), "expected an enum definition")
+                .to_compile_error()
+                .into()
+        }
+    };
+    let parsed = match syn::parse2::<ParsedEnum>(definition.stream()) {
+        Ok(parsed) => parsed,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let input_parsed = match syn::parse2::<impl_macro::TypeEnumImplInput>(tokens.collect()) {
+        Ok(parsed) => parsed,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let enum_name = parsed.ident.to_string();
+    let enum_path = &input_parsed.enum_path;
+    let at = first_span(&quote! { #enum_path });
+    match impl_macro::expand_type_enum_impl(parsed, input_parsed) {
+        Ok(expanded) => {
+            dump::dump_expansion(&format!("type_enum_impl-{}", enum_name), at, &expanded);
+            expanded.into()
+        }
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Add variants to an enum defined by `type_enum!`, with arms for the enum's
+/// methods and `impl` sections. The new variant structs implement the enum's
+/// trait, so they box into the same trait objects. From another crate, the
+/// enum needs `#[type_enum(extensible)]`.
+///
+/// # Example
+///
+/// ```ignore
+/// extend_enum! {
+///     pub shapes::Shape {
+///         Hexagon(f64),
+///     }
+///
+///     fn area(&self) -> f64 {
+///         Hexagon(side) => 2.598 * side * side,
+///     }
+/// }
+///
+/// let shape: Box<dyn shapes::Shape> = Hexagon(1.0).boxed();
+/// assert!(shape.area() > 2.5);
+/// ```
+#[proc_macro]
+pub fn extend_enum(input: TokenStream) -> TokenStream {
+    let tokens = TokenStream2::from(input);
+    let enum_path = match syn::parse2::<extend::ExtendEnumInput>(tokens.clone()) {
+        Ok(parsed) => parsed.enum_path,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    match metadata_macro_path(&quote! { #enum_path }) {
+        Some(metadata_macro) => quote! { #metadata_macro! { @extend #tokens } }.into(),
+        None => syn::Error::new_spanned(enum_path, "expected the path of a `type_enum!` enum")
+            .to_compile_error()
+            .into(),
+    }
+}
+
+/// Implementation detail of `extend_enum!`: the enum definition replayed by
+/// its companion macro, followed by the original `extend_enum!` input
+#[doc(hidden)]
+#[proc_macro]
+pub fn __extend_enum_with(input: TokenStream) -> TokenStream {
+    let mut tokens = TokenStream2::from(input).into_iter();
+    let definition = match tokens.next() {
+        Some(TokenTree::Group(group)) if group.delimiter() == Delimiter::Brace => group,
+        _ => {
+            return syn::Error::new(Span::call_site(), "expected an enum definition")
+                .to_compile_error()
+                .into()
+        }
+    };
+    let parsed = match syn::parse2::<ParsedEnum>(definition.stream()) {
+        Ok(parsed) => parsed,
+        Err(e) => return e.to_compile_error().into(),
+    };
+    let input_parsed = match syn::parse2::<extend::ExtendEnumInput>(tokens.collect()) {
+        Ok(parsed) => parsed,
+        Err(e) => return e.to_compile_error().into(),
+    };
+
+    let enum_name = parsed.ident.to_string();
+    let enum_path = &input_parsed.enum_path;
+    let at = first_span(&quote! { #enum_path });
+    match extend::expand_extend_enum(parsed, input_parsed) {
+        Ok(expanded) => {
+            dump::dump_expansion(&format!("extend_enum-{}", enum_name), at, &expanded);
+            expanded.into()
+        }
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// The span of the first of some tokens, whose place in the source names the
+/// dump of an expansion
+fn first_span(tokens: &TokenStream2) -> Span {
+    tokens
+        .clone()
+        .into_iter()
+        .next()
+        .map_or_else(Span::call_site, |token| token.span())
+}
+
+/// The enum of a `{ @opaque Enum }` group, which stands for a hinted trait
+/// without a companion macro in reach
+fn opaque_hint(stream: &TokenStream2) -> Option<Ident> {
+    let mut tokens = stream.clone().into_iter();
+    match (tokens.next(), tokens.next(), tokens.next(), tokens.next()) {
+        (
+            Some(TokenTree::Punct(at)),
+            Some(TokenTree::Ident(marker)),
+            Some(TokenTree::Ident(enum_name)),
+            None,
+        ) if at.as_char() == '@' && marker == "opaque" => Some(enum_name),
+        _ => None,
+    }
+}
+
+/// Whether a brace group holds a replayed enum definition rather than the
+/// start of a `match_t!` scrutinee
+fn is_enum_definition(stream: &TokenStream2) -> bool {
+    match stream.clone().into_iter().next() {
+        Some(TokenTree::Ident(ident)) => ident == "enum",
+        Some(TokenTree::Punct(punct)) => punct.as_char() == '#',
+        _ => false,
+    }
+}
@@ -0,0 +1,1696 @@
+//! `match_t!` expansion
+
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{quote, quote_spanned, ToTokens};
+use std::collections::{HashMap, HashSet};
+use syn::spanned::Spanned;
+use syn::visit_mut::{self, VisitMut};
+use syn::{Ident, Pat};
+
+use crate::codegen::{
+    apply_type_hint_to_pattern, arc_path, box_path, discriminant_hook_ident, fields_tuple_pattern,
+    rc_path, type_name_hook_ident, unreachable_branch,
+};
+use crate::downcasts::{view_hook_ident, view_mut_hook_ident, view_variants, ViewedVariant};
+use crate::enum_parser::{ParsedEnum, ParsedVariant};
+use crate::erasure::{erase_variants, into_hook_ident, mentions, ref_hook_ident, ErasedVariant};
+use crate::helpers::{add_static_bounds, collect_ordered_type_params, extract_trait_type_args};
+use crate::metadata::{hint_enum_ident, hint_trait_path};
+use crate::namespace::namespace_ident;
+use crate::pattern_parser::{
+    extract_generics_from_type_hint, extract_type_and_pattern, split_or_pattern,
+    split_tuple_pattern, ElseClause, MatchArm, MatchTInput,
+};
+use crate::validation::{check_pattern_shape, parse_arm_pat, pat_head_ident, replace_arm_head};
+use crate::variant_gen::variant_struct_generics;
+
+/// What `match_t!` knows about an enum named by one of its type hints
+pub struct EnumInfo {
+    parsed: ParsedEnum,
+    erased: Vec<ErasedVariant>,
+    /// View hooks of a borrowing enum, which has no `Any` to downcast with
+    views: Vec<ViewedVariant>,
+    /// Type and const parameters of the enum, in declaration order
+    enum_params: Vec<String>,
+    /// Type and const parameters of the structs of variants that take
+    /// generic arguments
+    generic_structs: HashMap<String, Vec<String>>,
+    /// Generic arguments of the hint naming this enum, e.g. `<i32>`
+    hint_generics: Option<TokenStream2>,
+    /// Path of the namespace module holding the variant structs, for enums
+    /// declared with `#[type_enum(namespace)]`
+    namespace: Option<TokenStream2>,
+}
+
+impl EnumInfo {
+    pub fn new(parsed: ParsedEnum, type_hints: &[TokenStream2]) -> Self {
+        let generics_with_static = add_static_bounds(&parsed.generics);
+        let all_type_params: HashSet<String> = collect_ordered_type_params(&parsed.generics)
+            .into_iter()
+            .collect();
+
+        let erased = erase_variants(&parsed, &generics_with_static);
+        let views = view_variants(&parsed, &generics_with_static, &all_type_params);
+        let enum_params = index_param_names(&parsed.generics);
+        let generic_structs = parsed
+            .variants
+            .iter()
+            .filter_map(|variant| {
+                let generics =
+                    variant_struct_generics(variant, &generics_with_static, &all_type_params);
+                let params = index_param_names(&generics);
+                (!params.is_empty()).then(|| (variant.ident.to_string(), params))
+            })
+            .collect();
+
+        let hint = match type_hints {
+            [only] => Some(only),
+            hints => hints
+                .iter()
+                .find(|hint| hint_enum_ident(hint).is_some_and(|ident| ident == parsed.ident)),
+        };
+        let hint_generics = hint.and_then(extract_generics_from_type_hint);
+        let namespace = namespace_ident(&parsed).map(|module| {
+            let mut path = hint
+                .and_then(|hint| syn::parse2::<syn::Type>(hint.clone()).ok())
+                .and_then(|ty| hint_trait_path(&ty))
+                .unwrap_or_else(|| parsed.ident.clone().into());
+            path.segments.pop();
+            quote! { #path #module:: }
+        });
+
+        EnumInfo {
+            parsed,
+            erased,
+            views,
+            enum_params,
+            generic_structs,
+            hint_generics,
+            namespace,
+        }
+    }
+
+    /// The hint's arguments for the struct of a variant, picked by parameter
+    /// name so a struct using only some of the enum's parameters gets just
+    /// those. Falls back to the hint's arguments as written when the struct
+    /// has parameters the hint cannot supply.
+    fn struct_hint_generics(&self, variant: &str) -> Option<TokenStream2> {
+        let hint_generics = self.hint_generics.as_ref()?;
+        let struct_params = self.generic_structs.get(variant)?;
+        let args = syn::parse2::<syn::AngleBracketedGenericArguments>(hint_generics.clone())
+            .ok()
+            .filter(|args| args.args.len() == self.enum_params.len());
+        let Some(args) = args else {
+            return Some(hint_generics.clone());
+        };
+        let by_param: HashMap<&str, &syn::GenericArgument> = self
+            .enum_params
+            .iter()
+            .map(String::as_str)
+            .zip(&args.args)
+            .collect();
+        let picked: Option<Vec<_>> = struct_params
+            .iter()
+            .map(|param| by_param.get(param.as_str()))
+            .collect();
+        match picked {
+            Some(picked) => Some(quote! { <#(#picked),*> }),
+            None => Some(hint_generics.clone()),
+        }
+    }
+
+    fn declares(&self, variant: &str) -> bool {
+        self.parsed.variants.iter().any(|v| v.ident == variant)
+    }
+
+    /// Position of the variant an arm names among the enum's variants, which
+    /// is what its values' discriminant hook returns
+    fn discriminant(&self, arm: &MatchArm) -> Option<usize> {
+        if self.parsed.options.implements.is_some() {
+            return None;
+        }
+        let pat = parse_arm_pat(&arm.pattern).ok()?;
+        let head = pat_head_ident(&pat)?;
+        self.parsed.variants.iter().position(|v| v.ident == *head)
+    }
+
+    /// Whether the enum borrows, so its trait objects are matched through
+    /// view hooks rather than `Any`
+    fn borrows(&self) -> bool {
+        self.parsed.generics.lifetimes().next().is_some()
+    }
+
+    /// Reject what view hooks can't do for a borrowing enum: moving the
+    /// matched value out, matching it as a `dyn Any` across several enums,
+    /// and variants without a view
+    fn check_views(&self, input: &MatchTInput) -> syn::Result<()> {
+        if !self.borrows() {
+            return Ok(());
+        }
+        let enum_name = &self.parsed.ident;
+        if input.type_hints.len() > 1 {
+            return Err(syn::Error::new_spanned(
+                &input.expr,
+                format!(
+                    "`match_t!` across several enums takes a `dyn Any`, and `{}` borrows, so \
+                     its trait objects aren't `Any`; match them on their own with `as {}<..>`",
+                    enum_name, enum_name
+                ),
+            ));
+        }
+        if input.is_move {
+            return Err(syn::Error::new_spanned(
+                &input.expr,
+                format!(
+                    "`match_t!(move ..)` takes a `Box<dyn Any>`, and `{}` borrows, so its \
+                     trait objects aren't `Any`; match them by reference",
+                    enum_name
+                ),
+            ));
+        }
+
+        let mut errors: Option<syn::Error> = None;
+        for arm in &input.arms {
+            let Ok(pat) = parse_arm_pat(&arm.pattern) else {
+                continue;
+            };
+            let Some(head) = pat_head_ident(&pat) else {
+                continue;
+            };
+            if !self.declares(&head.to_string()) || self.views.iter().any(|v| v.ident == *head) {
+                continue;
+            }
+            let e = syn::Error::new(
+                head.span(),
+                format!(
+                    "`{}` borrows, so `match_t!` matches its variants through hooks on the \
+                     trait, which can't name `{}`: its struct takes generics or index \
+                     arguments the trait doesn't have",
+                    enum_name, head
+                ),
+            );
+            match &mut errors {
+                Some(existing) => existing.combine(e),
+                None => errors = Some(e),
+            }
+        }
+
+        match errors {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Whether a value matched against the hint can hold this variant: it
+    /// can't when the variant's index and the hint pass different concrete
+    /// types at some position, like `Expr<bool>` against `as Expr<i32>`
+    fn reachable(&self, variant: &ParsedVariant) -> bool {
+        let (Some(hint), Some(index)) = (&self.hint_generics, &variant.trait_type) else {
+            return true;
+        };
+        let Ok(hint_args) = syn::parse2::<syn::AngleBracketedGenericArguments>(hint.clone()) else {
+            return true;
+        };
+        let mut params: HashSet<String> = self.enum_params.iter().cloned().collect();
+        params.extend(collect_ordered_type_params(&variant.generics));
+        let index_args = extract_trait_type_args(index);
+        let hint_args = hint_args.args.iter().filter_map(|arg| match arg {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        });
+        !index_args
+            .iter()
+            .zip(hint_args)
+            .any(|(index_arg, hint_arg)| {
+                !mentions(index_arg.to_token_stream(), &params)
+                    && index_arg.to_token_stream().to_string()
+                        != hint_arg.to_token_stream().to_string()
+            })
+    }
+
+    /// Without a `_` arm, every variant a value of the hinted type can hold
+    /// needs an arm matching all of its values. Variants behind `#[cfg]`,
+    /// and variants of a borrowing enum that have no view, are left out.
+    fn check_exhaustive(&self, arms: &[MatchArm], hint: &TokenStream2) -> syn::Result<()> {
+        let covered: HashSet<String> = arms
+            .iter()
+            .filter_map(|arm| parse_arm_pat(&arm.pattern).ok())
+            .filter(matches_whole_variant)
+            .filter_map(|pat| pat_head_ident(&pat).map(Ident::to_string))
+            .collect();
+        let missing: Vec<String> = self
+            .parsed
+            .variants
+            .iter()
+            .filter(|v| !v.attrs.iter().any(|a| a.path().is_ident("cfg")))
+            .filter(|v| !self.borrows() || self.views.iter().any(|view| view.ident == v.ident))
+            .filter(|v| self.reachable(v))
+            .filter(|v| !covered.contains(&v.ident.to_string()))
+            .map(|v| format!("`{}`", v.name))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
+        }
+        Err(syn::Error::new_spanned(
+            hint,
+            format!(
+                "non-exhaustive `match_t!` on `{}`: {} not covered; add {}, or a `_ => ..` \
+                 arm last",
+                self.parsed.ident,
+                missing.join(", "),
+                if missing.len() == 1 {
+                    "an arm for it"
+                } else {
+                    "arms for them"
+                }
+            ),
+        ))
+    }
+
+    /// Check every arm naming a known variant against that variant's fields
+    fn validate_arms(&self, arms: &[MatchArm]) -> syn::Result<()> {
+        let mut errors: Option<syn::Error> = None;
+
+        for arm in arms {
+            let (_, pattern_for_match) = extract_type_and_pattern(&arm.pattern);
+            let Ok(pat) = syn::parse::Parser::parse2(Pat::parse_single, pattern_for_match) else {
+                continue;
+            };
+            let Some(head) = pat_head_ident(&pat) else {
+                continue;
+            };
+            let Some(variant) = self.parsed.variants.iter().find(|v| v.ident == *head) else {
+                continue;
+            };
+            if let Err(e) = check_pattern_shape(&pat, variant) {
+                match &mut errors {
+                    Some(existing) => existing.combine(e),
+                    None => errors = Some(e),
+                }
+            }
+        }
+
+        match errors {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Whether an arm pattern matches every value of the variant it names, judged
+/// from its shape: literals, ranges and `box P` anywhere make it refutable,
+/// nested paths and bindings are taken to match whatever they are given
+fn matches_whole_variant(pat: &Pat) -> bool {
+    match pat {
+        Pat::Lit(_) | Pat::Range(_) | Pat::Verbatim(_) => false,
+        Pat::Ident(p) => p
+            .subpat
+            .as_ref()
+            .is_none_or(|(_, subpat)| matches_whole_variant(subpat)),
+        Pat::TupleStruct(p) => p.elems.iter().all(matches_whole_variant),
+        Pat::Struct(p) => p.fields.iter().all(|f| matches_whole_variant(&f.pat)),
+        Pat::Tuple(p) => p.elems.iter().all(matches_whole_variant),
+        Pat::Reference(p) => matches_whole_variant(&p.pat),
+        Pat::Paren(p) => matches_whole_variant(&p.pat),
+        Pat::Type(p) => matches_whole_variant(&p.pat),
+        Pat::Or(p) => p.cases.iter().any(matches_whole_variant),
+        _ => true,
+    }
+}
+
+/// Replaces the bindings of a pattern with `_`, keeping what they are bound
+/// to after an `@`. Lowercase names are taken as bindings, capitalized ones
+/// as unit variants, unit structs or constants.
+struct StripBindings;
+
+impl VisitMut for StripBindings {
+    fn visit_pat_mut(&mut self, pat: &mut Pat) {
+        if let Pat::Ident(binding) = pat {
+            if let Some((_, subpat)) = binding.subpat.take() {
+                *pat = *subpat;
+            } else if !binding.ident.to_string().starts_with(char::is_uppercase) {
+                *pat = Pat::Wild(syn::PatWild {
+                    attrs: Vec::new(),
+                    underscore_token: Default::default(),
+                });
+                return;
+            }
+        }
+        visit_mut::visit_pat_mut(self, pat);
+    }
+
+    fn visit_field_pat_mut(&mut self, field: &mut syn::FieldPat) {
+        // `Jump { to }` becomes `Jump { to: _ }`
+        field.colon_token.get_or_insert_with(Default::default);
+        visit_mut::visit_field_pat_mut(self, field);
+    }
+}
+
+/// An arm pattern without its bindings, which tests a value behind a shared
+/// reference without moving out of it, whatever binding modes it was written
+/// with
+fn without_bindings(pat: &Pat) -> Pat {
+    let mut pat = pat.clone();
+    StripBindings.visit_pat_mut(&mut pat);
+    pat
+}
+
+/// A `box P` position of an arm pattern, like `box Num(a)` in
+/// `Add(box Num(a), rhs)`: the boxed trait object in that field is bound to
+/// `binding`, downcast to `ty` and matched against `pat`
+struct NestedBox {
+    binding: Ident,
+    ty: TokenStream2,
+    pat: Pat,
+    nested: Vec<NestedBox>,
+}
+
+/// Replaces the `box P` positions of a pattern with bindings, collecting the
+/// patterns their boxes are matched against
+struct SplitBoxes<'a> {
+    infos: &'a [EnumInfo],
+    hint_generics: &'a Option<TokenStream2>,
+    strip: bool,
+    count: usize,
+    nested: Vec<NestedBox>,
+    errors: Option<syn::Error>,
+}
+
+impl SplitBoxes<'_> {
+    /// The struct a nested pattern tests for, and the pattern naming it,
+    /// resolved the way an arm naming the same variant is
+    fn nested_type(&self, pat: &Pat) -> syn::Result<(TokenStream2, Pat)> {
+        let pattern = resolve_arm_head(&pat.to_token_stream(), self.infos);
+        let (type_name, pattern) = extract_type_and_pattern(&pattern);
+        let pat = syn::parse::Parser::parse2(Pat::parse_single, pattern)?;
+        let Some(head) = pat_head_ident(&pat)
+            .map(ToString::to_string)
+            .filter(|head| head.starts_with(char::is_uppercase))
+        else {
+            return Err(syn::Error::new_spanned(
+                &pat,
+                "expected a variant after `box`, as in `box Num(n)`",
+            ));
+        };
+        if self.infos.is_empty() {
+            let ty = apply_type_hint_to_pattern(type_name, self.hint_generics);
+            return Ok((ty, pat));
+        }
+        let Some(info) = self.infos.iter().find(|info| info.declares(&head)) else {
+            return Ok((type_name, pat));
+        };
+        if info.borrows() {
+            return Err(syn::Error::new_spanned(
+                &pat,
+                format!(
+                    "`box {head}(..)` downcasts through `Any`, which a value of the borrowing \
+                     enum `{}` can't be",
+                    info.parsed.ident
+                ),
+            ));
+        }
+        let names_generics = type_name.to_string().contains('<');
+        if !names_generics && info.erased.iter().any(|e| e.ident == head) {
+            return Err(syn::Error::new_spanned(
+                &pat,
+                format!(
+                    "`box {head}(..)` can't test for `{head}`, whose struct has generics of its \
+                     own that the hint doesn't name; name them, as in `box {head}::<..>(..)`"
+                ),
+            ));
+        }
+        let struct_generics = info.struct_hint_generics(&head);
+        Ok((apply_type_hint_to_pattern(type_name, &struct_generics), pat))
+    }
+}
+
+impl VisitMut for SplitBoxes<'_> {
+    fn visit_pat_mut(&mut self, pat: &mut Pat) {
+        let Pat::Verbatim(tokens) = pat else {
+            visit_mut::visit_pat_mut(self, pat);
+            return;
+        };
+        let parse_box = |input: syn::parse::ParseStream| {
+            input.parse::<syn::Token![box]>()?;
+            Pat::parse_single(input)
+        };
+        let Ok(boxed) = syn::parse::Parser::parse2(parse_box, tokens.clone()) else {
+            return;
+        };
+        let (ty, mut inner) = match self.nested_type(&boxed) {
+            Ok(nested) => nested,
+            Err(e) => {
+                match &mut self.errors {
+                    Some(existing) => existing.combine(e),
+                    None => self.errors = Some(e),
+                }
+                return;
+            }
+        };
+        if self.strip {
+            inner = without_bindings(&inner);
+        }
+        let binding = internal_ident(&format!("__nested{}", self.count));
+        self.count += 1;
+        let outer = std::mem::take(&mut self.nested);
+        self.visit_pat_mut(&mut inner);
+        let nested = std::mem::replace(&mut self.nested, outer);
+        self.nested.push(NestedBox {
+            binding: binding.clone(),
+            ty,
+            pat: inner,
+            nested,
+        });
+        *pat = syn::parse_quote!(#binding);
+    }
+}
+
+/// An arm pattern with its `box P` positions split off, both as written and
+/// without bindings, to test a value before moving out of it
+struct ArmPattern {
+    pat: Pat,
+    nested: Vec<NestedBox>,
+    test: Pat,
+    test_nested: Vec<NestedBox>,
+}
+
+impl ArmPattern {
+    fn new(
+        pat: Pat,
+        infos: &[EnumInfo],
+        hint_generics: &Option<TokenStream2>,
+    ) -> syn::Result<Self> {
+        let split = |pat: Pat, strip: bool| {
+            let mut split = SplitBoxes {
+                infos,
+                hint_generics,
+                strip,
+                count: 0,
+                nested: Vec::new(),
+                errors: None,
+            };
+            let mut pat = pat;
+            split.visit_pat_mut(&mut pat);
+            match split.errors {
+                Some(e) => Err(e),
+                None => Ok((pat, split.nested)),
+            }
+        };
+        let (test, test_nested) = split(without_bindings(&pat), true)?;
+        let (pat, nested) = split(pat, false)?;
+        Ok(ArmPattern {
+            pat,
+            nested,
+            test,
+            test_nested,
+        })
+    }
+}
+
+/// Test the boxes split off a pattern by reference, as a `bool`
+fn nested_test(nested: &[NestedBox]) -> TokenStream2 {
+    let inner = internal_ident("__inner");
+    let tests = nested.iter().map(|n| {
+        let NestedBox {
+            binding, ty, pat, ..
+        } = n;
+        let rest = nested_test(&n.nested);
+        quote! {
+            match (&**#binding as &dyn ::core::any::Any).downcast_ref::<#ty>() {
+                ::core::option::Option::Some(#inner) => match #inner {
+                    #pat => #rest,
+                    #[allow(unreachable_patterns)]
+                    _ => false,
+                },
+                ::core::option::Option::None => false,
+            }
+        }
+    });
+    quote! { true #(&& #tests)* }
+}
+
+/// The label the by-reference arms of a `match_t!` nested `depth` deep break
+/// out of with their value, hygienic so a body cannot break to it itself
+fn match_label(depth: usize) -> syn::Lifetime {
+    syn::Lifetime::new(&format!("'__match_t{}", depth), Span::mixed_site())
+}
+
+/// Bind an arm's body to `found` for `then` to use. The body sits in a match
+/// arm, as it would in a `match`, and `then` may be unreachable, so a body
+/// leaving the function with `return` or `?` isn't linted as diverging in the
+/// middle of generated code
+fn bind_body(body: &impl ToTokens, found: &Ident, then: TokenStream2) -> TokenStream2 {
+    quote! {
+        let #found = match () {
+            () => #body,
+        };
+        #[allow(unreachable_code)]
+        #then
+    }
+}
+
+/// Match the boxes split off a pattern by reference around `then`, which
+/// sees what their patterns bind; by mutable reference under `mut`
+fn nested_ref(nested: &[NestedBox], then: TokenStream2, is_mut: bool) -> TokenStream2 {
+    let inner = internal_ident("__inner");
+    nested.iter().rev().fold(then, |then, n| {
+        let NestedBox {
+            binding, ty, pat, ..
+        } = n;
+        let then = nested_ref(&n.nested, then, is_mut);
+        let downcast = if is_mut {
+            quote! { (&mut **#binding as &mut dyn ::core::any::Any).downcast_mut::<#ty>() }
+        } else {
+            quote! { (&**#binding as &dyn ::core::any::Any).downcast_ref::<#ty>() }
+        };
+        quote! {
+            if let ::core::option::Option::Some(#inner) = #downcast {
+                if let #pat = #inner {
+                    #then
+                }
+            }
+        }
+    })
+}
+
+/// Move the boxes split off a pattern, already tested to match, out into
+/// what their patterns bind around `body`
+fn nested_move(nested: &[NestedBox], body: TokenStream2) -> TokenStream2 {
+    let box_ty = box_path();
+    let inner = internal_ident("__inner");
+    let downcast_failed = unreachable_branch("Downcast failed in match_t!");
+    nested.iter().rev().fold(body, |body, n| {
+        let NestedBox {
+            binding, ty, pat, ..
+        } = n;
+        let body = nested_move(&n.nested, body);
+        quote! {
+            {
+                let #inner: #box_ty<dyn ::core::any::Any> = #binding;
+                match #inner.downcast::<#ty>() {
+                    #[allow(unused_mut)]
+                    ::core::result::Result::Ok(mut #inner) => match *#inner {
+                        #pat => #body,
+                        #[allow(unreachable_patterns)]
+                        _ => ::core::panic!("Pattern match failed in match_t!"),
+                    },
+                    ::core::result::Result::Err(_) => #downcast_failed,
+                }
+            }
+        }
+    })
+}
+
+/// Split a trailing `_ => ..` arm, taken when no other arm matches, off the
+/// arms naming variants
+fn split_fallback(arms: &[MatchArm]) -> syn::Result<(&[MatchArm], Option<&MatchArm>)> {
+    let is_wildcard = |arm: &MatchArm| matches!(parse_arm_pat(&arm.pattern), Ok(Pat::Wild(_)));
+    match arms.iter().position(is_wildcard) {
+        None => Ok((arms, None)),
+        Some(last) if last + 1 == arms.len() => Ok((&arms[..last], Some(&arms[last]))),
+        Some(position) => Err(syn::Error::new_spanned(
+            &arms[position].pattern,
+            "`_` matches every value, so it must be the last arm of `match_t!`",
+        )),
+    }
+}
+
+/// Point the arms naming variants of namespaced or renamed enums at their
+/// structs: `Value(v)` matches `token::Value` where only the trait is in
+/// scope, and `Circle(r)` matches `ShapeCircle` under
+/// `rename_variants = "Shape{}"`
+pub fn resolve_arm_heads(arms: &mut [MatchArm], infos: &[EnumInfo]) {
+    for arm in arms {
+        arm.pattern = resolve_arm_head(&arm.pattern, infos);
+    }
+}
+
+/// Point a single pattern at the struct of the variant it names, as
+/// [`resolve_arm_heads`] does for whole arms
+fn resolve_arm_head(pattern: &TokenStream2, infos: &[EnumInfo]) -> TokenStream2 {
+    let Ok(pat) = parse_arm_pat(pattern) else {
+        return pattern.clone();
+    };
+    let Some(head) = pat_head_ident(&pat) else {
+        return pattern.clone();
+    };
+    let found = infos.iter().find_map(|info| {
+        let variants = &info.parsed.variants;
+        let variant = variants
+            .iter()
+            .find(|v| v.name == *head)
+            .or_else(|| variants.iter().find(|v| v.ident == *head))?;
+        Some((info, variant))
+    });
+    let Some((info, variant)) = found else {
+        return pattern.clone();
+    };
+    if info.namespace.is_none() && variant.ident == *head {
+        return pattern.clone();
+    }
+    let module = &info.namespace;
+    let ident = Ident::new(&variant.ident.to_string(), head.span());
+    replace_arm_head(pattern, |_| Some(quote! { #module #ident }))
+}
+
+/// How a single arm recognizes its variant
+enum ArmDispatch<'a> {
+    /// Downcast to the concrete struct type
+    Downcast(TokenStream2),
+    /// Go through the erased hooks of a variant with its own generics
+    Erased(&'a ErasedVariant, Pat),
+    /// Go through the view hook of a variant of a borrowing enum
+    View(&'a ViewedVariant),
+}
+
+/// Recognize an arm's variant. Arms naming variants of a hinted enum are
+/// routed to that enum; erased hooks are only reachable when the scrutinee is
+/// the one hinted trait.
+fn arm_dispatch<'a>(
+    arm: &MatchArm,
+    infos: &'a [EnumInfo],
+    hint_generics: &Option<TokenStream2>,
+) -> ArmDispatch<'a> {
+    let (type_name, pattern_for_match) = extract_type_and_pattern(&arm.pattern);
+    let names_generics = type_name.to_string().contains('<');
+
+    if infos.is_empty() {
+        return ArmDispatch::Downcast(apply_type_hint_to_pattern(type_name, hint_generics));
+    }
+
+    let pat = syn::parse::Parser::parse2(Pat::parse_single, pattern_for_match).ok();
+    let head = pat.as_ref().and_then(pat_head_ident).map(|i| i.to_string());
+    let Some(info) = head
+        .as_ref()
+        .and_then(|head| infos.iter().find(|info| info.declares(head)))
+    else {
+        return ArmDispatch::Downcast(type_name);
+    };
+
+    if let Some(view) = head
+        .as_ref()
+        .and_then(|head| info.views.iter().find(|v| v.ident == *head))
+    {
+        return ArmDispatch::View(view);
+    }
+
+    if !names_generics && infos.len() == 1 {
+        if let (Some(head), Some(pat)) = (&head, &pat) {
+            if let Some(erased) = info.erased.iter().find(|e| e.ident == *head) {
+                return ArmDispatch::Erased(erased, pat.clone());
+            }
+        }
+    }
+
+    // Only structs that actually take generic arguments receive the hint's
+    let struct_generics = head.and_then(|h| info.struct_hint_generics(&h));
+    ArmDispatch::Downcast(apply_type_hint_to_pattern(type_name, &struct_generics))
+}
+
+/// Names of the type and const parameters of generics, in declaration order
+fn index_param_names(generics: &syn::Generics) -> Vec<String> {
+    generics
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            syn::GenericParam::Type(tp) => Some(tp.ident.to_string()),
+            syn::GenericParam::Const(cp) => Some(cp.ident.to_string()),
+            syn::GenericParam::Lifetime(_) => None,
+        })
+        .collect()
+}
+
+/// The discriminant of every arm, when the scrutinee is the one hinted trait
+/// and every arm names one of its variants
+fn arm_discriminants(arms: &[MatchArm], infos: &[EnumInfo]) -> Option<Vec<usize>> {
+    let [info] = infos else {
+        return None;
+    };
+    arms.iter().map(|arm| info.discriminant(arm)).collect()
+}
+
+/// Run the code of each arm only for values of the variant it names, picked
+/// with one `match` on the discriminant instead of trying every arm in turn.
+/// Arms naming the same variant keep their order. A scrutinee that isn't the
+/// hinted trait object, such as a `dyn Any`, has no discriminant hook; a local
+/// blanket trait answers for it with `usize::MAX`, and values reporting that
+/// try every arm in turn.
+fn dispatch_on_discriminant(
+    scrutinee: &Ident,
+    arm_code: Vec<TokenStream2>,
+    discriminants: &[usize],
+) -> TokenStream2 {
+    let mut groups: Vec<(usize, Vec<TokenStream2>)> = Vec::new();
+    for (code, &discriminant) in arm_code.iter().zip(discriminants) {
+        match groups.iter_mut().find(|(d, _)| *d == discriminant) {
+            Some((_, codes)) => codes.push(code.clone()),
+            None => groups.push((discriminant, vec![code.clone()])),
+        }
+    }
+    let hook = discriminant_hook_ident();
+    let fallback = internal_ident("__NoDiscriminant");
+    let branches = groups.iter().map(|(discriminant, codes)| {
+        quote! { #discriminant => { #(#codes)* } }
+    });
+    // Dereferenced so a `Box<dyn Enum>` reaches the trait object's own hook
+    // rather than the blanket one for the box
+    quote! {
+        {
+            #[allow(dead_code)]
+            trait #fallback {
+                fn #hook(&self) -> usize {
+                    usize::MAX
+                }
+            }
+            impl<T: ?::core::marker::Sized> #fallback for T {}
+            match (*#scrutinee).#hook() {
+                #(#branches)*
+                _ => { #(#arm_code)* }
+            }
+        }
+    }
+}
+
+/// Local items taking the value out of the `Box`, `Rc` or `Arc` a
+/// `match_t!(move ..)` consumes, once its type has been checked: `Owned<T>`
+/// moves the `T` out, or hands back a pointer to it when an `Rc` or `Arc` is
+/// shared. The arm then clones the value out of it when `T: Clone`, picked by
+/// autoref on `Shared`, and panics otherwise.
+///
+/// By default the pointer is first turned into one to `dyn Any`, which is
+/// downcast. That coercion is only possible where the scrutinee's type is
+/// known, so `Probe` names the target by autoref on the scrutinee and
+/// `with_type` coerces the value as its argument: `Box<dyn Any>`,
+/// `Rc<dyn Any>`, and `Arc<dyn Any + Send + Sync>` for an `Arc` of a
+/// `Send + Sync` value. Other `Arc`s become `Arc<dyn Any>`, which can't be
+/// downcast by value, so their value is always cloned out. With the
+/// `unchecked` feature, `Owned<T>` is implemented for each pointer over any
+/// pointee instead, and takes the `T` out through raw pointers.
+fn owned_items() -> TokenStream2 {
+    let box_ty = box_path();
+    let rc_ty = rc_path();
+    let arc_ty = arc_path();
+    let owned = internal_ident("__Owned");
+    let take = internal_ident("__take");
+    let pointer = internal_ident("__Pointer");
+    let shared = internal_ident("__Shared");
+    let clone_shared = internal_ident("__CloneShared");
+    let panic_shared = internal_ident("__PanicShared");
+    let get = internal_ident("__get");
+    let owned_impls = if cfg!(feature = "unchecked") {
+        let impls = [(&box_ty, false), (&rc_ty, true), (&arc_ty, true)]
+            .into_iter()
+            .map(|(ptr, counted)| {
+                let taken = if counted {
+                    quote! { #ptr::try_unwrap(unsafe { #ptr::from_raw(#ptr::into_raw(self) as *const T) }) }
+                } else {
+                    quote! { ::core::result::Result::Ok(*unsafe { #ptr::from_raw(#ptr::into_raw(self) as *mut T) }) }
+                };
+                quote! {
+                    impl<T, D: ?::core::marker::Sized> #owned<T> for #ptr<D> {
+                        type #pointer = #ptr<T>;
+                        unsafe fn #take(self) -> ::core::result::Result<T, #ptr<T>> {
+                            #taken
+                        }
+                    }
+                }
+            });
+        quote! {
+            trait #owned<T>: ::core::marker::Sized {
+                type #pointer: ::core::ops::Deref<Target = T>;
+                /// `self` must point at a `T`
+                unsafe fn #take(self) -> ::core::result::Result<T, Self::#pointer>;
+            }
+            #(#impls)*
+        }
+    } else {
+        let probe = internal_ident("__Probe");
+        let any_send_sync = internal_ident("__AnySendSync");
+        let any_pointer = internal_ident("__AnyPointer");
+        let target = internal_ident("__target");
+        let with_type = internal_ident("__with_type");
+        let any_ref = internal_ident("__AnyRef");
+        let not_t = unreachable_branch("Downcast failed in match_t!");
+        let any = quote! { dyn ::core::any::Any };
+        let send_sync_any =
+            quote! { dyn ::core::any::Any + ::core::marker::Send + ::core::marker::Sync };
+        let send_sync = quote! { + ::core::marker::Send + ::core::marker::Sync };
+        let phantom = quote! { ::core::marker::PhantomData };
+        let probe_impls = [&box_ty, &rc_ty, &arc_ty].into_iter().map(|ptr| {
+            quote! {
+                impl<D: ?::core::marker::Sized> #any_pointer for &#probe<'_, #ptr<D>> {
+                    type #pointer = #ptr<#any>;
+                }
+            }
+        });
+        let counted_impls = [
+            (&rc_ty, &any, None),
+            (&arc_ty, &send_sync_any, Some(&send_sync)),
+        ]
+        .into_iter()
+        .map(|(ptr, pointee, bounds)| {
+            quote! {
+                impl<T: ::core::any::Any #bounds> #owned<T> for #ptr<#pointee> {
+                    type #pointer = #ptr<T>;
+                    fn #take(self) -> ::core::result::Result<T, #ptr<T>> {
+                        match self.downcast::<T>() {
+                            ::core::result::Result::Ok(value) => #ptr::try_unwrap(value),
+                            ::core::result::Result::Err(_) => #not_t,
+                        }
+                    }
+                }
+            }
+        });
+        quote! {
+            struct #probe<'a, P>(&'a P);
+            trait #any_send_sync {
+                type #pointer;
+                fn #target(&self) -> #phantom<Self::#pointer> {
+                    #phantom
+                }
+            }
+            impl<D: ?::core::marker::Sized + ::core::marker::Send + ::core::marker::Sync>
+                #any_send_sync for #probe<'_, #arc_ty<D>>
+            {
+                type #pointer = #arc_ty<#send_sync_any>;
+            }
+            trait #any_pointer {
+                type #pointer;
+                fn #target(&self) -> #phantom<Self::#pointer> {
+                    #phantom
+                }
+            }
+            #(#probe_impls)*
+            fn #with_type<P>(_: #phantom<P>, value: P) -> P {
+                value
+            }
+
+            trait #owned<T> {
+                type #pointer: ::core::ops::Deref<Target = T>;
+                /// `self` must point at a `T`
+                fn #take(self) -> ::core::result::Result<T, Self::#pointer>;
+            }
+            impl<T: ::core::any::Any> #owned<T> for #box_ty<#any> {
+                type #pointer = #box_ty<T>;
+                fn #take(self) -> ::core::result::Result<T, #box_ty<T>> {
+                    match self.downcast::<T>() {
+                        ::core::result::Result::Ok(value) => ::core::result::Result::Ok(*value),
+                        ::core::result::Result::Err(_) => #not_t,
+                    }
+                }
+            }
+            #(#counted_impls)*
+            struct #any_ref<T>(#arc_ty<#any>, #phantom<T>);
+            impl<T: ::core::any::Any> ::core::ops::Deref for #any_ref<T> {
+                type Target = T;
+                fn deref(&self) -> &T {
+                    match (*self.0).downcast_ref::<T>() {
+                        ::core::option::Option::Some(value) => value,
+                        ::core::option::Option::None => #not_t,
+                    }
+                }
+            }
+            impl<T: ::core::any::Any> #owned<T> for #arc_ty<#any> {
+                type #pointer = #any_ref<T>;
+                fn #take(self) -> ::core::result::Result<T, #any_ref<T>> {
+                    ::core::result::Result::Err(#any_ref(self, #phantom))
+                }
+            }
+        }
+    };
+    quote! {
+        #owned_impls
+        struct #shared<'a, T>(&'a T);
+        trait #clone_shared<T> {
+            fn #get(&self) -> T;
+        }
+        impl<T: ::core::clone::Clone> #clone_shared<T> for #shared<'_, T> {
+            fn #get(&self) -> T {
+                ::core::clone::Clone::clone(self.0)
+            }
+        }
+        trait #panic_shared<T> {
+            fn #get(&self) -> T;
+        }
+        impl<T> #panic_shared<T> for &#shared<'_, T> {
+            fn #get(&self) -> T {
+                ::core::panic!(
+                    "match_t!(move ..) can't move `{}` out of a shared pointer, and it isn't `Clone`",
+                    ::core::any::type_name::<T>()
+                )
+            }
+        }
+    }
+}
+
+/// The call of `Owned::take` for the value of an arm of type `type_name`,
+/// see `owned_items`
+fn take_owned(type_name: &TokenStream2, scrutinee: &Ident) -> TokenStream2 {
+    let owned = internal_ident("__Owned");
+    let take = internal_ident("__take");
+    if cfg!(feature = "unchecked") {
+        return quote! { unsafe { #owned::<#type_name>::#take(#scrutinee) } };
+    }
+    let probe = internal_ident("__Probe");
+    let target = internal_ident("__target");
+    let with_type = internal_ident("__with_type");
+    quote! {
+        #owned::<#type_name>::#take(#with_type((&#probe(&#scrutinee)).#target(), #scrutinee))
+    }
+}
+
+/// How an arm's type reads in a message: the variant's own name, without
+/// the path reaching it, and generics as written, as in `Leaf<i32>`
+fn type_label(ty: &TokenStream2) -> String {
+    let last = match syn::parse2::<syn::Path>(ty.clone()) {
+        Ok(path) => match path.segments.last() {
+            Some(segment) => segment.to_token_stream(),
+            None => ty.clone(),
+        },
+        Err(_) => ty.clone(),
+    };
+    let spaced = last.to_string();
+    let mut label = String::new();
+    let mut chars = spaced.chars().peekable();
+    while let Some(c) = chars.next() {
+        let next = chars.peek().copied();
+        let glued = label.ends_with(['<', ':', '&', '*', '(', '['])
+            || next.is_some_and(|n| matches!(n, '<' | '>' | ':' | ',' | ')' | ']'));
+        if c != ' ' || !glued {
+            label.push(c);
+        }
+    }
+    label
+}
+
+/// Code panicking for values no arm matched, naming the type of each next to
+/// what the arms tried. A trait generated by `type_enum!` names the variant
+/// behind it through a hidden hook; other values, such as a `dyn Any`, fall
+/// back on a local blanket trait naming the type they're seen as.
+fn mismatch_panic(values: &[&Ident], tried: &[String]) -> TokenStream2 {
+    let hook = type_name_hook_ident();
+    let fallback = internal_ident("__NoTypeName");
+    let mut unique: Vec<&String> = Vec::new();
+    for label in tried {
+        if !unique.contains(&label) {
+            unique.push(label);
+        }
+    }
+    let tried = unique
+        .iter()
+        .map(|label| format!("`{label}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let message = match values {
+        [_] => "No match_t! arm matches a value of type `{}`; arms tried {}".to_string(),
+        _ => {
+            let types = vec!["`{}`"; values.len()].join(", ");
+            format!("No match_t! arm matches values of types ({types}); arms tried {{}}")
+        }
+    };
+    quote! {
+        {
+            #[allow(dead_code)]
+            trait #fallback {
+                fn #hook(&self) -> &'static str {
+                    ::core::any::type_name::<Self>()
+                }
+            }
+            impl<T: ?::core::marker::Sized> #fallback for T {}
+            ::core::panic!(#message, #((*#values).#hook(),)* #tried)
+        }
+    }
+}
+
+/// Tuple pattern over the fields handed out by the erased hooks of a variant
+fn erased_tuple_pattern(pat: &Pat, erased: &ErasedVariant) -> TokenStream2 {
+    let members: Vec<_> = erased.fields.iter().map(|f| f.member.clone()).collect();
+    fields_tuple_pattern(pat, &members)
+}
+
+/// A local of the expansion, resolved at the macro definition so arm bodies
+/// and the scrutinee never see it, nor shadow it with bindings of their own
+fn internal_ident(name: &str) -> Ident {
+    Ident::new(name, Span::mixed_site())
+}
+
+/// The elements of a tuple arm over `count` values, with a `..` among them
+/// standing for as many `_` as it takes. `None` when they don't add up.
+fn fill_rest(elems: Vec<TokenStream2>, count: usize) -> Option<Vec<TokenStream2>> {
+    let is_rest = |elem: &TokenStream2| matches!(parse_arm_pat(elem), Ok(Pat::Rest(_)));
+    let Some(rest) = elems.iter().position(is_rest) else {
+        return (elems.len() == count).then_some(elems);
+    };
+    let mut filled = elems;
+    filled.remove(rest);
+    if filled.iter().any(is_rest) || filled.len() > count {
+        return None;
+    }
+    let wild = std::iter::repeat_n(quote! { _ }, count - filled.len());
+    filled.splice(rest..rest, wild);
+    Some(filled)
+}
+
+/// Expand a `match_t!` over a tuple of values, `(lhs, rhs)`, whose arms are
+/// tuples of patterns. Each arm matches every value against its pattern in
+/// turn, through a `match_t!` of its own over that value; `_` elements match
+/// without a type check. Under `move`, an arm is tested by reference before
+/// any of the values is consumed.
+fn expand_tuple_match_t(
+    input: &MatchTInput,
+    scrutinees: &[syn::Expr],
+    infos: &[EnumInfo],
+    depth: usize,
+) -> syn::Result<TokenStream2> {
+    let (arms, fallback) = split_fallback(&input.arms)?;
+    if let (Some(arm), Some(_)) = (fallback, &input.otherwise) {
+        return Err(syn::Error::new_spanned(
+            &arm.pattern,
+            "`match_t!` takes either a `_` arm or an `else` clause, not both",
+        ));
+    }
+    let count = scrutinees.len();
+    let mut errors: Option<syn::Error> = None;
+    let mut elements = Vec::new();
+    for arm in arms {
+        let elems = split_tuple_pattern(&arm.pattern).and_then(|elems| fill_rest(elems, count));
+        match elems {
+            Some(elems) => elements.push(elems),
+            None => {
+                let e = syn::Error::new_spanned(
+                    &arm.pattern,
+                    format!(
+                        "`match_t!` over {} values takes arms matching a tuple of {} patterns, \
+                         as in `(Num(a), Num(b))`",
+                        count, count
+                    ),
+                );
+                match &mut errors {
+                    Some(existing) => existing.combine(e),
+                    None => errors = Some(e),
+                }
+            }
+        }
+    }
+    if let Some(e) = errors {
+        return Err(e);
+    }
+
+    let bindings: Vec<Ident> = (0..count)
+        .map(|i| internal_ident(&format!("__scrutinee{}", i)))
+        .collect();
+    // One `match_t!` matching a single value against one element, with
+    // `body` as its only arm, nested `nesting` deep in this one
+    let element_match = |expr: TokenStream2,
+                         pattern: &TokenStream2,
+                         body: TokenStream2,
+                         is_move: bool,
+                         is_try: bool,
+                         nesting: usize|
+     -> syn::Result<TokenStream2> {
+        let body = syn::Expr::Verbatim(body);
+        let mut arms: Vec<MatchArm> = split_or_pattern(pattern.clone())?
+            .into_iter()
+            .map(|pattern| MatchArm {
+                pattern,
+                body: body.clone(),
+            })
+            .collect();
+        resolve_arm_heads(&mut arms, infos);
+        // An element already tested to match is consumed without another
+        // exhaustiveness check
+        let otherwise = (!is_try).then(|| ElseClause {
+            binding: None,
+            body: syn::parse_quote!({ ::core::panic!("Pattern match failed in match_t!") }),
+        });
+        expand_nested_match_t(
+            &MatchTInput {
+                is_move,
+                is_mut: input.is_mut,
+                is_try,
+                expr,
+                type_hints: input.type_hints.clone(),
+                arms,
+                otherwise,
+            },
+            infos,
+            depth + nesting,
+        )
+    };
+    let is_wild = |pattern: &TokenStream2| matches!(parse_arm_pat(pattern), Ok(Pat::Wild(_)));
+    let values = quote! { (#(#bindings,)*) };
+    let tried: Vec<_> = elements
+        .iter()
+        .map(|elems| {
+            let labels: Vec<_> = elems
+                .iter()
+                .map(|elem| match split_or_pattern(elem.clone()) {
+                    _ if is_wild(elem) => "_".to_string(),
+                    Ok(alternatives) => alternatives
+                        .iter()
+                        .map(|alt| type_label(&extract_type_and_pattern(alt).0))
+                        .collect::<Vec<_>>()
+                        .join(" | "),
+                    Err(_) => type_label(&extract_type_and_pattern(elem).0),
+                })
+                .collect();
+            format!("({})", labels.join(", "))
+        })
+        .collect();
+    let bindings_ref: Vec<&Ident> = bindings.iter().collect();
+    let fallback = match (fallback, &input.otherwise) {
+        (Some(arm), _) => Some(arm.body.to_token_stream()),
+        (None, Some(ElseClause { binding, body })) => {
+            let binding = binding.iter();
+            let stmts = &body.stmts;
+            Some(quote! {
+                {
+                    #(let #binding = #values;)*
+                    #(#stmts)*
+                }
+            })
+        }
+        (None, None) => None,
+    };
+    let lets = scrutinees.iter().zip(&bindings).map(|(expr, binding)| {
+        if input.is_mut {
+            quote! { let #binding = &mut *(#expr); }
+        } else {
+            quote! { let #binding = #expr; }
+        }
+    });
+
+    if input.is_move {
+        let matched = internal_ident("__matched_idx");
+        let mut tests = Vec::new();
+        let mut consumers = Vec::new();
+        for (idx, (arm, elems)) in arms.iter().zip(&elements).enumerate() {
+            let mut test = Vec::new();
+            let mut consume = arm.body.to_token_stream();
+            for (elem, binding) in elems.iter().zip(&bindings).rev() {
+                if is_wild(elem) {
+                    continue;
+                }
+                let by_ref =
+                    element_match(quote! { &*#binding }, elem, quote! { () }, false, true, 1)?;
+                test.push(quote! { ::core::option::Option::is_some(&#by_ref) });
+                consume = element_match(quote! { #binding }, elem, consume, true, false, 1)?;
+            }
+            if input.is_try {
+                consume = quote! { ::core::result::Result::Ok(#consume) };
+            }
+            tests.push(quote! { if true #(&& #test)* { ::core::option::Option::Some(#idx) } });
+            consumers.push(quote! { ::core::option::Option::Some(#idx) => #consume, });
+        }
+        let unmatched = match (fallback, input.is_try) {
+            (Some(body), false) => body,
+            (Some(body), true) => quote! { ::core::result::Result::Ok(#body) },
+            (None, false) => mismatch_panic(&bindings_ref, &tried),
+            (None, true) => quote! { ::core::result::Result::Err(#values) },
+        };
+        Ok(quote! {
+            {
+                #(#lets)*
+                // The arms' bindings are only looked at to test them
+                #[allow(unused_variables)]
+                let #matched: ::core::option::Option<usize> =
+                    #(#tests else)* { ::core::option::Option::None };
+                match #matched {
+                    #(#consumers)*
+                    _ => #unmatched
+                }
+            }
+        })
+    } else {
+        let found = internal_ident("__found");
+        let label = match_label(depth);
+        let mut matches = Vec::new();
+        for (arm, elems) in arms.iter().zip(&elements) {
+            let body = &arm.body;
+            let some = internal_ident("__some");
+            let bound = bind_body(
+                body,
+                &found,
+                quote! { let #some = ::core::option::Option::Some(#found); },
+            );
+            let mut matched = quote! { { #bound #some } };
+            for (i, (elem, binding)) in elems.iter().zip(&bindings).enumerate().rev() {
+                if is_wild(elem) {
+                    continue;
+                }
+                // Reborrowed per arm, so each arm sees every value
+                let expr = if input.is_mut {
+                    quote! { &mut *#binding }
+                } else {
+                    quote! { &*#binding }
+                };
+                // Each element's match holds the next one's, so its label differs
+                let inner = element_match(expr, elem, matched, false, true, i + 1)?;
+                matched = quote! { ::core::option::Option::flatten(#inner) };
+            }
+            let value = if input.is_try {
+                quote! { ::core::option::Option::Some(#found) }
+            } else {
+                quote! { #found }
+            };
+            matches.push(quote! {
+                if let ::core::option::Option::Some(#found) = #matched {
+                    break #label #value;
+                }
+            });
+        }
+        let unmatched = match (fallback, input.is_try) {
+            (Some(body), false) => body,
+            (Some(body), true) => bind_body(
+                &body,
+                &found,
+                quote! { break #label ::core::option::Option::Some(#found); },
+            ),
+            (None, false) => mismatch_panic(&bindings_ref, &tried),
+            (None, true) => quote! { ::core::option::Option::None },
+        };
+
+        Ok(quote! {
+            {
+                #(#lets)*
+                #label: {
+                    #(#matches)*
+                    #unmatched
+                }
+            }
+        })
+    }
+}
+
+/// Expand a parsed `match_t!`, with the definitions of the enums named by its
+/// type hints when they are known
+pub fn expand_match_t(input: &MatchTInput, infos: &[EnumInfo]) -> syn::Result<TokenStream2> {
+    expand_nested_match_t(input, infos, 0)
+}
+
+/// Expand a `match_t!` nested `depth` deep in the one being expanded, which
+/// numbers the label its by-reference arms break out of
+fn expand_nested_match_t(
+    input: &MatchTInput,
+    infos: &[EnumInfo],
+    depth: usize,
+) -> syn::Result<TokenStream2> {
+    if let Ok(syn::Expr::Tuple(tuple)) = syn::parse2::<syn::Expr>(input.expr.clone()) {
+        if tuple.elems.len() > 1 {
+            let scrutinees: Vec<_> = tuple.elems.into_iter().collect();
+            return expand_tuple_match_t(input, &scrutinees, infos, depth);
+        }
+    }
+    let (arms, fallback) = split_fallback(&input.arms)?;
+    if let (Some(arm), Some(_)) = (fallback, &input.otherwise) {
+        return Err(syn::Error::new_spanned(
+            &arm.pattern,
+            "`match_t!` takes either a `_` arm or an `else` clause, not both",
+        ));
+    }
+    let mut errors: Option<syn::Error> = None;
+    for info in infos {
+        if let Err(e) = info
+            .validate_arms(arms)
+            .and_then(|()| info.check_views(input))
+        {
+            match &mut errors {
+                Some(existing) => existing.combine(e),
+                None => errors = Some(e),
+            }
+        }
+    }
+    // Exhaustiveness is checked against a single hinted enum; a value
+    // matched across several could be anything behind its `dyn Any`, and
+    // `try` expects values no arm matches
+    let has_fallback = fallback.is_some() || input.otherwise.is_some();
+    if let ([info], [hint], false, false) = (
+        infos,
+        input.type_hints.as_slice(),
+        has_fallback,
+        input.is_try,
+    ) {
+        if let Err(e) = info.check_exhaustive(arms, hint) {
+            match &mut errors {
+                Some(existing) => existing.combine(e),
+                None => errors = Some(e),
+            }
+        }
+    }
+    let hint_generics = match input.type_hints.as_slice() {
+        [only] => extract_generics_from_type_hint(only),
+        _ => None,
+    };
+    // A variant with generics of its own is only reached through its hooks,
+    // which hand out its fields but not the struct
+    for arm in arms {
+        if let ArmDispatch::Erased(erased, Pat::Ident(binding)) =
+            arm_dispatch(arm, infos, &hint_generics)
+        {
+            let e = syn::Error::new_spanned(
+                &binding.ident,
+                format!(
+                    "`{} @ ..` can't bind `{}`, whose struct has generics of its own that the \
+                     hint doesn't name; name them, as in `{}<..>(..)`, or bind its fields instead",
+                    binding.ident, erased.ident, erased.ident
+                ),
+            );
+            match &mut errors {
+                Some(existing) => existing.combine(e),
+                None => errors = Some(e),
+            }
+        }
+    }
+    // Nor can their hooks hand out fields to mutate
+    if input.is_mut {
+        for arm in arms {
+            if let ArmDispatch::Erased(erased, pat) = arm_dispatch(arm, infos, &hint_generics) {
+                let e = syn::Error::new_spanned(
+                    &pat,
+                    format!(
+                        "`match_t!(mut ..)` can't reach the fields of `{}`, whose struct has \
+                         generics of its own that the hint doesn't name; name them, as in `{}<..>(..)`",
+                        erased.ident, erased.ident
+                    ),
+                );
+                match &mut errors {
+                    Some(existing) => existing.combine(e),
+                    None => errors = Some(e),
+                }
+            }
+        }
+    }
+    // Arms are matched with their `box P` positions split off; arms whose
+    // pattern doesn't parse are left for the compiler to report
+    let mut patterns = Vec::new();
+    for arm in arms {
+        let pattern = match parse_arm_pat(&arm.pattern) {
+            Ok(pat) => ArmPattern::new(pat, infos, &hint_generics).map(Some),
+            Err(_) => Ok(None),
+        };
+        match pattern {
+            Ok(pattern) => patterns.push(pattern),
+            Err(e) => match &mut errors {
+                Some(existing) => existing.combine(e),
+                None => errors = Some(e),
+            },
+        }
+    }
+    if let Some(e) = errors {
+        return Err(e);
+    }
+
+    let expr = &input.expr;
+    let scrutinee = internal_ident("__expr");
+    let type_id = internal_ident("__type_id");
+    let matched = internal_ident("__matched_idx");
+    let index = internal_ident("__idx");
+    let value = internal_ident("__value");
+    let found = internal_ident("__found");
+    let discriminants = arm_discriminants(arms, infos);
+    let tried: Vec<_> = arms
+        .iter()
+        .map(|arm| type_label(&extract_type_and_pattern(&arm.pattern).0))
+        .collect();
+    // The `else` clause binds the value no arm matched, still owned in move
+    // mode
+    let fallback = match (fallback, &input.otherwise) {
+        (Some(arm), _) => Some(arm.body.to_token_stream()),
+        (None, Some(ElseClause { binding, body })) => {
+            let binding = binding.iter();
+            let stmts = &body.stmts;
+            Some(quote! {
+                {
+                    #(let #binding = #scrutinee;)*
+                    #(#stmts)*
+                }
+            })
+        }
+        (None, None) => None,
+    };
+
+    if input.is_move {
+        // `try` hands back the box, still owned, when no arm matches
+        let unmatched = match (fallback, input.is_try) {
+            (Some(body), false) => body,
+            (Some(body), true) => quote! { ::core::result::Result::Ok(#body) },
+            (None, false) => mismatch_panic(&[&scrutinee], &tried),
+            (None, true) => quote! { ::core::result::Result::Err(#scrutinee) },
+        };
+        let wildcard: Pat = syn::parse_quote!(_);
+        let invalid_index = unreachable_branch("Invalid match index in match_t!");
+
+        let type_checks: Vec<_> = arms
+            .iter()
+            .enumerate()
+            .map(|(idx, arm)| {
+                // The whole pattern is tested by reference before the box is
+                // consumed, so a value no arm matches is left intact
+                let (test, nested) = match &patterns[idx] {
+                    Some(pattern) => (&pattern.test, nested_test(&pattern.test_nested)),
+                    None => (&wildcard, quote! { true }),
+                };
+                let check = match arm_dispatch(arm, infos, &hint_generics) {
+                    ArmDispatch::Downcast(type_name) => {
+                        // Matched by reference when boxes were split off, so
+                        // their bindings borrow them
+                        let place = match &patterns[idx] {
+                            Some(pattern) if !pattern.test_nested.is_empty() => quote! { #value },
+                            _ => quote! { *#value },
+                        };
+                        quote! {
+                            #type_id == ::core::any::TypeId::of::<#type_name>()
+                                && match (&*#scrutinee as &dyn ::core::any::Any)
+                                    .downcast_ref::<#type_name>()
+                                {
+                                    ::core::option::Option::Some(#value) => {
+                                        match #place {
+                                            #test => #nested,
+                                            #[allow(unreachable_patterns)]
+                                            _ => false,
+                                        }
+                                    }
+                                    ::core::option::Option::None => false,
+                                }
+                        }
+                    }
+                    ArmDispatch::Erased(erased, _) => {
+                        let ref_hook = ref_hook_ident(&erased.ident);
+                        let pattern = erased_tuple_pattern(test, erased);
+                        quote! {
+                            match #scrutinee.#ref_hook() {
+                                ::core::option::Option::Some(#pattern) => #nested,
+                                #[allow(unreachable_patterns)]
+                                _ => false,
+                            }
+                        }
+                    }
+                    ArmDispatch::View(_) => {
+                        unreachable!("borrowing enums are matched by reference")
+                    }
+                };
+
+                quote! {
+                    let #found = #matched.is_none() && #check;
+                    if #found {
+                        #matched = ::core::option::Option::Some(#idx);
+                    }
+                }
+            })
+            .collect();
+        let type_checks = match &discriminants {
+            Some(discriminants) => dispatch_on_discriminant(&scrutinee, type_checks, discriminants),
+            None => quote! { #(#type_checks)* },
+        };
+        // The value's `TypeId` is looked up once, and each arm's downcast
+        // check compares against it. That is a linear scan over the arms
+        // without a hint; with one, the discriminant `match` above has
+        // already narrowed them to the value's variant.
+        let downcasts = arms.iter().any(|arm| {
+            matches!(
+                arm_dispatch(arm, infos, &hint_generics),
+                ArmDispatch::Downcast(_)
+            )
+        });
+        let type_id_lookup = downcasts.then(|| {
+            let owned_items = owned_items();
+            quote! {
+                #owned_items
+                let #type_id = (&*#scrutinee as &dyn ::core::any::Any).type_id();
+            }
+        });
+        let shared = internal_ident("__Shared");
+        let get = internal_ident("__get");
+
+        let match_arms = arms.iter().enumerate().map(|(idx, arm)| {
+            let (pattern, body) = match &patterns[idx] {
+                Some(pattern) => (
+                    pattern.pat.to_token_stream(),
+                    nested_move(&pattern.nested, arm.body.to_token_stream()),
+                ),
+                None => (
+                    extract_type_and_pattern(&arm.pattern).1,
+                    arm.body.to_token_stream(),
+                ),
+            };
+
+            match arm_dispatch(arm, infos, &hint_generics) {
+                // Only taken once the type check above found a `type_name`
+                ArmDispatch::Downcast(type_name) => {
+                    let taken = take_owned(&type_name, &scrutinee);
+                    quote! {
+                        #idx => {
+                            // Mutable for `ref mut` bindings
+                            #[allow(unused_mut)]
+                            let mut #value = match #taken {
+                                ::core::result::Result::Ok(#value) => #value,
+                                ::core::result::Result::Err(#value) => (&#shared(&*#value)).#get(),
+                            };
+                            match #value {
+                                #pattern => #body,
+                                #[allow(unreachable_patterns)]
+                                _ => ::core::panic!("Pattern match failed in match_t!")
+                            }
+                        }
+                    }
+                }
+                ArmDispatch::View(_) => unreachable!("borrowing enums are matched by reference"),
+                ArmDispatch::Erased(erased, pat) => {
+                    let into_hook = into_hook_ident(&erased.ident);
+                    let pat = patterns[idx].as_ref().map_or(&pat, |pattern| &pattern.pat);
+                    let tuple_pattern = erased_tuple_pattern(pat, erased);
+                    quote! {
+                        #idx => {
+                            match #scrutinee.#into_hook() {
+                                ::core::result::Result::Ok(#tuple_pattern) => #body,
+                                #[allow(unreachable_patterns)]
+                                _ => ::core::panic!("Pattern match failed in match_t!")
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        let mut matched_arm = quote! {
+            match #index {
+                #(#match_arms,)*
+                _ => #invalid_index
+            }
+        };
+        if input.is_try {
+            matched_arm = quote! { ::core::result::Result::Ok(#matched_arm) };
+        }
+
+        Ok(quote! {
+            {
+                let #scrutinee = #expr;
+                let mut #matched: ::core::option::Option<usize> =
+                    ::core::option::Option::None;
+                #type_id_lookup
+
+                #type_checks
+
+                match #matched {
+                    ::core::option::Option::Some(#index) => #matched_arm,
+                    ::core::option::Option::None => #unmatched
+                }
+            }
+        })
+    } else {
+        // Arms break out of a labeled block rather than return from a
+        // closure, so `?` and `return` in a body act on the enclosing function
+        let label = match_label(depth);
+        let found = internal_ident("__found");
+        let match_arms: Vec<_> = arms.iter().enumerate().map(|(idx, arm)| {
+            let body = &arm.body;
+            // Spanned at the body, so an arm of another type is reported
+            // there rather than at the enum definition
+            let found_value = if input.is_try {
+                quote_spanned! {body.span()=> ::core::option::Option::Some(#found) }
+            } else {
+                quote_spanned! {body.span()=> #found }
+            };
+            let found = bind_body(body, &found, quote! { break #label #found_value; });
+            let (pattern, found) = match &patterns[idx] {
+                Some(pattern) => (
+                    pattern.pat.to_token_stream(),
+                    nested_ref(&pattern.nested, found, input.is_mut),
+                ),
+                None => (extract_type_and_pattern(&arm.pattern).1, found),
+            };
+
+            match arm_dispatch(arm, infos, &hint_generics) {
+                ArmDispatch::Downcast(type_name) => {
+                    let downcast = if input.is_mut {
+                        quote! { (&mut *#scrutinee as &mut dyn ::core::any::Any).downcast_mut::<#type_name>() }
+                    } else {
+                        quote! { (&*#scrutinee as &dyn ::core::any::Any).downcast_ref::<#type_name>() }
+                    };
+                    quote! {
+                        if let ::core::option::Option::Some(#value) = #downcast {
+                            if let #pattern = #value {
+                                #found
+                            }
+                        }
+                    }
+                }
+                ArmDispatch::Erased(erased, pat) => {
+                    let ref_hook = ref_hook_ident(&erased.ident);
+                    let pat = patterns[idx].as_ref().map_or(&pat, |pattern| &pattern.pat);
+                    let tuple_pattern = erased_tuple_pattern(pat, erased);
+                    quote! {
+                        if let ::core::option::Option::Some(#tuple_pattern) = #scrutinee.#ref_hook() {
+                            #found
+                        }
+                    }
+                }
+                ArmDispatch::View(view) => {
+                    let view_hook = if input.is_mut {
+                        view_mut_hook_ident(&view.ident)
+                    } else {
+                        view_hook_ident(&view.ident)
+                    };
+                    quote! {
+                        if let ::core::option::Option::Some(#value) = #scrutinee.#view_hook() {
+                            if let #pattern = #value {
+                                #found
+                            }
+                        }
+                    }
+                }
+            }
+        }).collect();
+        let match_arms = match &discriminants {
+            Some(discriminants) => dispatch_on_discriminant(&scrutinee, match_arms, discriminants),
+            None => quote! { #(#match_arms)* },
+        };
+        let unmatched = match (fallback, input.is_try) {
+            (Some(body), false) => body,
+            (Some(body), true) => bind_body(
+                &body,
+                &found,
+                quote! { break #label ::core::option::Option::Some(#found); },
+            ),
+            (None, false) => mismatch_panic(&[&scrutinee], &tried),
+            (None, true) => quote! { ::core::option::Option::None },
+        };
+        // Under `mut` the scrutinee is reborrowed, so a `&mut dyn Trait` is
+        // still usable afterwards and a `Box<dyn Trait>` is matched in place
+        let scrutinee_expr = if input.is_mut {
+            quote! { &mut *(#expr) }
+        } else {
+            quote! { #expr }
+        };
+
+        Ok(quote! {
+            {
+                let #scrutinee = #scrutinee_expr;
+                #label: {
+                    #match_arms
+                    #unmatched
+                }
+            }
+        })
+    }
+}
@@ -0,0 +1,263 @@
+//! Variant metadata shared between `type_enum!`, `match_t!`,
+//! `type_enum_impl!` and `extend_enum!`
+//!
+//! Procedural macros cannot see each other's input, so `type_enum!` emits a
+//! hidden `macro_rules!` companion next to the trait that replays the enum
+//! definition. A `match_t!` with an `as Enum<..>` hint expands to a call of
+//! that companion, which forwards the definition together with the original
+//! `match_t!` input to `__match_t_with!`. `type_enum_impl!` calls it with a
+//! leading `@impl`, which routes the definition to `__type_enum_impl_with!`,
+//! and `extend_enum!` with `@extend`, routing it to `__extend_enum_with!`.
+//!
+//! The companion is also re-exported under the trait's own name, in the macro
+//! namespace, so whatever brings the trait into scope brings its companion
+//! along. `match_t!` looks the hint up there and falls back to matching
+//! without a definition when nothing is found: for plain traits, and for
+//! enums whose companion is private to another crate.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{GenericArgument, Generics, Ident, Path, PathArguments, Type, Visibility};
+
+use crate::display_impls::{display_source, DisplaySource};
+use crate::enum_parser::ParsedEnum;
+use crate::namespace::namespace_ident;
+use crate::variant_gen::resolve_trait_type;
+
+/// Name of the companion macro generated for an enum
+pub fn metadata_macro_ident(enum_name: &Ident) -> Ident {
+    format_ident!("__enum_typer_{}", enum_name)
+}
+
+/// `#[type_enum(extensible)]` exports the companion macro from the crate,
+/// which only reaches other crates alongside a `pub` trait
+pub fn check_extensible(parsed: &ParsedEnum) -> syn::Result<()> {
+    if matches!(parsed.vis, Visibility::Public(_)) {
+        return Ok(());
+    }
+    Err(syn::Error::new_spanned(
+        &parsed.ident,
+        "`#[type_enum(extensible)]` lets other crates extend the enum, so it must be `pub`",
+    ))
+}
+
+/// Generate the companion macro that replays the enum definition. Besides
+/// the variants, with their `#[cfg]`s, and the module they live in, the
+/// definition carries what
+/// decides the trait's hidden methods, which variants added by
+/// `extend_enum!` implement too: the derives, the `debug`, `typetag` and
+/// `iter` options, and an empty `impl Display {}` standing for the enum's
+/// `impl Display` section. The `send_sync`, `of` and `rename_variants`
+/// options carry over to the variants `extend_enum!` adds.
+pub fn generate_metadata_macro(
+    parsed: &ParsedEnum,
+    generics_with_static: &Generics,
+) -> TokenStream2 {
+    let enum_name = &parsed.ident;
+    let generics = &parsed.generics;
+    let where_clause = &parsed.generics.where_clause;
+    let macro_name = metadata_macro_ident(enum_name);
+
+    let variants = parsed.variants.iter().map(|variant| {
+        let attrs = variant
+            .attrs
+            .iter()
+            .filter(|a| a.path().is_ident("derive") || a.path().is_ident("cfg"));
+        let ident = &variant.name;
+        let variant_generics = &variant.generics;
+        let variant_where = &variant.generics.where_clause;
+        let fields = &variant.fields;
+        let trait_type = resolve_trait_type(variant, enum_name, generics_with_static);
+        quote! { #(#attrs)* #ident #variant_generics #fields #variant_where : #trait_type }
+    });
+
+    let options = &parsed.options;
+    let allow = &options.allow;
+    let mut replayed_options: Vec<_> = allow.iter().map(|lint| quote! { allow(#lint) }).collect();
+    replayed_options.extend(options.iter.then(|| quote! { iter }));
+    replayed_options.extend(options.debug.then(|| quote! { debug }));
+    replayed_options.extend(options.typetag.then(|| quote! { typetag }));
+    replayed_options.extend(options.send_sync.then(|| quote! { send_sync }));
+    replayed_options.extend(options.of.then(|| quote! { of }));
+    replayed_options.extend(namespace_ident(parsed).map(|module| quote! { namespace = #module }));
+    replayed_options.extend(
+        options
+            .rename_variants
+            .as_ref()
+            .map(|format| quote! { rename_variants = #format }),
+    );
+    let replayed_options =
+        (!replayed_options.is_empty()).then(|| quote! { #[type_enum(#(#replayed_options),*)] });
+    let derives = &options.derives;
+    let implements = options
+        .implements
+        .as_ref()
+        .map(|path| quote! { #[implements(#path)] });
+    let display_section = (display_source(parsed) == Some(DisplaySource::Section))
+        .then(|| quote! { impl ::core::fmt::Display {} });
+    let definition = quote! {
+        {
+            #replayed_options
+            #(#derives)*
+            #implements
+            enum #enum_name #generics #where_clause { #(#variants),* }
+            #display_section
+        }
+    };
+
+    let rules = quote! {
+        (@impl $($input:tt)*) => {
+            ::enum_typer::__type_enum_impl_with! {
+                #definition
+                $($input)*
+            }
+        };
+        (@extend $($input:tt)*) => {
+            ::enum_typer::__extend_enum_with! {
+                #definition
+                $($input)*
+            }
+        };
+        ($($input:tt)*) => {
+            ::enum_typer::__match_t_with! {
+                #definition
+                $($input)*
+            }
+        };
+    };
+
+    // An exported macro lands at the crate root, under a name of its own so
+    // the re-export next to the trait doesn't clash with it there
+    if options.extensible {
+        let exported_name = format_ident!("__enum_typer_export_{}", enum_name);
+        return quote! {
+            #[doc(hidden)]
+            #[macro_export]
+            macro_rules! #exported_name {
+                #rules
+            }
+
+            #[doc(hidden)]
+            #[allow(unused_imports)]
+            pub use #exported_name as #macro_name;
+
+            #[doc(hidden)]
+            #[allow(unused_imports)]
+            pub use #exported_name as #enum_name;
+        };
+    }
+
+    // `macro_rules!` macros can only be re-exported within the crate
+    let reexport_vis = match &parsed.vis {
+        Visibility::Public(_) => quote! { pub(crate) },
+        other => quote! { #other },
+    };
+
+    quote! {
+        #[doc(hidden)]
+        #[allow(unused_macros)]
+        macro_rules! #macro_name {
+            #rules
+        }
+
+        #[doc(hidden)]
+        #[allow(unused_imports)]
+        #reexport_vis use #macro_name;
+
+        #[doc(hidden)]
+        #[allow(unused_imports)]
+        #reexport_vis use #macro_name as #enum_name;
+    }
+}
+
+/// Find the trait path named by a `match_t!` type hint, looking through
+/// references, `dyn` and the usual smart pointers
+pub fn hint_trait_path(ty: &Type) -> Option<Path> {
+    match ty {
+        Type::Reference(reference) => hint_trait_path(&reference.elem),
+        Type::Paren(paren) => hint_trait_path(&paren.elem),
+        Type::TraitObject(object) => object.bounds.iter().find_map(|bound| match bound {
+            syn::TypeParamBound::Trait(t) => Some(t.path.clone()),
+            _ => None,
+        }),
+        Type::Path(type_path) if type_path.qself.is_none() => {
+            let last = type_path.path.segments.last()?;
+            let is_pointer = ["Box", "Rc", "Arc"].iter().any(|p| last.ident == p);
+            match &last.arguments {
+                PathArguments::AngleBracketed(args) if is_pointer => {
+                    args.args.iter().find_map(|arg| match arg {
+                        GenericArgument::Type(inner) => hint_trait_path(inner),
+                        _ => None,
+                    })
+                }
+                _ => Some(type_path.path.clone()),
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Path of the companion macro for the trait named by a type hint, e.g.
+/// `shapes::__enum_typer_Shape` for `Box<dyn shapes::Shape>`
+pub fn metadata_macro_path(hint: &TokenStream2) -> Option<Path> {
+    let ty = syn::parse2::<Type>(hint.clone()).ok()?;
+    let mut path = hint_trait_path(&ty)?;
+    let last = path.segments.last_mut()?;
+    last.ident = metadata_macro_ident(&last.ident);
+    last.arguments = PathArguments::None;
+    Some(path)
+}
+
+/// Hand `input` to the companion macro of the trait named by a type hint,
+/// looked up under the trait's name. Where that name has no companion, a
+/// stand-in takes the call and passes `{ @opaque Enum }` on to
+/// `__match_t_with!` in place of the definition.
+///
+/// The lookup imports the hint's path under the companion's name next to a
+/// glob import of the stand-in, which the explicit import shadows when it
+/// finds a macro. The call sits in a block of its own, so resolving the
+/// import doesn't wait on the call's expansion.
+pub fn companion_call(hint: &TokenStream2, input: TokenStream2) -> Option<TokenStream2> {
+    let ty = syn::parse2::<Type>(hint.clone()).ok()?;
+    let mut path = hint_trait_path(&ty)?;
+    let last = path.segments.last_mut()?;
+    last.arguments = PathArguments::None;
+    let enum_name = last.ident.clone();
+    let fallback = format_ident!("__enum_typer_fallback");
+    let companion = format_ident!("__enum_typer_companion");
+    Some(quote! {
+        {
+            mod #fallback {
+                #[allow(unused_macros)]
+                macro_rules! #companion {
+                    ($($input:tt)*) => {
+                        ::enum_typer::__match_t_with! {
+                            { @opaque #enum_name }
+                            $($input)*
+                        }
+                    };
+                }
+                #[allow(unused_imports)]
+                pub(crate) use #companion;
+            }
+            #[allow(unused_imports)]
+            use #fallback::#companion;
+            {
+                #[allow(unused_imports)]
+                use #path as #companion;
+                {
+                    #companion! { #input }
+                }
+            }
+        }
+    })
+}
+
+/// The enum named by a type hint, e.g. `Shape` for `&dyn shapes::Shape<i32>`
+pub fn hint_enum_ident(hint: &TokenStream2) -> Option<Ident> {
+    let ty = syn::parse2::<Type>(hint.clone()).ok()?;
+    hint_trait_path(&ty)?
+        .segments
+        .last()
+        .map(|segment| segment.ident.clone())
+}
@@ -0,0 +1,186 @@
+//! `#[type_enum(namespace)]`: the generated items live in a module named
+//! after the enum, `shape` for `Shape`, so variant structs don't collide
+//! with other items of the enclosing module. The trait and the other items
+//! the enum is used through are re-exported next to it; the variant structs
+//! only with `reexport`.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use std::collections::HashSet;
+use syn::{Attribute, Ident, Item, UseTree, Visibility};
+
+use crate::enum_parser::ParsedEnum;
+use crate::helpers::{cfg_attrs, snake_case_ident};
+
+/// The namespace module's name: the one given, or the enum's in snake case
+pub fn namespace_ident(parsed: &ParsedEnum) -> Option<Ident> {
+    let module = parsed.options.namespace.as_ref()?;
+    Some(
+        module
+            .clone()
+            .unwrap_or_else(|| snake_case_ident(&parsed.ident)),
+    )
+}
+
+/// Reject a constructors module of the same name as the namespace module,
+/// which both would be re-exported under
+pub fn check_namespace(parsed: &ParsedEnum, module: &Ident) -> syn::Result<()> {
+    let Some(constructors) = &parsed.options.constructors else {
+        return Ok(());
+    };
+    let constructors = constructors
+        .clone()
+        .unwrap_or_else(|| snake_case_ident(&parsed.ident));
+    if constructors != *module {
+        return Ok(());
+    }
+    Err(syn::Error::new(
+        module.span(),
+        format!(
+            "the namespace and constructors modules are both named `{}`; \
+             name one of them, e.g. `constructors = make_{}`",
+            module, module
+        ),
+    ))
+}
+
+/// A visibility meaning the same one module further down: private items
+/// become `pub(super)`, `pub(super)` ones `pub(in super::super)`
+pub fn nested_visibility(vis: &Visibility) -> Visibility {
+    match vis {
+        Visibility::Public(_) => vis.clone(),
+        Visibility::Inherited => syn::parse_quote!(pub(super)),
+        Visibility::Restricted(restricted) => {
+            let path = &restricted.path;
+            let first = &path.segments[0].ident;
+            if first == "self" {
+                let rest = path.segments.iter().skip(1);
+                syn::parse_quote!(pub(in super #(:: #rest)*))
+            } else if first == "super" {
+                syn::parse_quote!(pub(in super::#path))
+            } else {
+                vis.clone()
+            }
+        }
+    }
+}
+
+/// The visibility an item of the namespace module has seen from the module
+/// around it, to re-export it with. `None` for items private to the
+/// namespace module.
+fn outer_visibility(vis: &Visibility) -> Option<Visibility> {
+    match vis {
+        Visibility::Public(_) => Some(vis.clone()),
+        Visibility::Inherited => None,
+        Visibility::Restricted(restricted) => {
+            let path = &restricted.path;
+            let first = &path.segments[0].ident;
+            if first == "self" {
+                None
+            } else if first != "super" {
+                Some(vis.clone())
+            } else if path.segments.len() == 1 {
+                Some(Visibility::Inherited)
+            } else {
+                let rest = path.segments.iter().skip(1);
+                Some(syn::parse_quote!(pub(in #(#rest)::*)))
+            }
+        }
+    }
+}
+
+/// Shift the enum's visibility and its variants' field visibilities one
+/// module down, for the items generated inside the namespace module
+pub fn move_into_namespace(parsed: &mut ParsedEnum) {
+    parsed.vis = nested_visibility(&parsed.vis);
+    for variant in &mut parsed.variants {
+        for field in variant.fields.iter_mut() {
+            field.vis = nested_visibility(&field.vis);
+        }
+    }
+}
+
+/// The name, visibility and attributes of an item the expansion declares
+fn named_item(item: &Item) -> Option<(&Ident, &Visibility, &[Attribute])> {
+    let (ident, vis, attrs) = match item {
+        Item::Trait(item) => (&item.ident, &item.vis, &item.attrs),
+        Item::Struct(item) => (&item.ident, &item.vis, &item.attrs),
+        Item::Enum(item) => (&item.ident, &item.vis, &item.attrs),
+        Item::Mod(item) => (&item.ident, &item.vis, &item.attrs),
+        Item::Type(item) => (&item.ident, &item.vis, &item.attrs),
+        Item::Fn(item) => (&item.sig.ident, &item.vis, &item.attrs),
+        Item::Use(item) => (use_name(&item.tree)?, &item.vis, &item.attrs),
+        _ => return None,
+    };
+    Some((ident, vis, attrs))
+}
+
+/// The name a single-item `use` declares
+fn use_name(tree: &UseTree) -> Option<&Ident> {
+    match tree {
+        UseTree::Path(path) => use_name(&path.tree),
+        UseTree::Name(name) => Some(&name.ident),
+        UseTree::Rename(rename) => Some(&rename.rename),
+        UseTree::Glob(_) | UseTree::Group(_) => None,
+    }
+}
+
+/// Wrap the expansion in the namespace module and re-export what the
+/// module around it can see, leaving out the variant structs unless
+/// `reexport` is set
+pub fn wrap_in_namespace(
+    expanded: TokenStream2,
+    parsed: &ParsedEnum,
+    module: &Ident,
+    vis: &Visibility,
+) -> syn::Result<TokenStream2> {
+    let file: syn::File = syn::parse2(expanded.clone())?;
+    let variants: HashSet<&Ident> = parsed.variants.iter().map(|v| &v.ident).collect();
+    // The companion macro's `use` under the trait's name goes along with the
+    // trait's re-export, which covers every namespace
+    let declared: HashSet<&Ident> = file
+        .items
+        .iter()
+        .filter(|item| !matches!(item, Item::Use(_)))
+        .filter_map(|item| named_item(item).map(|(ident, _, _)| ident))
+        .collect();
+
+    let reexports: Vec<_> = file
+        .items
+        .iter()
+        .filter_map(|item| {
+            let (ident, item_vis, attrs) = named_item(item)?;
+            let is_variant = matches!(item, Item::Struct(_)) && variants.contains(ident);
+            if is_variant && !parsed.options.reexport {
+                return None;
+            }
+            if matches!(item, Item::Use(_)) && declared.contains(ident) {
+                return None;
+            }
+            let outer_vis = outer_visibility(item_vis)?;
+            let cfgs = cfg_attrs(attrs);
+            let hidden = attrs.iter().filter(|a| {
+                a.path().is_ident("doc") && a.parse_args::<Ident>().is_ok_and(|arg| arg == "hidden")
+            });
+            Some(quote! {
+                #(#cfgs)*
+                #(#hidden)*
+                #[allow(unused_imports, deprecated)]
+                #outer_vis use #module::#ident;
+            })
+        })
+        .collect();
+
+    let doc = format!("The items generated for [`{}`]", parsed.ident);
+    Ok(quote! {
+        #[doc = #doc]
+        #vis mod #module {
+            #[allow(unused_imports)]
+            use super::*;
+
+            #expanded
+        }
+
+        #(#reexports)*
+    })
+}
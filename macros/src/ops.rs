@@ -0,0 +1,398 @@
+//! `std::ops` impls requested with `#[ops(Op = target)]`
+//!
+//! The target is either a method of the enum, which `Box<dyn Enum<..>>`
+//! delegates to, or a variant, which the operator constructs from its operands.
+//! `Index` and `IndexMut` always delegate to a method returning a reference.
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use std::collections::HashSet;
+use syn::{FnArg, Generics, Ident, ReturnType, Type};
+
+use crate::codegen::box_path;
+use crate::enum_parser::{ParsedEnum, ParsedMethod, ParsedVariant};
+use crate::helpers::{collect_ordered_type_params, field_members, merge_generics};
+use crate::options::OpImpl;
+use crate::variant_gen::{extract_type_params_from_trait, is_boxed_receiver, resolve_trait_type};
+
+/// Operator traits and their method, with the number of operands they take
+const OPERATORS: &[(&str, &str, usize)] = &[
+    ("Add", "add", 2),
+    ("Sub", "sub", 2),
+    ("Mul", "mul", 2),
+    ("Div", "div", 2),
+    ("Rem", "rem", 2),
+    ("BitAnd", "bitand", 2),
+    ("BitOr", "bitor", 2),
+    ("BitXor", "bitxor", 2),
+    ("Shl", "shl", 2),
+    ("Shr", "shr", 2),
+    ("Neg", "neg", 1),
+    ("Not", "not", 1),
+];
+
+/// The operator's method and operand count
+fn lookup_operator(op: &Ident) -> syn::Result<(Ident, usize)> {
+    OPERATORS
+        .iter()
+        .find(|(name, _, _)| op == name)
+        .map(|(_, method, arity)| (Ident::new(method, op.span()), *arity))
+        .ok_or_else(|| {
+            let names: Vec<_> = OPERATORS.iter().map(|(name, _, _)| *name).collect();
+            syn::Error::new(
+                op.span(),
+                format!(
+                    "unsupported operator `{}`; expected one of {}, Index, IndexMut",
+                    op,
+                    names.join(", ")
+                ),
+            )
+        })
+}
+
+/// The error for an operator backed by a method without a `self` receiver,
+/// which the operator's trait object has no way to call
+fn missing_receiver(op_impl: &OpImpl, method_name: &Ident) -> syn::Error {
+    syn::Error::new(
+        op_impl.target.span(),
+        format!(
+            "`{}` needs a `self` receiver to back `{}`",
+            method_name, op_impl.op
+        ),
+    )
+}
+
+/// `impl Op<Rhs> for Box<dyn Enum<..>>` calling the method named by the target
+fn delegate_to_method(
+    op_impl: &OpImpl,
+    op_method: &Ident,
+    arity: usize,
+    method: &ParsedMethod,
+    parsed: &ParsedEnum,
+    generics_with_static: &Generics,
+) -> syn::Result<TokenStream2> {
+    let box_ty = box_path();
+    let op = &op_impl.op;
+    let sig = &method.sig;
+    let method_name = &sig.ident;
+    let enum_name = &parsed.ident;
+
+    if sig.generics.type_params().next().is_some() {
+        return Err(syn::Error::new(
+            op_impl.target.span(),
+            format!(
+                "`{}` is generic and cannot be called on `Box<dyn {}>`",
+                method_name, enum_name
+            ),
+        ));
+    }
+
+    let operands: Vec<&Type> = sig
+        .inputs
+        .iter()
+        .filter_map(|arg| match arg {
+            FnArg::Typed(pat_type) => Some(&*pat_type.ty),
+            FnArg::Receiver(_) => None,
+        })
+        .collect();
+    let Some(receiver) = sig.receiver() else {
+        return Err(missing_receiver(op_impl, method_name));
+    };
+    if operands.len() + 1 != arity {
+        return Err(syn::Error::new(
+            op_impl.target.span(),
+            format!(
+                "`{}` takes {} argument{} besides `self`, but `{}` needs {}",
+                method_name,
+                operands.len(),
+                if operands.len() == 1 { "" } else { "s" },
+                op,
+                arity - 1
+            ),
+        ));
+    }
+
+    let by_ref_or_box = receiver.reference.is_some() || is_boxed_receiver(sig);
+    if !by_ref_or_box {
+        return Err(syn::Error::new(
+            op_impl.target.span(),
+            format!(
+                "`{}` must take `&self`, `&mut self` or `self: Box<Self>` to back an operator",
+                method_name
+            ),
+        ));
+    }
+    let self_binding = if receiver.mutability.is_some() {
+        quote! { mut self }
+    } else {
+        quote! { self }
+    };
+
+    let output = match &sig.output {
+        ReturnType::Default => quote! { () },
+        ReturnType::Type(_, ty) => quote! { #ty },
+    };
+
+    let (impl_generics, ty_generics, where_clause) = generics_with_static.split_for_impl();
+    let self_ty = quote! { #box_ty<dyn #enum_name #ty_generics> };
+
+    Ok(match operands.first() {
+        Some(rhs) => quote! {
+            impl #impl_generics ::core::ops::#op<#rhs> for #self_ty #where_clause {
+                type Output = #output;
+
+                fn #op_method(#self_binding, rhs: #rhs) -> Self::Output {
+                    self.#method_name(rhs)
+                }
+            }
+        },
+        None => quote! {
+            impl #impl_generics ::core::ops::#op for #self_ty #where_clause {
+                type Output = #output;
+
+                fn #op_method(#self_binding) -> Self::Output {
+                    self.#method_name()
+                }
+            }
+        },
+    })
+}
+
+/// `impl Index<Idx> for Box<dyn Enum<..>>` (or `IndexMut`) forwarding to a
+/// method like `fn get(&self, index: usize) -> &T`
+fn delegate_indexing(
+    op_impl: &OpImpl,
+    method: &ParsedMethod,
+    parsed: &ParsedEnum,
+    generics_with_static: &Generics,
+) -> syn::Result<TokenStream2> {
+    let box_ty = box_path();
+    let op = &op_impl.op;
+    let sig = &method.sig;
+    let method_name = &sig.ident;
+    let enum_name = &parsed.ident;
+    let mutable = op == "IndexMut";
+
+    let Some(receiver) = sig.receiver() else {
+        return Err(missing_receiver(op_impl, method_name));
+    };
+    let indices: Vec<&Type> = sig
+        .inputs
+        .iter()
+        .filter_map(|arg| match arg {
+            FnArg::Typed(pat_type) => Some(&*pat_type.ty),
+            FnArg::Receiver(_) => None,
+        })
+        .collect();
+    let output = match &sig.output {
+        ReturnType::Type(_, ty) => match &**ty {
+            Type::Reference(r) if r.mutability.is_some() == mutable => Some(&r.elem),
+            _ => None,
+        },
+        ReturnType::Default => None,
+    };
+
+    let (self_ref, returns) = if mutable {
+        ("&mut self", "&mut")
+    } else {
+        ("&self", "&")
+    };
+    let receiver_matches = receiver.reference.is_some() && receiver.mutability.is_some() == mutable;
+    let (Some(output), [index], true) = (output, indices.as_slice(), receiver_matches) else {
+        return Err(syn::Error::new(
+            op_impl.target.span(),
+            format!(
+                "`{}` backs `{}`, so it must look like `fn {}({}, index: Idx) -> {} Output`",
+                method_name, op, method_name, self_ref, returns
+            ),
+        ));
+    };
+    if sig.generics.type_params().next().is_some() {
+        return Err(syn::Error::new(
+            op_impl.target.span(),
+            format!(
+                "`{}` is generic and cannot be called on `Box<dyn {}>`",
+                method_name, enum_name
+            ),
+        ));
+    }
+
+    let (impl_generics, ty_generics, where_clause) = generics_with_static.split_for_impl();
+    let self_ty = quote! { #box_ty<dyn #enum_name #ty_generics> };
+
+    Ok(if mutable {
+        quote! {
+            impl #impl_generics ::core::ops::IndexMut<#index> for #self_ty #where_clause {
+                fn index_mut(&mut self, index: #index) -> &mut Self::Output {
+                    (**self).#method_name(index)
+                }
+            }
+        }
+    } else {
+        quote! {
+            impl #impl_generics ::core::ops::Index<#index> for #self_ty #where_clause {
+                type Output = #output;
+
+                fn index(&self, index: #index) -> &Self::Output {
+                    (**self).#method_name(index)
+                }
+            }
+        }
+    })
+}
+
+/// `impl Op<F1> for F0` building the variant from its operands
+fn construct_variant(
+    op_impl: &OpImpl,
+    op_method: &Ident,
+    arity: usize,
+    variant: &ParsedVariant,
+    parsed: &ParsedEnum,
+    generics_with_static: &Generics,
+) -> syn::Result<TokenStream2> {
+    let box_ty = box_path();
+    let op = &op_impl.op;
+    let variant_name = &variant.ident;
+
+    if variant.generics.type_params().next().is_some() {
+        return Err(syn::Error::new(
+            op_impl.target.span(),
+            format!(
+                "`{}` has generics of its own, so its operands have no single type to implement `{}` for",
+                variant_name, op
+            ),
+        ));
+    }
+
+    let fields: Vec<_> = variant.fields.iter().collect();
+    if fields.len() != arity {
+        return Err(syn::Error::new(
+            op_impl.target.span(),
+            format!(
+                "`{}` has {} field{}, but `{}` takes {} operand{}",
+                variant_name,
+                fields.len(),
+                if fields.len() == 1 { "" } else { "s" },
+                op,
+                arity,
+                if arity == 1 { "" } else { "s" }
+            ),
+        ));
+    }
+
+    let members = field_members(&variant.fields);
+    let trait_type = resolve_trait_type(variant, &parsed.ident, generics_with_static);
+    let lhs = &fields[0].ty;
+
+    // Only the enum parameters the operands and the output mention are bound
+    let all_type_params: HashSet<String> = collect_ordered_type_params(generics_with_static)
+        .into_iter()
+        .collect();
+    let mut used = extract_type_params_from_trait(&trait_type, &all_type_params);
+    for field in &fields {
+        used.extend(extract_type_params_from_trait(
+            &field.ty.to_token_stream(),
+            &all_type_params,
+        ));
+    }
+    let impl_generics = merge_generics(&Generics::default(), generics_with_static, &used);
+    let (impl_generics, _, where_clause) = impl_generics.split_for_impl();
+
+    Ok(match fields.get(1) {
+        Some(rhs_field) => {
+            let rhs = &rhs_field.ty;
+            let (lhs_member, rhs_member) = (&members[0], &members[1]);
+            quote! {
+                impl #impl_generics ::core::ops::#op<#rhs> for #lhs #where_clause {
+                    type Output = #box_ty<dyn #trait_type>;
+
+                    fn #op_method(self, rhs: #rhs) -> Self::Output {
+                        #box_ty::new(#variant_name { #lhs_member: self, #rhs_member: rhs })
+                    }
+                }
+            }
+        }
+        None => {
+            let member = &members[0];
+            quote! {
+                impl #impl_generics ::core::ops::#op for #lhs #where_clause {
+                    type Output = #box_ty<dyn #trait_type>;
+
+                    fn #op_method(self) -> Self::Output {
+                        #box_ty::new(#variant_name { #member: self })
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Generate every operator impl requested on the enum
+pub fn generate_operator_impls(
+    parsed: &ParsedEnum,
+    generics_with_static: &Generics,
+) -> syn::Result<TokenStream2> {
+    let mut impls = Vec::new();
+
+    for op_impl in &parsed.options.ops {
+        let target = &op_impl.target;
+        let method = parsed.methods.iter().find(|m| m.sig.ident == *target);
+        let variant = parsed.variants.iter().find(|v| v.name == *target);
+        let indexing = op_impl.op == "Index" || op_impl.op == "IndexMut";
+
+        let generated = match (method, variant) {
+            (Some(method), _) if indexing => {
+                delegate_indexing(op_impl, method, parsed, generics_with_static)?
+            }
+            (None, _) if indexing => {
+                return Err(syn::Error::new(
+                    target.span(),
+                    format!(
+                        "`{}` needs a method of `{}` to forward to",
+                        op_impl.op, parsed.ident
+                    ),
+                ));
+            }
+            (Some(method), _) => {
+                let (op_method, arity) = lookup_operator(&op_impl.op)?;
+                delegate_to_method(
+                    op_impl,
+                    &op_method,
+                    arity,
+                    method,
+                    parsed,
+                    generics_with_static,
+                )?
+            }
+            (None, Some(variant)) => {
+                let (op_method, arity) = lookup_operator(&op_impl.op)?;
+                construct_variant(
+                    op_impl,
+                    &op_method,
+                    arity,
+                    variant,
+                    parsed,
+                    generics_with_static,
+                )?
+            }
+            (None, None) => {
+                return Err(syn::Error::new(
+                    target.span(),
+                    format!(
+                        "`{}` is neither a method nor a variant of `{}`",
+                        target, parsed.ident
+                    ),
+                ));
+            }
+        };
+
+        let lint_attrs = parsed.options.lint_attrs();
+        impls.push(quote! {
+            #[automatically_derived]
+            #lint_attrs
+            #generated
+        });
+    }
+
+    Ok(quote! { #(#impls)* })
+}
@@ -0,0 +1,309 @@
+//! Options written as attributes on the enum inside `type_enum!`:
+//! `#[type_enum(...)]`, `#[ops(...)]`, `#[implements(...)]` and
+//! `#[assert_impl(...)]`
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::meta::ParseNestedMeta;
+use syn::{punctuated::Punctuated, Attribute, Ident, LitStr, Path, PathArguments, Token};
+
+use crate::codegen::allocates;
+
+/// An operator trait to implement, from `#[ops(Add = target)]`
+pub struct OpImpl {
+    /// The `std::ops` trait, e.g. `Add`
+    pub op: Ident,
+    /// The method to delegate to, or the variant to construct
+    pub target: Ident,
+}
+
+/// Options controlling how `type_enum!` generates code
+#[derive(Default)]
+pub struct EnumOptions {
+    /// Extra lints allowed on every generated item, from `allow(...)`
+    pub allow: Vec<Path>,
+    /// Operator traits to implement, from `#[ops(...)]`
+    pub ops: Vec<OpImpl>,
+    /// Generate iterators for a list-shaped enum, from `iter`
+    pub iter: bool,
+    /// Add `index_type_id()` to the generated trait, from `index_type_id`
+    pub index_type_id: bool,
+    /// Generate `Variant::of(..)` constructors boxing trait object fields,
+    /// from `of`
+    pub of: bool,
+    /// Derive `Debug` on every variant struct and for the trait object, from
+    /// `debug`
+    pub debug: bool,
+    /// Serialize and deserialize trait objects with serde, from `serde`
+    pub serde: bool,
+    /// Leave trait object serialization to typetag, from `typetag`
+    pub typetag: bool,
+    /// Generate a plain `<Enum>Kind` enum converting to and from trait
+    /// objects, from `kind`
+    pub kind: bool,
+    /// Generate a module of boxing constructors, from `constructors`, or
+    /// `constructors = name` to name the module
+    pub constructors: Option<Option<Ident>>,
+    /// Export the companion macro so other crates can reach the enum with
+    /// `extend_enum!`, `match_t!` and `type_enum_impl!`, from `extensible`
+    pub extensible: bool,
+    /// Make the generated trait `Send + Sync`, bounding the enum's type
+    /// parameters and the variants' by both, from `send_sync`
+    pub send_sync: bool,
+    /// Put the generated items in a module, from `namespace`, or
+    /// `namespace = name` to name it
+    pub namespace: Option<Option<Ident>>,
+    /// Re-export the variant structs from the namespace module, from
+    /// `reexport`
+    pub reexport: bool,
+    /// Name the variant structs after a pattern, from
+    /// `rename_variants = "Shape{}"`; arms keep the declared names
+    pub rename_variants: Option<LitStr>,
+    /// An existing trait to implement instead of generating one, from
+    /// `#[implements(path::Trait)]`
+    pub implements: Option<Path>,
+    /// Traits every variant struct must implement, from `#[assert_impl(...)]`
+    pub assert_impl: Vec<Path>,
+    /// `#[derive(...)]` attributes put on every variant struct that has none
+    /// of its own
+    pub derives: Vec<Attribute>,
+}
+
+impl EnumOptions {
+    /// Collect options from every `#[type_enum(...)]`, `#[ops(...)]`,
+    /// `#[implements(...)]` and `#[assert_impl(...)]` attribute
+    pub fn from_attrs(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut options = EnumOptions::default();
+        let mut reexport = None;
+
+        for attr in attrs.iter().filter(|a| a.path().is_ident("type_enum")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("allow") {
+                    meta.parse_nested_meta(|lint| {
+                        options.allow.push(lint.path);
+                        Ok(())
+                    })
+                } else if meta.path.is_ident("iter") {
+                    require_alloc(&meta, "`iter` walks lists linked through boxed tails")?;
+                    options.iter = true;
+                    Ok(())
+                } else if meta.path.is_ident("index_type_id") {
+                    options.index_type_id = true;
+                    Ok(())
+                } else if meta.path.is_ident("of") {
+                    require_alloc(&meta, "`of` boxes the trait object fields")?;
+                    options.of = true;
+                    Ok(())
+                } else if meta.path.is_ident("debug") {
+                    options.debug = true;
+                    Ok(())
+                } else if meta.path.is_ident("serde") || meta.path.is_ident("typetag") {
+                    let serde = meta.path.is_ident("serde");
+                    require_alloc(&meta, "serialization hands out `Box<dyn Enum>`")?;
+                    if (serde && options.typetag) || (!serde && options.serde) {
+                        return Err(meta.error(
+                            "`serde` and `typetag` both serialize the trait objects; pick one",
+                        ));
+                    }
+                    options.serde |= serde;
+                    options.typetag |= !serde;
+                    Ok(())
+                } else if meta.path.is_ident("kind") {
+                    require_alloc(&meta, "`kind` converts to and from `Box<dyn Enum>`")?;
+                    options.kind = true;
+                    Ok(())
+                } else if meta.path.is_ident("constructors") {
+                    require_alloc(&meta, "`constructors` returns boxed variants")?;
+                    let module = if meta.input.peek(Token![=]) {
+                        Some(meta.value()?.parse::<Ident>()?)
+                    } else {
+                        None
+                    };
+                    options.constructors = Some(module);
+                    Ok(())
+                } else if meta.path.is_ident("extensible") {
+                    options.extensible = true;
+                    Ok(())
+                } else if meta.path.is_ident("send_sync") {
+                    options.send_sync = true;
+                    Ok(())
+                } else if meta.path.is_ident("namespace") {
+                    let module = if meta.input.peek(Token![=]) {
+                        Some(meta.value()?.parse::<Ident>()?)
+                    } else {
+                        None
+                    };
+                    options.namespace = Some(module);
+                    Ok(())
+                } else if meta.path.is_ident("reexport") {
+                    options.reexport = true;
+                    reexport = Some(meta.path.clone());
+                    Ok(())
+                } else if meta.path.is_ident("rename_variants") {
+                    let format: LitStr = meta.value()?.parse()?;
+                    if format.value().matches("{}").count() != 1 {
+                        return Err(syn::Error::new_spanned(
+                            &format,
+                            "expected one `{}` where the variant's name goes, e.g. \"Shape{}\"",
+                        ));
+                    }
+                    options.rename_variants = Some(format);
+                    Ok(())
+                } else {
+                    Err(meta.error(
+                        "unknown `type_enum` option; expected `allow(...)`, `iter`, \
+                         `index_type_id`, `of`, `debug`, `serde`, `typetag`, `kind`, \
+                         `constructors`, `extensible`, `send_sync`, `namespace`, `reexport` \
+                         or `rename_variants`",
+                    ))
+                }
+            })?;
+        }
+        if let (Some(path), None) = (&reexport, &options.namespace) {
+            return Err(syn::Error::new_spanned(
+                path,
+                "`reexport` re-exports the variant structs from the enum's namespace module; \
+                 add `namespace` to put them in one",
+            ));
+        }
+
+        for attr in attrs.iter().filter(|a| a.path().is_ident("ops")) {
+            attr.parse_nested_meta(|meta| {
+                require_alloc(
+                    &meta,
+                    "`#[ops(...)]` implements operators on `Box<dyn Enum>`",
+                )?;
+                let Some(op) = meta.path.get_ident().cloned() else {
+                    return Err(meta.error("expected an operator trait such as `Add`"));
+                };
+                let target: Ident = meta.value()?.parse()?;
+                options.ops.push(OpImpl { op, target });
+                Ok(())
+            })?;
+        }
+
+        for attr in attrs.iter().filter(|a| a.path().is_ident("implements")) {
+            let path: Path = attr.parse_args()?;
+            if let Some(segment) = path
+                .segments
+                .iter()
+                .find(|s| !matches!(s.arguments, PathArguments::None))
+            {
+                return Err(syn::Error::new_spanned(
+                    &segment.arguments,
+                    "name the trait without arguments; each variant's `: Enum<..>` supplies them",
+                ));
+            }
+            if options.implements.is_some() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`#[implements(...)]` can only name one trait",
+                ));
+            }
+            if options.iter {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`#[type_enum(iter)]` adds methods to the generated trait, \
+                     so it cannot be combined with `#[implements(...)]`",
+                ));
+            }
+            if options.index_type_id {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`#[type_enum(index_type_id)]` adds a method to the generated trait, \
+                     so it cannot be combined with `#[implements(...)]`",
+                ));
+            }
+            if options.debug {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`#[type_enum(debug)]` adds a method to the generated trait, \
+                     so it cannot be combined with `#[implements(...)]`",
+                ));
+            }
+            if options.serde {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`#[type_enum(serde)]` implements serde for the generated trait's objects, \
+                     so it cannot be combined with `#[implements(...)]`",
+                ));
+            }
+            if options.typetag {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`#[type_enum(typetag)]` annotates the generated trait, \
+                     so it cannot be combined with `#[implements(...)]`",
+                ));
+            }
+            if options.kind {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`#[type_enum(kind)]` downcasts the generated trait's objects, \
+                     so it cannot be combined with `#[implements(...)]`",
+                ));
+            }
+            if options.send_sync {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "`#[type_enum(send_sync)]` adds supertraits to the generated trait, \
+                     so it cannot be combined with `#[implements(...)]`",
+                ));
+            }
+            options.implements = Some(path);
+        }
+
+        for attr in attrs.iter().filter(|a| a.path().is_ident("assert_impl")) {
+            let traits = attr.parse_args_with(Punctuated::<Path, Token![,]>::parse_terminated)?;
+            options.assert_impl.extend(traits);
+        }
+
+        options.derives = attrs
+            .iter()
+            .filter(|a| a.path().is_ident("derive"))
+            .cloned()
+            .collect();
+
+        Ok(options)
+    }
+
+    /// Lint attributes put on every generated item. Generated code follows
+    /// the user's naming (hooks named after variants, lowercase variants),
+    /// binds fields the user's arms may ignore and refers to variants the user
+    /// may have deprecated, so those lints are always allowed on top of the
+    /// configured ones.
+    pub fn lint_attrs(&self) -> TokenStream2 {
+        let allow = &self.allow;
+        quote! {
+            #[allow(
+                non_snake_case,
+                non_camel_case_types,
+                unused_variables,
+                deprecated,
+                clippy::all
+                #(, #allow)*
+            )]
+        }
+    }
+
+    /// Only the lints allowed with `allow(...)`, for items that exist to
+    /// trigger a lint themselves
+    pub fn configured_lint_attrs(&self) -> TokenStream2 {
+        let allow = &self.allow;
+        if allow.is_empty() {
+            return TokenStream2::new();
+        }
+        quote! { #[allow(#(#allow),*)] }
+    }
+}
+
+/// Reject an option whose items are built on `Box` without the `alloc`
+/// feature
+fn require_alloc(meta: &ParseNestedMeta, what: &str) -> syn::Result<()> {
+    if allocates() {
+        return Ok(());
+    }
+    Err(meta.error(format!(
+        "{}, which needs the `alloc` feature of enum-typer",
+        what
+    )))
+}
@@ -0,0 +1,582 @@
+//! Pattern matching parser utilities
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, ToTokens};
+use syn::{parse::ParseStream, Expr, Token};
+
+use crate::codegen::allocates;
+
+pub struct MatchArm {
+    pub pattern: TokenStream2,
+    pub body: Expr,
+}
+
+/// `else |value| { .. }` after the arms of `match_t!`, run with the value no
+/// arm matched; the binding may be left out, as in `else { .. }`
+pub struct ElseClause {
+    pub binding: Option<syn::Pat>,
+    pub body: syn::Block,
+}
+
+pub struct MatchTInput {
+    pub is_move: bool,
+    /// `match_t!(mut ..)` matches a `&mut dyn Trait`, binding fields by
+    /// mutable reference
+    pub is_mut: bool,
+    /// `match_t!(try ..)` yields `None`, or the box back with `move`, for a
+    /// value no arm matches instead of panicking
+    pub is_try: bool,
+    pub expr: TokenStream2,
+    /// Type hints after `as`, one per enum the arms may come from
+    /// (`as Shape<i32> | Token`)
+    pub type_hints: Vec<TokenStream2>,
+    pub arms: Vec<MatchArm>,
+    pub otherwise: Option<ElseClause>,
+}
+
+#[allow(clippy::cmp_owned)]
+pub fn parse_match_t(tokens: TokenStream2) -> syn::Result<MatchTInput> {
+    use proc_macro2::{Delimiter, TokenTree};
+
+    let mut iter = tokens.into_iter().peekable();
+
+    // Check for optional 'try' and 'move' or 'mut' keywords, in this order
+    let is_try = matches!(
+        iter.peek(),
+        Some(TokenTree::Ident(ident)) if *ident == "try"
+    );
+    if is_try {
+        iter.next();
+    }
+    let is_move = matches!(
+        iter.peek(),
+        Some(TokenTree::Ident(ident)) if ident.to_string() == "move"
+    );
+    if is_move {
+        let keyword = iter.next();
+        if !allocates() {
+            return Err(syn::Error::new_spanned(
+                keyword,
+                "`match_t!(move ..)` takes a `Box`, which needs the `alloc` feature of enum-typer",
+            ));
+        }
+    }
+    let is_mut = matches!(
+        iter.peek(),
+        Some(TokenTree::Ident(ident)) if *ident == "mut"
+    );
+    if is_mut {
+        let keyword = iter.next();
+        if is_move {
+            return Err(syn::Error::new_spanned(
+                keyword,
+                "`match_t!` either moves out of a box or matches by mutable reference; \
+                 use `move` or `mut`, not both",
+            ));
+        }
+    }
+
+    // Parse the expression (everything before 'as' or the first brace)
+    let (expr, mut type_hints) = parse_expression_and_type_hints(&mut iter)?;
+    // `sum: Box<dyn Sum<A, B>>` types the value, and hints its enum when no
+    // `as` does
+    let expr = match split_ascription(&expr) {
+        Some((value, ty)) => {
+            if type_hints.is_empty() {
+                type_hints.extend(ascribed_hint(&ty));
+            }
+            quote! { ::core::convert::identity::<#ty>(#value) }
+        }
+        None => expr,
+    };
+
+    // Parse the brace group containing arms
+    let arms_group = match iter.next() {
+        Some(TokenTree::Group(g)) if g.delimiter() == Delimiter::Brace => g,
+        other => {
+            let span = other.map_or_else(proc_macro2::Span::call_site, |t| t.span());
+            return Err(syn::Error::new(
+                span,
+                "Expected braced block with match arms",
+            ));
+        }
+    };
+
+    let arms = parse_match_arms(TokenTree::Group(arms_group).into())?;
+    let otherwise = parse_else_clause(iter.collect())?;
+
+    Ok(MatchTInput {
+        is_move,
+        is_mut,
+        is_try,
+        expr,
+        type_hints,
+        arms,
+        otherwise,
+    })
+}
+
+/// Parse what follows the arms: nothing, or an `else` clause
+fn parse_else_clause(tokens: TokenStream2) -> syn::Result<Option<ElseClause>> {
+    let parser = |input: ParseStream| {
+        if input.is_empty() {
+            return Ok(None);
+        }
+        input.parse::<Token![else]>()?;
+        let binding = if input.peek(Token![|]) {
+            input.parse::<Token![|]>()?;
+            let binding = syn::Pat::parse_single(input)?;
+            input.parse::<Token![|]>()?;
+            Some(binding)
+        } else {
+            None
+        };
+        let body = input.parse::<syn::Block>()?;
+        Ok(Some(ElseClause { binding, body }))
+    };
+
+    syn::parse::Parser::parse2(parser, tokens)
+}
+
+/// Parse expression and optional type hints (e.g., `expr as Type` or
+/// `expr as First | Second`)
+#[allow(clippy::cmp_owned)]
+fn parse_expression_and_type_hints(
+    iter: &mut std::iter::Peekable<impl Iterator<Item = proc_macro2::TokenTree>>,
+) -> syn::Result<(TokenStream2, Vec<TokenStream2>)> {
+    use proc_macro2::{Delimiter, TokenTree};
+
+    let mut expr_tokens = Vec::new();
+    let mut type_hints = Vec::new();
+
+    while let Some(token) = iter.peek() {
+        if matches!(token, TokenTree::Group(g) if g.delimiter() == Delimiter::Brace) {
+            break;
+        }
+
+        // Check for 'as' keyword for type hints
+        if let TokenTree::Ident(ident) = token {
+            if ident.to_string() == "as" {
+                iter.next(); // consume 'as'
+
+                // Parse type hints (everything until the brace), split on
+                // `|` outside angle brackets
+                let mut type_tokens = Vec::new();
+                let mut angle_depth = 0usize;
+                while let Some(t) = iter.peek() {
+                    if matches!(t, TokenTree::Group(g) if g.delimiter() == Delimiter::Brace) {
+                        break;
+                    }
+                    let t = iter.next().unwrap();
+                    if let TokenTree::Punct(punct) = &t {
+                        match punct.as_char() {
+                            '<' => angle_depth += 1,
+                            '>' => angle_depth = angle_depth.saturating_sub(1),
+                            '|' if angle_depth == 0 => {
+                                type_hints.push(type_tokens.drain(..).collect());
+                                continue;
+                            }
+                            _ => {}
+                        }
+                    }
+                    type_tokens.push(t);
+                }
+                type_hints.push(type_tokens.into_iter().collect());
+                break;
+            }
+        }
+
+        expr_tokens.push(iter.next().unwrap());
+    }
+
+    Ok((expr_tokens.into_iter().collect(), type_hints))
+}
+
+/// Split `value: Type`, the scrutinee with its type written out, at the
+/// colon outside any group; `None` unless both sides parse
+fn split_ascription(expr: &TokenStream2) -> Option<(TokenStream2, syn::Type)> {
+    use proc_macro2::{Spacing, TokenTree};
+
+    let tokens: Vec<TokenTree> = expr.clone().into_iter().collect();
+    let mut idx = 0;
+    while idx < tokens.len() {
+        if let TokenTree::Punct(p) = &tokens[idx] {
+            if p.as_char() == ':' {
+                // The `::` of a path
+                if p.spacing() == Spacing::Joint {
+                    idx += 2;
+                    continue;
+                }
+                let value: TokenStream2 = tokens[..idx].iter().cloned().collect();
+                let ty: TokenStream2 = tokens[idx + 1..].iter().cloned().collect();
+                syn::parse2::<Expr>(value.clone()).ok()?;
+                return Some((value, syn::parse2(ty).ok()?));
+            }
+        }
+        idx += 1;
+    }
+    None
+}
+
+/// The trait of the `dyn` value an ascribed type holds, behind references
+/// and a `Box`, `Rc` or `Arc`, as a hint: `Sum<A, B>` for
+/// `Box<dyn Sum<A, B>>`. `dyn Any` hints no enum.
+fn ascribed_hint(ty: &syn::Type) -> Option<TokenStream2> {
+    match ty {
+        syn::Type::Reference(reference) => ascribed_hint(&reference.elem),
+        syn::Type::Paren(paren) => ascribed_hint(&paren.elem),
+        syn::Type::Group(group) => ascribed_hint(&group.elem),
+        syn::Type::Path(path) => {
+            let segment = path.path.segments.last()?;
+            if !["Box", "Rc", "Arc"].iter().any(|ptr| segment.ident == ptr) {
+                return None;
+            }
+            let syn::PathArguments::AngleBracketed(args) = &segment.arguments else {
+                return None;
+            };
+            match args.args.first()? {
+                syn::GenericArgument::Type(inner) => ascribed_hint(inner),
+                _ => None,
+            }
+        }
+        syn::Type::TraitObject(object) => {
+            let path = object.bounds.iter().find_map(|bound| match bound {
+                syn::TypeParamBound::Trait(bound) => Some(&bound.path),
+                _ => None,
+            })?;
+            let last = path.segments.last()?;
+            (last.ident != "Any").then(|| path.to_token_stream())
+        }
+        _ => None,
+    }
+}
+
+/// Whether an arm body ends in a block and so may omit the trailing comma
+fn is_block_like(expr: &Expr) -> bool {
+    matches!(
+        expr,
+        Expr::Block(_)
+            | Expr::Const(_)
+            | Expr::ForLoop(_)
+            | Expr::If(_)
+            | Expr::Loop(_)
+            | Expr::Match(_)
+            | Expr::TryBlock(_)
+            | Expr::Unsafe(_)
+            | Expr::While(_)
+    )
+}
+
+/// Parse the body of an arm following `=>`, the same way rustc does for `match`.
+/// The body is a full expression, so top-level commas inside tuples, calls or
+/// macro invocations stay part of it; the comma separating arms is consumed.
+pub fn parse_arm_body(input: ParseStream) -> syn::Result<Expr> {
+    let body = Expr::parse_with_earlier_boundary_rule(input)?;
+
+    if input.is_empty() {
+        return Ok(body);
+    }
+    if is_block_like(&body) {
+        if input.peek(Token![,]) {
+            input.parse::<Token![,]>()?;
+        }
+    } else {
+        input.parse::<Token![,]>()?;
+    }
+
+    Ok(body)
+}
+
+/// Parse an arm pattern: every token up to the `=>` that is not nested inside
+/// the `<...>` of a generic argument list. An `if` guard is included; angle
+/// brackets are not tracked inside it, where `<` compares.
+pub fn parse_arm_pattern(input: ParseStream) -> syn::Result<TokenStream2> {
+    use proc_macro2::TokenTree;
+
+    let mut pattern_tokens = Vec::new();
+    let mut angle_depth: i32 = 0;
+    let mut in_guard = false;
+
+    while !input.is_empty() {
+        if input.peek(Token![=>]) && angle_depth == 0 {
+            break;
+        }
+
+        let tt = input.parse::<TokenTree>()?;
+
+        // Track angle bracket depth for generic type parameters in patterns
+        match &tt {
+            TokenTree::Ident(ident) if ident == "if" && angle_depth == 0 => in_guard = true,
+            TokenTree::Punct(p) if p.as_char() == '<' && !in_guard => angle_depth += 1,
+            TokenTree::Punct(p) if p.as_char() == '>' && !in_guard => {
+                angle_depth = (angle_depth - 1).max(0)
+            }
+            _ => {}
+        }
+
+        pattern_tokens.push(tt);
+    }
+
+    let pattern: TokenStream2 = pattern_tokens.into_iter().collect();
+    if input.is_empty() && !pattern.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &pattern,
+            "expected `=>` after this pattern",
+        ));
+    }
+
+    Ok(pattern)
+}
+
+/// Split an arm pattern read by [`parse_arm_pattern`] at its top-level `if`
+/// into the pattern and its guard expression
+pub fn split_arm_guard(pattern: TokenStream2) -> syn::Result<(TokenStream2, Option<Expr>)> {
+    use proc_macro2::TokenTree;
+
+    let mut tokens = pattern.into_iter();
+    let mut head = Vec::new();
+    for tt in tokens.by_ref() {
+        if matches!(&tt, TokenTree::Ident(ident) if ident == "if") {
+            let guard_tokens: TokenStream2 = tokens.collect();
+            if guard_tokens.is_empty() {
+                return Err(syn::Error::new_spanned(
+                    tt,
+                    "expected a guard expression after `if`",
+                ));
+            }
+            let guard = syn::parse2::<Expr>(guard_tokens)?;
+            return Ok((head.into_iter().collect(), Some(guard)));
+        }
+        head.push(tt);
+    }
+    Ok((head.into_iter().collect(), None))
+}
+
+/// Parse match arms from the braced group holding them, so that errors at the
+/// end of the arms point at its closing brace rather than the whole macro call
+fn parse_match_arms(group: TokenStream2) -> syn::Result<Vec<MatchArm>> {
+    let parser = |input: ParseStream| {
+        let content;
+        syn::braced!(content in input);
+
+        let mut arms = Vec::new();
+        while !content.is_empty() {
+            let pattern = parse_arm_pattern(&content)?;
+            content.parse::<Token![=>]>()?;
+            let body = parse_arm_body(&content)?;
+            for pattern in split_or_pattern(pattern)? {
+                arms.push(MatchArm {
+                    pattern,
+                    body: body.clone(),
+                });
+            }
+        }
+        Ok(arms)
+    };
+
+    syn::parse::Parser::parse2(parser, group)
+}
+
+/// Split an or-pattern such as `Rectangle(w, h) | Square(w, h)` at its
+/// top-level `|`; each case names its own type to test for, so the arm is
+/// expanded once per case, all sharing the body
+pub fn split_or_pattern(pattern: TokenStream2) -> syn::Result<Vec<TokenStream2>> {
+    use proc_macro2::TokenTree;
+
+    let mut cases = Vec::new();
+    let mut case = Vec::new();
+    let mut angle_depth = 0usize;
+    let mut last_pipe = None;
+    let mut tokens = pattern.into_iter().peekable();
+    // A leading `|` is allowed, as in `match`
+    if matches!(tokens.peek(), Some(TokenTree::Punct(p)) if p.as_char() == '|') {
+        tokens.next();
+    }
+    for tt in tokens {
+        if let TokenTree::Punct(punct) = &tt {
+            match punct.as_char() {
+                '<' => angle_depth += 1,
+                '>' => angle_depth = angle_depth.saturating_sub(1),
+                '|' if angle_depth == 0 => {
+                    if case.is_empty() {
+                        return Err(syn::Error::new_spanned(tt, "expected a pattern before `|`"));
+                    }
+                    cases.push(case.drain(..).collect());
+                    last_pipe = Some(tt);
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        case.push(tt);
+    }
+    if let (true, Some(pipe)) = (case.is_empty(), last_pipe) {
+        return Err(syn::Error::new_spanned(
+            pipe,
+            "expected a pattern after `|`",
+        ));
+    }
+    cases.push(case.into_iter().collect());
+    Ok(cases)
+}
+
+/// Split a tuple pattern such as `(Num(a), Pair<i32, i32>(..))` into its
+/// elements, at commas outside angle brackets. `None` when the pattern isn't
+/// parenthesized.
+pub fn split_tuple_pattern(pattern: &TokenStream2) -> Option<Vec<TokenStream2>> {
+    use proc_macro2::{Delimiter, TokenTree};
+
+    let mut tokens = pattern.clone().into_iter();
+    let group = match (tokens.next(), tokens.next()) {
+        (Some(TokenTree::Group(g)), None) if g.delimiter() == Delimiter::Parenthesis => g,
+        _ => return None,
+    };
+    let mut elems = Vec::new();
+    let mut elem = Vec::new();
+    let mut angle_depth = 0usize;
+    for tt in group.stream() {
+        if let TokenTree::Punct(punct) = &tt {
+            match punct.as_char() {
+                '<' => angle_depth += 1,
+                '>' => angle_depth = angle_depth.saturating_sub(1),
+                ',' if angle_depth == 0 => {
+                    elems.push(elem.drain(..).collect());
+                    continue;
+                }
+                _ => {}
+            }
+        }
+        elem.push(tt);
+    }
+    // A trailing comma ends the last element
+    if !elem.is_empty() {
+        elems.push(elem.into_iter().collect());
+    }
+    Some(elems)
+}
+
+/// Extract the type name (e.g., "Circle<i32>") and the pattern (e.g., "{ radius }") from the pattern
+/// Examples:
+/// - `Circle(x)` -> (Circle, Circle(x))
+/// - `Leaf<i32>(x)` -> (Leaf<i32>, Leaf(x))
+/// - `Leaf::<i32>(x)` -> (Leaf::<i32>, Leaf(x))
+/// - `Rectangle { width, height }` -> (Rectangle, Rectangle { width, height })
+/// - `whole @ Circle(x)` -> (Circle, whole @ Circle(x))
+/// Returns: (type_name_for_downcast, pattern_without_generics)
+#[allow(clippy::doc_lazy_continuation)]
+pub fn extract_type_and_pattern(pattern: &TokenStream2) -> (TokenStream2, TokenStream2) {
+    use proc_macro2::{Delimiter, TokenTree};
+
+    let mut type_name_tokens = Vec::new();
+    let mut angle_bracket_depth = 0;
+
+    // First pass: extract type name with generics (everything before ( or { )
+    for token in pattern.clone() {
+        match &token {
+            // Stop at tuple fields ( or struct fields {
+            TokenTree::Group(g)
+                if g.delimiter() == Delimiter::Parenthesis || g.delimiter() == Delimiter::Brace =>
+            {
+                break;
+            }
+            // Track angle bracket depth
+            TokenTree::Punct(p) if p.as_char() == '<' => {
+                angle_bracket_depth += 1;
+                type_name_tokens.push(token);
+            }
+            TokenTree::Punct(p) if p.as_char() == '>' && angle_bracket_depth > 0 => {
+                type_name_tokens.push(token);
+                angle_bracket_depth -= 1;
+            }
+            // Keep the `::` of a path to the struct
+            TokenTree::Punct(p) if p.as_char() == ':' => type_name_tokens.push(token),
+            // The struct is named after the binding of `whole @ ..`
+            TokenTree::Punct(p) if p.as_char() == '@' && angle_bracket_depth == 0 => {
+                type_name_tokens.clear();
+            }
+            // Stop at other punctuation if not in angle brackets
+            TokenTree::Punct(_) if angle_bracket_depth == 0 => break,
+            _ => {
+                type_name_tokens.push(token);
+            }
+        }
+    }
+
+    // Second pass: build pattern without generics
+    let mut pattern_without_generics = Vec::new();
+    let mut skip_until_angle_close = false;
+    let mut angle_depth = 0;
+
+    for token in pattern.clone() {
+        match &token {
+            TokenTree::Punct(p) if p.as_char() == '<' && !skip_until_angle_close => {
+                skip_until_angle_close = true;
+                angle_depth = 1;
+                // The turbofish of `Leaf::<i32>(x)` goes with its generics
+                let len = pattern_without_generics.len();
+                if len >= 2
+                    && pattern_without_generics[len - 2..]
+                        .iter()
+                        .all(|t| matches!(t, TokenTree::Punct(p) if p.as_char() == ':'))
+                {
+                    pattern_without_generics.truncate(len - 2);
+                }
+            }
+            TokenTree::Punct(p) if p.as_char() == '<' && skip_until_angle_close => {
+                angle_depth += 1;
+            }
+            TokenTree::Punct(p) if p.as_char() == '>' && skip_until_angle_close => {
+                angle_depth -= 1;
+                if angle_depth == 0 {
+                    skip_until_angle_close = false;
+                }
+            }
+            _ if !skip_until_angle_close => {
+                pattern_without_generics.push(token);
+            }
+            _ => {} // Skip tokens inside angle brackets
+        }
+    }
+
+    (
+        type_name_tokens.into_iter().collect(),
+        pattern_without_generics.into_iter().collect(),
+    )
+}
+
+/// Extract generic type parameters from a type hint like `Tree<i32>` or `Box<dyn Tree<i32>>`
+/// Returns the generic parameters as a TokenStream, e.g., `<i32>`
+pub fn extract_generics_from_type_hint(type_hint: &TokenStream2) -> Option<TokenStream2> {
+    use proc_macro2::TokenTree;
+
+    let mut generics_tokens = Vec::new();
+    let mut depth = 0;
+    let mut collecting = false;
+
+    for token in type_hint.clone() {
+        match &token {
+            TokenTree::Punct(p) if p.as_char() == '<' => {
+                depth += 1;
+                collecting = true;
+                generics_tokens.push(token);
+            }
+            TokenTree::Punct(p) if p.as_char() == '>' && depth > 0 => {
+                generics_tokens.push(token);
+                depth -= 1;
+                if depth == 0 {
+                    return Some(generics_tokens.into_iter().collect());
+                }
+            }
+            _ if collecting => {
+                generics_tokens.push(token);
+            }
+            _ => {}
+        }
+    }
+
+    if !generics_tokens.is_empty() {
+        Some(generics_tokens.into_iter().collect())
+    } else {
+        None
+    }
+}
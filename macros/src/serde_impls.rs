@@ -0,0 +1,335 @@
+//! `#[type_enum(serde)]`: `Serialize` for `dyn Enum<..>` and `Deserialize` for
+//! `Box<dyn Enum<..>>`, externally tagged by variant name like a serde enum.
+//! Serializing downcasts to each variant struct; deserializing reads the tag,
+//! builds that struct and casts it to the requested index.
+
+use proc_macro2::{Literal, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use std::collections::HashSet;
+use syn::{GenericParam, Generics, Ident};
+
+use crate::codegen::box_path;
+use crate::enum_parser::ParsedEnum;
+use crate::helpers::collect_ordered_type_params;
+use crate::variant_gen::{resolve_trait_type, variant_struct_generics};
+
+/// Reject enums whose variants can't be named from the trait object: those
+/// borrowing, and variants with generics of their own
+pub fn check_serde(parsed: &ParsedEnum) -> syn::Result<()> {
+    if let Some(lifetime) = parsed.generics.lifetimes().next() {
+        return Err(syn::Error::new_spanned(
+            lifetime,
+            "`#[type_enum(serde)]` needs `'static` variants to downcast; \
+             enums with lifetime parameters can't use it",
+        ));
+    }
+    reject_variant_generics(parsed, |variant| {
+        format!(
+            "`#[type_enum(serde)]` can't tell which `{}` to deserialize; \
+             give it concrete field types instead of generics of its own",
+            variant
+        )
+    })
+}
+
+/// Reject enums typetag can't register: typetag supports neither generic
+/// traits nor generic impls
+pub fn check_typetag(parsed: &ParsedEnum) -> syn::Result<()> {
+    if !parsed.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &parsed.generics,
+            "`#[type_enum(typetag)]` needs an enum without parameters, as typetag \
+             doesn't support generic traits; use `#[type_enum(serde)]` instead",
+        ));
+    }
+    reject_variant_generics(parsed, |variant| {
+        format!(
+            "`#[type_enum(typetag)]` registers `{}` by name, so it can't have \
+             generics of its own",
+            variant
+        )
+    })
+}
+
+fn reject_variant_generics(
+    parsed: &ParsedEnum,
+    message: impl Fn(&Ident) -> String,
+) -> syn::Result<()> {
+    let mut errors: Option<syn::Error> = None;
+    for variant in parsed
+        .variants
+        .iter()
+        .filter(|v| !v.generics.params.is_empty())
+    {
+        let e = syn::Error::new_spanned(&variant.generics, message(&variant.ident));
+        match &mut errors {
+            Some(existing) => existing.combine(e),
+            None => errors = Some(e),
+        }
+    }
+    match errors {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// The serde impls for the trait object, with the helper types
+/// deserialization needs tucked into an anonymous const
+pub fn generate_serde_impls(
+    parsed: &ParsedEnum,
+    generics_with_static: &Generics,
+    lint_attrs: &TokenStream2,
+) -> TokenStream2 {
+    let box_ty = box_path();
+    let enum_name = &parsed.ident;
+    let enum_str = enum_name.to_string();
+    let all_type_params_ordered = collect_ordered_type_params(&parsed.generics);
+    let all_type_params: HashSet<String> = all_type_params_ordered.iter().cloned().collect();
+    let (impl_generics, ty_generics, where_clause) = generics_with_static.split_for_impl();
+    let own_type = quote! { #enum_name #ty_generics }.to_string();
+
+    let names: Vec<_> = parsed.variants.iter().map(|v| &v.name).collect();
+    let name_strs: Vec<_> = names.iter().map(|name| name.to_string()).collect();
+    let indices: Vec<_> = (0..names.len())
+        .map(|i| Literal::u32_suffixed(i as u32))
+        .collect();
+    let index_values: Vec<_> = (0..names.len() as u64).collect();
+
+    // Variant structs, and the bounds generic ones need to be (de)serializable
+    let mut struct_types = Vec::new();
+    let mut ser_generics = generics_with_static.clone();
+    let mut de_generics = generics_with_static.clone();
+    de_generics.params.insert(0, syn::parse_quote!('de));
+    for variant in &parsed.variants {
+        let struct_generics =
+            variant_struct_generics(variant, generics_with_static, &all_type_params);
+        let (_, struct_ty_generics, _) = struct_generics.split_for_impl();
+        let name = &variant.ident;
+        let ty = quote! { #name #struct_ty_generics };
+        if struct_generics.type_params().next().is_some() {
+            ser_generics
+                .make_where_clause()
+                .predicates
+                .push(syn::parse_quote!(#ty: ::serde::Serialize));
+            de_generics
+                .make_where_clause()
+                .predicates
+                .push(syn::parse_quote!(#ty: ::serde::Deserialize<'de>));
+        }
+        struct_types.push(ty);
+    }
+    let (ser_impl_generics, _, ser_where_clause) = ser_generics.split_for_impl();
+    let (de_impl_generics, _, de_where_clause) = de_generics.split_for_impl();
+
+    // Variants at another index than the one asked for fail to cast
+    let type_params: Vec<_> = generics_with_static
+        .type_params()
+        .map(|p| &p.ident)
+        .collect();
+    let builds = parsed
+        .variants
+        .iter()
+        .zip(&struct_types)
+        .map(|(variant, ty)| {
+            let trait_type = resolve_trait_type(variant, enum_name, generics_with_static);
+            let read = if variant.fields.is_empty() {
+                let name = &variant.ident;
+                quote! {
+                    ::serde::de::VariantAccess::unit_variant(variant)?;
+                    let value: #ty = #name {};
+                }
+            } else {
+                quote! {
+                    let value: #ty = ::serde::de::VariantAccess::newtype_variant(variant)?;
+                }
+            };
+            if type_params.is_empty() || trait_type.to_string() == own_type {
+                return quote! {
+                    #read
+                    ::core::result::Result::Ok(#box_ty::new(value))
+                };
+            }
+            let message = format!(
+                "variant `{}` implements `{}`, not the index being deserialized",
+                variant.name,
+                trait_type.to_string().replace(' ', "")
+            );
+            quote! {
+                #read
+                let boxed: #box_ty<dyn #trait_type> = #box_ty::new(value);
+                boxed
+                    .try_cast_index::<#(#type_params),*>()
+                    .map_err(|_| <__A::Error as ::serde::de::Error>::custom(#message))
+            }
+        });
+
+    // Fieldless variants are unit variants, a bare tag like serde's own
+    let writes = parsed
+        .variants
+        .iter()
+        .zip(&struct_types)
+        .zip(indices.iter().zip(&name_strs))
+        .map(|((variant, ty), (index, name))| {
+            if variant.fields.is_empty() {
+                return quote! {
+                    if any.is::<#ty>() {
+                        return serializer.serialize_unit_variant(#enum_str, #index, #name);
+                    }
+                };
+            }
+            quote! {
+                if let ::core::option::Option::Some(value) = any.downcast_ref::<#ty>() {
+                    return serializer.serialize_newtype_variant(#enum_str, #index, #name, value);
+                }
+            }
+        });
+
+    // The visitor carries the enum's parameters to name the boxes it builds
+    let phantom_params: Vec<_> = generics_with_static
+        .params
+        .iter()
+        .filter_map(|param| match param {
+            GenericParam::Type(t) => {
+                let ident = &t.ident;
+                Some(quote! { #ident })
+            }
+            GenericParam::Const(c) => {
+                let ident = &c.ident;
+                Some(quote! { [(); #ident] })
+            }
+            GenericParam::Lifetime(_) => None,
+        })
+        .collect();
+    let visitor = format_ident!("__{}Visitor", enum_name);
+    let tag = format_ident!("__{}Tag", enum_name);
+    let tag_visitor = format_ident!("__{}TagVisitor", enum_name);
+    let not_a_variant = format!("a value that is not a variant of `{}`", enum_str);
+    let expecting = format!("a variant of `{}`", enum_str);
+    let index_expecting = format!("variant index 0 <= i < {}", names.len());
+
+    quote! {
+        #[automatically_derived]
+        #lint_attrs
+        impl #ser_impl_generics ::serde::Serialize for dyn #enum_name #ty_generics
+            #ser_where_clause
+        {
+            fn serialize<__S: ::serde::Serializer>(
+                &self,
+                serializer: __S,
+            ) -> ::core::result::Result<__S::Ok, __S::Error> {
+                let any: &dyn ::core::any::Any = self;
+                #(#writes)*
+                ::core::result::Result::Err(
+                    <__S::Error as ::serde::ser::Error>::custom(#not_a_variant),
+                )
+            }
+        }
+
+        #lint_attrs
+        const _: () = {
+            const VARIANTS: &[&str] = &[#(#name_strs),*];
+
+            enum #tag {
+                #(#names),*
+            }
+
+            struct #tag_visitor;
+
+            impl<'de> ::serde::de::Visitor<'de> for #tag_visitor {
+                type Value = #tag;
+
+                fn expecting(
+                    &self,
+                    f: &mut ::core::fmt::Formatter<'_>,
+                ) -> ::core::fmt::Result {
+                    f.write_str("variant identifier")
+                }
+
+                fn visit_u64<__E: ::serde::de::Error>(
+                    self,
+                    value: u64,
+                ) -> ::core::result::Result<Self::Value, __E> {
+                    match value {
+                        #(#index_values => ::core::result::Result::Ok(#tag::#names),)*
+                        _ => ::core::result::Result::Err(__E::invalid_value(
+                            ::serde::de::Unexpected::Unsigned(value),
+                            &#index_expecting,
+                        )),
+                    }
+                }
+
+                fn visit_str<__E: ::serde::de::Error>(
+                    self,
+                    value: &str,
+                ) -> ::core::result::Result<Self::Value, __E> {
+                    match value {
+                        #(#name_strs => ::core::result::Result::Ok(#tag::#names),)*
+                        _ => ::core::result::Result::Err(__E::unknown_variant(value, VARIANTS)),
+                    }
+                }
+
+                fn visit_bytes<__E: ::serde::de::Error>(
+                    self,
+                    value: &[u8],
+                ) -> ::core::result::Result<Self::Value, __E> {
+                    match ::core::str::from_utf8(value) {
+                        ::core::result::Result::Ok(value) => self.visit_str(value),
+                        ::core::result::Result::Err(_) => ::core::result::Result::Err(
+                            __E::invalid_value(::serde::de::Unexpected::Bytes(value), &self),
+                        ),
+                    }
+                }
+            }
+
+            impl<'de> ::serde::Deserialize<'de> for #tag {
+                fn deserialize<__D: ::serde::Deserializer<'de>>(
+                    deserializer: __D,
+                ) -> ::core::result::Result<Self, __D::Error> {
+                    deserializer.deserialize_identifier(#tag_visitor)
+                }
+            }
+
+            struct #visitor #impl_generics (
+                ::core::marker::PhantomData<fn() -> (#(#phantom_params,)*)>,
+            ) #where_clause;
+
+            impl #de_impl_generics ::serde::de::Visitor<'de> for #visitor #ty_generics
+                #de_where_clause
+            {
+                type Value = #box_ty<dyn #enum_name #ty_generics>;
+
+                fn expecting(
+                    &self,
+                    f: &mut ::core::fmt::Formatter<'_>,
+                ) -> ::core::fmt::Result {
+                    f.write_str(#expecting)
+                }
+
+                fn visit_enum<__A: ::serde::de::EnumAccess<'de>>(
+                    self,
+                    data: __A,
+                ) -> ::core::result::Result<Self::Value, __A::Error> {
+                    let (tag, variant) = ::serde::de::EnumAccess::variant::<#tag>(data)?;
+                    match tag {
+                        #(#tag::#names => { #builds })*
+                    }
+                }
+            }
+
+            #[automatically_derived]
+            impl #de_impl_generics ::serde::Deserialize<'de> for #box_ty<dyn #enum_name #ty_generics>
+                #de_where_clause
+            {
+                fn deserialize<__D: ::serde::Deserializer<'de>>(
+                    deserializer: __D,
+                ) -> ::core::result::Result<Self, __D::Error> {
+                    deserializer.deserialize_enum(
+                        #enum_str,
+                        VARIANTS,
+                        #visitor(::core::marker::PhantomData),
+                    )
+                }
+            }
+        };
+    }
+}
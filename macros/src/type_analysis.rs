@@ -0,0 +1,170 @@
+//! Type parameter analysis utilities
+
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use std::collections::HashSet;
+use syn::{Attribute, Fields, Meta, Type, TypePath};
+
+/// Extract trait type from variant attributes like #[impl_trait(Term<bool>)]
+pub fn extract_trait_type_from_attrs(attrs: &[Attribute]) -> Option<TokenStream2> {
+    for attr in attrs {
+        if let Meta::List(meta_list) = &attr.meta {
+            if meta_list.path.is_ident("impl_trait") {
+                return Some(meta_list.tokens.clone());
+            }
+        }
+    }
+    None
+}
+
+/// Extract all type parameters used in a given type
+pub fn extract_used_type_params(ty: &Type, available_params: &HashSet<String>) -> HashSet<String> {
+    let mut used = HashSet::new();
+    collect_type_params(ty, available_params, &mut used);
+    used
+}
+
+/// Collect type parameter names from the segments and generic arguments of a path
+fn collect_path_type_params(
+    path: &syn::Path,
+    available: &HashSet<String>,
+    used: &mut HashSet<String>,
+) {
+    for segment in &path.segments {
+        let ident = segment.ident.to_string();
+        if available.contains(&ident) {
+            used.insert(ident);
+        }
+
+        match &segment.arguments {
+            syn::PathArguments::AngleBracketed(args) => {
+                for arg in &args.args {
+                    match arg {
+                        syn::GenericArgument::Type(inner_ty) => {
+                            collect_type_params(inner_ty, available, used)
+                        }
+                        syn::GenericArgument::AssocType(assoc) => {
+                            collect_type_params(&assoc.ty, available, used)
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            syn::PathArguments::Parenthesized(args) => {
+                for input in &args.inputs {
+                    collect_type_params(input, available, used);
+                }
+                if let syn::ReturnType::Type(_, output) = &args.output {
+                    collect_type_params(output, available, used);
+                }
+            }
+            syn::PathArguments::None => {}
+        }
+    }
+}
+
+/// Collect type parameter names from the trait bounds of `dyn`/`impl` types
+fn collect_bound_type_params<'a>(
+    bounds: impl IntoIterator<Item = &'a syn::TypeParamBound>,
+    available: &HashSet<String>,
+    used: &mut HashSet<String>,
+) {
+    for bound in bounds {
+        if let syn::TypeParamBound::Trait(trait_bound) = bound {
+            collect_path_type_params(&trait_bound.path, available, used);
+        }
+    }
+}
+
+/// Recursively collect type parameter names from a type
+fn collect_type_params(ty: &Type, available: &HashSet<String>, used: &mut HashSet<String>) {
+    match ty {
+        Type::Path(TypePath { qself, path }) => {
+            if let Some(qself) = qself {
+                collect_type_params(&qself.ty, available, used);
+            }
+            collect_path_type_params(path, available, used);
+        }
+        Type::TraitObject(t) => collect_bound_type_params(&t.bounds, available, used),
+        Type::ImplTrait(t) => collect_bound_type_params(&t.bounds, available, used),
+        Type::BareFn(f) => {
+            for input in &f.inputs {
+                collect_type_params(&input.ty, available, used);
+            }
+            if let syn::ReturnType::Type(_, output) = &f.output {
+                collect_type_params(output, available, used);
+            }
+        }
+        Type::Reference(r) => collect_type_params(&r.elem, available, used),
+        Type::Tuple(t) => t
+            .elems
+            .iter()
+            .for_each(|elem| collect_type_params(elem, available, used)),
+        Type::Array(a) => collect_type_params(&a.elem, available, used),
+        Type::Ptr(p) => collect_type_params(&p.elem, available, used),
+        Type::Slice(s) => collect_type_params(&s.elem, available, used),
+        Type::Paren(p) => collect_type_params(&p.elem, available, used),
+        Type::Group(g) => collect_type_params(&g.elem, available, used),
+        _ => {}
+    }
+}
+
+/// Collect all type parameters from variant fields
+pub fn collect_variant_type_params(
+    fields: &Fields,
+    available_params: &HashSet<String>,
+) -> HashSet<String> {
+    let mut used_params = HashSet::new();
+
+    match fields {
+        Fields::Named(fields_named) => {
+            for field in &fields_named.named {
+                used_params.extend(extract_used_type_params(&field.ty, available_params));
+            }
+        }
+        Fields::Unnamed(fields_unnamed) => {
+            for field in &fields_unnamed.unnamed {
+                used_params.extend(extract_used_type_params(&field.ty, available_params));
+            }
+        }
+        Fields::Unit => {}
+    }
+
+    used_params
+}
+
+/// Collect all type parameter names from generics (variant-level or enum-level)
+pub fn collect_all_type_param_names(generics: &syn::Generics) -> HashSet<String> {
+    generics
+        .type_params()
+        .map(|tp| tp.ident.to_string())
+        .collect()
+}
+
+/// Collect all const parameter names from generics
+pub fn collect_const_param_names(generics: &syn::Generics) -> HashSet<String> {
+    generics
+        .const_params()
+        .map(|cp| cp.ident.to_string())
+        .collect()
+}
+
+/// Collect the names of lifetimes mentioned in tokens, as `'a`
+pub fn collect_lifetime_names(tokens: TokenStream2) -> HashSet<String> {
+    let mut names = HashSet::new();
+    let mut after_quote = false;
+    for token in tokens {
+        match token {
+            TokenTree::Punct(punct) if punct.as_char() == '\'' => {
+                after_quote = true;
+                continue;
+            }
+            TokenTree::Ident(ident) if after_quote => {
+                names.insert(format!("'{}", ident));
+            }
+            TokenTree::Group(group) => names.extend(collect_lifetime_names(group.stream())),
+            _ => {}
+        }
+        after_quote = false;
+    }
+    names
+}
@@ -0,0 +1,683 @@
+//! Expansion-time checks for user-written names, arm patterns and type
+//! parameters
+
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use std::collections::HashSet;
+use syn::{spanned::Spanned, visit::Visit, Fields, Generics, Ident, Lifetime, Member, Pat};
+
+use crate::enum_parser::{ParsedEnum, ParsedMethod, ParsedVariant};
+use crate::erasure::mentions;
+use crate::helpers::{extract_trait_type_args, is_default_variant};
+use crate::pattern_parser::extract_type_and_pattern;
+use crate::variant_gen::resolve_trait_type;
+
+/// Describe the fields a variant expects, e.g. `Num(_)` or `Book { title, author }`
+fn describe_variant_shape(variant: &ParsedVariant) -> String {
+    let name = &variant.ident;
+    match &variant.fields {
+        Fields::Named(fields) => {
+            let names: Vec<_> = fields
+                .named
+                .iter()
+                .filter_map(|f| f.ident.as_ref().map(|i| i.to_string()))
+                .collect();
+            format!("{} {{ {} }}", name, names.join(", "))
+        }
+        Fields::Unnamed(fields) => {
+            let holes = vec!["_"; fields.unnamed.len()];
+            format!("{}({})", name, holes.join(", "))
+        }
+        Fields::Unit => name.to_string(),
+    }
+}
+
+fn shape_error(variant: &ParsedVariant, span: proc_macro2::Span, problem: String) -> syn::Error {
+    syn::Error::new(
+        span,
+        format!(
+            "{}; expected `{}`",
+            problem,
+            describe_variant_shape(variant)
+        ),
+    )
+}
+
+/// Check that a parsed pattern destructures `variant` with the right field shape
+pub fn check_pattern_shape(pat: &Pat, variant: &ParsedVariant) -> syn::Result<()> {
+    let name = &variant.ident;
+
+    match pat {
+        Pat::Ident(syn::PatIdent {
+            subpat: Some((_, subpat)),
+            ..
+        }) => check_pattern_shape(subpat, variant),
+        Pat::TupleStruct(tuple) => {
+            let Fields::Unnamed(fields) = &variant.fields else {
+                return Err(shape_error(
+                    variant,
+                    tuple.span(),
+                    format!("variant `{}` is not a tuple variant", name),
+                ));
+            };
+
+            let has_rest = tuple.elems.iter().any(|p| matches!(p, Pat::Rest(_)));
+            let bound = tuple
+                .elems
+                .iter()
+                .filter(|p| !matches!(p, Pat::Rest(_)))
+                .count();
+            let expected = fields.unnamed.len();
+
+            if (has_rest && bound > expected) || (!has_rest && bound != expected) {
+                return Err(shape_error(
+                    variant,
+                    tuple.span(),
+                    format!(
+                        "variant `{}` has {} field{}, but this pattern has {}",
+                        name,
+                        expected,
+                        if expected == 1 { "" } else { "s" },
+                        bound
+                    ),
+                ));
+            }
+            Ok(())
+        }
+        Pat::Struct(pat_struct) => {
+            let known: Vec<String> = match &variant.fields {
+                Fields::Named(fields) => fields
+                    .named
+                    .iter()
+                    .filter_map(|f| f.ident.as_ref().map(|i| i.to_string()))
+                    .collect(),
+                Fields::Unnamed(fields) => {
+                    (0..fields.unnamed.len()).map(|i| i.to_string()).collect()
+                }
+                Fields::Unit => Vec::new(),
+            };
+
+            let mut seen = Vec::new();
+            for field in &pat_struct.fields {
+                let field_name = match &field.member {
+                    Member::Named(ident) => ident.to_string(),
+                    Member::Unnamed(index) => index.index.to_string(),
+                };
+                if !known.contains(&field_name) {
+                    return Err(shape_error(
+                        variant,
+                        field.member.span(),
+                        format!("variant `{}` has no field named `{}`", name, field_name),
+                    ));
+                }
+                seen.push(field_name);
+            }
+
+            let missing: Vec<_> = known.iter().filter(|f| !seen.contains(f)).collect();
+            if pat_struct.rest.is_none() && !missing.is_empty() {
+                let missing: Vec<_> = missing.iter().map(|f| format!("`{}`", f)).collect();
+                return Err(shape_error(
+                    variant,
+                    pat_struct.span(),
+                    format!(
+                        "pattern does not mention field{} {} of variant `{}`",
+                        if missing.len() == 1 { "" } else { "s" },
+                        missing.join(", "),
+                        name
+                    ),
+                ));
+            }
+            Ok(())
+        }
+        Pat::Ident(_) | Pat::Path(_) => {
+            if matches!(variant.fields, Fields::Unit) {
+                Ok(())
+            } else {
+                Err(shape_error(
+                    variant,
+                    pat.span(),
+                    format!("variant `{}` has fields that must be destructured", name),
+                ))
+            }
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Parse the user-facing part of an arm pattern (generics stripped) into a `syn::Pat`
+pub fn parse_arm_pat(pattern: &TokenStream2) -> syn::Result<Pat> {
+    let (_, pattern_for_match) = extract_type_and_pattern(pattern);
+    syn::parse::Parser::parse2(Pat::parse_single, pattern_for_match)
+}
+
+/// The identifier naming the variant in a pattern (`Num` in `Num(i)`)
+pub fn pat_head_ident(pat: &Pat) -> Option<&syn::Ident> {
+    let path = match pat {
+        Pat::TupleStruct(p) => &p.path,
+        Pat::Struct(p) => &p.path,
+        Pat::Path(p) => &p.path,
+        // `whole @ Num(n)` names its variant after the `@`
+        Pat::Ident(p) => match &p.subpat {
+            Some((_, subpat)) => return pat_head_ident(subpat),
+            None => return Some(&p.ident),
+        },
+        _ => return None,
+    };
+    path.segments.last().map(|s| &s.ident)
+}
+
+/// Prefix the variant an arm pattern names with `module`, so `Circle(r)`
+/// matches `shapes::Circle`. Patterns that don't parse are left as written.
+pub fn qualify_arm_head(pattern: &TokenStream2, module: &TokenStream2) -> TokenStream2 {
+    replace_arm_head(pattern, |head| Some(quote! { #module #head }))
+}
+
+/// Name the struct of a variant renamed by `rename_variants` in an arm
+/// written with its declared name, so `Circle(r)` matches `ShapeCircle`
+pub fn rename_arm_head(pattern: &TokenStream2, variants: &[ParsedVariant]) -> TokenStream2 {
+    replace_arm_head(pattern, |head| {
+        let variant = variants
+            .iter()
+            .find(|v| v.name == *head && v.ident != *head)?;
+        let renamed = Ident::new(&variant.ident.to_string(), head.span());
+        Some(quote! { #renamed })
+    })
+}
+
+/// Replace the identifier an arm pattern names its variant with by what
+/// `replace` gives for it, leaving the pattern as written for `None`
+pub fn replace_arm_head(
+    pattern: &TokenStream2,
+    replace: impl FnOnce(&Ident) -> Option<TokenStream2>,
+) -> TokenStream2 {
+    let Some(head) = parse_arm_pat(pattern)
+        .ok()
+        .and_then(|pat| pat_head_ident(&pat).cloned())
+    else {
+        return pattern.clone();
+    };
+    let Some(replacement) = replace(&head) else {
+        return pattern.clone();
+    };
+    let mut replaced = TokenStream2::new();
+    let mut replacement = Some(replacement);
+    let mut after_colons = false;
+    for token in pattern.clone() {
+        match &token {
+            TokenTree::Ident(ident) if !after_colons && *ident == head && replacement.is_some() => {
+                replaced.extend(replacement.take());
+            }
+            _ => replaced.extend(std::iter::once(token.clone())),
+        }
+        after_colons = matches!(&token, TokenTree::Punct(p) if p.as_char() == ':');
+    }
+    replaced
+}
+
+/// Check every method arm against the shape of the variant it names
+pub fn validate_method_arms(parsed: &ParsedEnum) -> syn::Result<()> {
+    let mut errors: Option<syn::Error> = None;
+
+    let section_methods = parsed.impls.iter().flat_map(|section| &section.methods);
+    for method in parsed.methods.iter().chain(section_methods) {
+        let has_self = method.sig.receiver().is_some();
+        if !has_self {
+            if let Err(e) = check_static_arms(method) {
+                push_error(&mut errors, e);
+            }
+        }
+
+        for arm in &method.arms {
+            // Patterns that don't parse are left for rustc to report
+            let Ok(pat) = parse_arm_pat(&arm.pattern) else {
+                continue;
+            };
+            let Some(head) = pat_head_ident(&pat) else {
+                continue;
+            };
+            let result = match parsed.variants.iter().find(|v| v.ident == *head) {
+                Some(variant) if has_self => check_pattern_shape(&pat, variant),
+                Some(_) => Ok(()),
+                None => Err(unknown_variant_error(head, parsed)),
+            };
+
+            if let Err(e) = result {
+                push_error(&mut errors, e);
+            }
+        }
+    }
+
+    match errors {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// An arm naming no variant of the enum would never run, so it is reported
+/// with the closest declared name as a suggestion
+fn unknown_variant_error(head: &syn::Ident, parsed: &ParsedEnum) -> syn::Error {
+    let written = head.to_string();
+    let closest = parsed
+        .variants
+        .iter()
+        .map(|v| v.name.to_string())
+        .map(|name| (edit_distance(&written, &name), name))
+        .filter(|(distance, name)| *distance <= name.len().max(3) / 3)
+        .min_by_key(|(distance, _)| *distance);
+    let message = match closest {
+        Some((_, name)) => format!(
+            "unknown variant `{}` of `{}`; did you mean `{}`?",
+            written, parsed.ident, name
+        ),
+        None => format!("unknown variant `{}` of `{}`", written, parsed.ident),
+    };
+    syn::Error::new(head.span(), message)
+}
+
+/// Edit distance between two names, counting a swap of adjacent characters
+/// as one edit like an insertion, deletion or substitution
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut d = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+    d[a.len()][b.len()]
+}
+
+/// Arms of a function without `self` have no value to destructure, so they
+/// name a variant or `_` and nothing more
+fn check_static_arms(method: &ParsedMethod) -> syn::Result<()> {
+    let mut errors: Option<syn::Error> = None;
+    for arm in &method.arms {
+        let bare = match parse_arm_pat(&arm.pattern) {
+            Ok(Pat::Wild(_)) => true,
+            Ok(Pat::Ident(binding)) => binding.subpat.is_none() && binding.by_ref.is_none(),
+            Ok(Pat::Path(_)) => true,
+            _ => false,
+        };
+        if !bare || arm.guard.is_some() {
+            let message = format!(
+                "`{}` has no `self` to match on, so its arms name a variant without \
+                 fields or guards, as in `Add => ..`",
+                method.sig.ident
+            );
+            let e = match &arm.guard {
+                Some(guard) if bare => syn::Error::new_spanned(guard, message),
+                _ => syn::Error::new_spanned(&arm.pattern, message),
+            };
+            push_error(&mut errors, e);
+        }
+    }
+    match errors {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+fn push_error(errors: &mut Option<syn::Error>, e: syn::Error) {
+    match errors {
+        Some(existing) => existing.combine(e),
+        None => *errors = Some(e),
+    }
+}
+
+/// Reject variant names that would make the generated items collide: a
+/// variant declared twice, or a variant struct sharing the trait's name
+pub fn validate_names(parsed: &ParsedEnum) -> syn::Result<()> {
+    let mut errors: Option<syn::Error> = None;
+    let enum_name = &parsed.ident;
+
+    for (index, variant) in parsed.variants.iter().enumerate() {
+        let name = &variant.ident;
+
+        if name == enum_name {
+            push_error(
+                &mut errors,
+                syn::Error::new(
+                    name.span(),
+                    format!(
+                        "variant `{}` has the same name as the enum; both become items named `{}`, \
+                         so rename the variant (e.g. `{}Value`) or the enum",
+                        name, name, name
+                    ),
+                ),
+            );
+        }
+
+        if let Some(first) = parsed.variants[..index].iter().find(|v| v.ident == *name) {
+            let mut e = syn::Error::new(
+                name.span(),
+                format!(
+                    "variant `{}` is declared more than once in `{}`; each variant becomes a \
+                     struct, so give this one a different name",
+                    name, enum_name
+                ),
+            );
+            e.combine(syn::Error::new(
+                first.ident.span(),
+                format!("`{}` first declared here", name),
+            ));
+            push_error(&mut errors, e);
+        }
+    }
+
+    match errors {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Check that the index each variant declares with `: Expr<..>` names the
+/// enum, or the path of the trait it stands in for under `#[implements(...)]`;
+/// any other head would make the variant implement a trait that doesn't exist
+pub fn validate_trait_types(parsed: &ParsedEnum) -> syn::Result<()> {
+    let mut errors: Option<syn::Error> = None;
+    let enum_name = &parsed.ident;
+    let segment_names = |path: &syn::Path| -> Vec<String> {
+        path.segments.iter().map(|s| s.ident.to_string()).collect()
+    };
+    let implemented = parsed.options.implements.as_ref().map(segment_names);
+
+    for variant in &parsed.variants {
+        let Some(trait_type) = &variant.trait_type else {
+            continue;
+        };
+        let path = syn::parse2::<syn::Path>(trait_type.clone()).ok();
+        if path.as_ref().is_some_and(|path| {
+            path.segments.last().is_some_and(|s| s.ident == *enum_name)
+                || implemented.as_ref() == Some(&segment_names(path))
+        }) {
+            continue;
+        }
+        let head = path.and_then(|path| path.segments.last().map(|s| s.ident.clone()));
+        let written = head
+            .as_ref()
+            .map_or_else(|| trait_type.to_string(), Ident::to_string);
+        let message = format!(
+            "`{}` is not `{}`; the index of variant `{}` names the enum it belongs to, \
+             as in `: {}{}`",
+            written,
+            enum_name,
+            variant.name,
+            enum_name,
+            if parsed.generics.params.is_empty() {
+                ""
+            } else {
+                "<..>"
+            }
+        );
+        let e = match head {
+            Some(head) => syn::Error::new(head.span(), message),
+            None => syn::Error::new_spanned(trait_type, message),
+        };
+        push_error(&mut errors, e);
+    }
+
+    match errors {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Check `#[default]` markers: at most one variant, written bare, whose own
+/// generics all appear in its trait type so the `Default` impl for the
+/// trait object can name them
+pub fn validate_default_variant(parsed: &ParsedEnum) -> syn::Result<()> {
+    let mut errors: Option<syn::Error> = None;
+    let mut first: Option<&ParsedVariant> = None;
+
+    for variant in parsed.variants.iter().filter(|v| is_default_variant(v)) {
+        let attr = variant
+            .attrs
+            .iter()
+            .find(|a| a.path().is_ident("default"))
+            .expect("filtered on the attribute");
+        if let Err(e) = attr.meta.require_path_only() {
+            push_error(&mut errors, e);
+        }
+
+        if let Some(first) = first {
+            let mut e = syn::Error::new_spanned(
+                attr,
+                format!(
+                    "only one variant can be `#[default]`; `{}` already is",
+                    first.ident
+                ),
+            );
+            e.combine(syn::Error::new(
+                first.ident.span(),
+                format!("`{}` marked `#[default]` here", first.ident),
+            ));
+            push_error(&mut errors, e);
+        } else {
+            first = Some(variant);
+        }
+
+        let own_params: HashSet<String> = variant
+            .generics
+            .type_params()
+            .map(|tp| tp.ident.to_string())
+            .chain(
+                variant
+                    .generics
+                    .const_params()
+                    .map(|cp| cp.ident.to_string()),
+            )
+            .collect();
+        let trait_type = variant.trait_type.clone().unwrap_or_default();
+        if !own_params.is_empty() && !mentions(trait_type, &own_params) {
+            push_error(
+                &mut errors,
+                syn::Error::new_spanned(
+                    &variant.generics,
+                    format!(
+                        "`#[default]` variant `{}` has generics of its own that its trait type \
+                         doesn't name, so `Box<dyn {}<..>>::default()` can't pick them",
+                        variant.ident, parsed.ident
+                    ),
+                ),
+            );
+        }
+    }
+
+    match errors {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+/// Warn about generic methods, `impl Trait` arguments included, that were
+/// kept out of the vtable with `where Self: Sized`: they can be called on
+/// the variant structs but not on `dyn Enum`. Writing the bound out
+/// acknowledges that and silences the warning. Emitted like
+/// [`unused_index_warnings`].
+pub fn unsized_method_warnings(parsed: &ParsedEnum) -> TokenStream2 {
+    let lint_attrs = parsed.options.configured_lint_attrs();
+    parsed
+        .methods
+        .iter()
+        .filter(|method| method.implicitly_sized)
+        .map(|method| {
+            let name = &method.sig.ident;
+            let note = format!(
+                "`{}` is generic, so it is kept out of the vtable and can't be called on \
+                 `dyn {}`, only on the variant structs; add `where Self: Sized` to it to say \
+                 so, or make it non-generic",
+                name, parsed.ident
+            );
+            let constant = format_ident!("generic_method_{}", name);
+            quote_spanned! {name.span()=>
+                #lint_attrs
+                const _: () = {
+                    #[deprecated(note = #note)]
+                    #[allow(non_upper_case_globals)]
+                    const #constant: () = ();
+                    #constant
+                };
+            }
+        })
+        .collect()
+}
+
+/// Warn about enum type parameters that do nothing: no variant's fields or
+/// trait type names them, and every variant fixes them to the same argument.
+/// Usually a typo in an index, which would otherwise only surface as
+/// inference failures where the enum is used.
+///
+/// Proc macros cannot emit warnings on stable, so each warning is the use of
+/// a `#[deprecated]` constant spanned at the parameter; `#[deny(deprecated)]`
+/// turns it into an error and `#[type_enum(allow(deprecated))]` silences it.
+pub fn unused_index_warnings(parsed: &ParsedEnum, generics_with_static: &Generics) -> TokenStream2 {
+    let trait_types: Vec<_> = parsed
+        .variants
+        .iter()
+        .map(|variant| resolve_trait_type(variant, &parsed.ident, generics_with_static))
+        .collect();
+
+    let lint_attrs = parsed.options.configured_lint_attrs();
+    let warnings = parsed
+        .generics
+        .type_params()
+        .enumerate()
+        .filter_map(|(position, param)| {
+            let name: HashSet<String> = [param.ident.to_string()].into();
+            let named = parsed
+                .variants
+                .iter()
+                .any(|variant| mentions(variant.fields.to_token_stream(), &name))
+                || trait_types.iter().any(|tt| mentions(tt.clone(), &name));
+            if named {
+                return None;
+            }
+
+            let fixed_to: HashSet<String> = trait_types
+                .iter()
+                .filter_map(|tt| extract_trait_type_args(tt).get(position).cloned())
+                .map(|arg| arg.to_token_stream().to_string())
+                .collect();
+            let [only] = Vec::from_iter(fixed_to).try_into().ok()?;
+
+            let note = format!(
+                "type parameter `{}` of `{}` is never used: no variant's fields mention it and \
+                 every variant fixes it to `{}`; check the variants' `: {}<..>` for a typo",
+                param.ident, parsed.ident, only, parsed.ident
+            );
+            let constant = format_ident!("unused_type_parameter_{}", param.ident);
+            Some(quote_spanned! {param.ident.span()=>
+                #lint_attrs
+                const _: () = {
+                    #[deprecated(note = #note)]
+                    #[allow(non_upper_case_globals)]
+                    const #constant: () = ();
+                    #constant
+                };
+            })
+        });
+
+    warnings.collect()
+}
+
+/// Finds borrows in a field type: elided ones, and named lifetimes other than
+/// `'static`. The signatures of `fn` pointers and `Fn` traits are skipped, as
+/// their lifetimes are their own.
+struct Borrows {
+    elided: Vec<proc_macro2::Span>,
+    named: Vec<proc_macro2::Span>,
+}
+
+impl<'ast> Visit<'ast> for Borrows {
+    fn visit_type_reference(&mut self, reference: &'ast syn::TypeReference) {
+        if reference.lifetime.is_none() {
+            self.elided.push(reference.and_token.span);
+        }
+        syn::visit::visit_type_reference(self, reference);
+    }
+
+    fn visit_lifetime(&mut self, lifetime: &'ast Lifetime) {
+        if lifetime.ident != "static" {
+            self.named.push(lifetime.span());
+        }
+    }
+
+    fn visit_type_bare_fn(&mut self, _: &'ast syn::TypeBareFn) {}
+
+    fn visit_parenthesized_generic_arguments(
+        &mut self,
+        _: &'ast syn::ParenthesizedGenericArguments,
+    ) {
+    }
+}
+
+/// Check the lifetimes on the enum, its variants and their fields. Without
+/// lifetime parameters on the enum the generated trait extends `Any`, so every
+/// variant struct has to be `'static`. With them, borrows must name one of
+/// the lifetimes, since a struct field cannot elide it.
+pub fn validate_lifetimes(parsed: &ParsedEnum) -> syn::Result<()> {
+    let mut errors: Option<syn::Error> = None;
+    let enum_name = &parsed.ident;
+    let borrowing = parsed.generics.lifetimes().next().is_some();
+    let message = |what: &str| {
+        format!(
+            "{} need a lifetime parameter on the enum, e.g. `enum {}<'a, ..>`; without one the \
+             generated trait extends `Any` and every variant must be `'static`, so hold \
+             children as `Box<dyn {}<..>>` or `&'static dyn {}<..>`",
+            what, enum_name, enum_name, enum_name
+        )
+    };
+
+    let mut borrows = Borrows {
+        elided: Vec::new(),
+        named: Vec::new(),
+    };
+    for variant in &parsed.variants {
+        for field in &variant.fields {
+            borrows.visit_type(&field.ty);
+        }
+        if !borrowing {
+            for param in variant.generics.lifetimes() {
+                borrows.named.push(param.lifetime.span());
+            }
+        }
+    }
+
+    for span in borrows.elided {
+        let e = if borrowing {
+            syn::Error::new(
+                span,
+                "borrows in variant fields must name a lifetime of the enum, as in `&'a T`",
+            )
+        } else {
+            syn::Error::new(span, message("borrows in variant fields"))
+        };
+        push_error(&mut errors, e);
+    }
+    if !borrowing {
+        for span in borrows.named {
+            push_error(
+                &mut errors,
+                syn::Error::new(span, message("lifetimes in variants")),
+            );
+        }
+    }
+
+    match errors {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
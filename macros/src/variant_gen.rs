@@ -0,0 +1,782 @@
+//! Variant struct and implementation code generation
+
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote, quote_spanned, ToTokens};
+use std::collections::HashSet;
+use syn::{
+    Fields, GenericArgument, Generics, Ident, Pat, PathArguments, Signature, Type, Visibility,
+};
+
+use crate::assertions::generate_impl_assertions;
+use crate::async_methods::boxed_future;
+use crate::clone_impls::generate_clone_hook_impl;
+use crate::codegen::{allocates, discriminant_hook_ident, fields_tuple_pattern};
+use crate::constructors::{
+    generate_boxed_method, generate_boxing_constructor, generate_default_box, generate_into_box,
+    generate_positional_getters, generate_try_from_box,
+};
+use crate::debug_impls::generate_debug_hook_impl;
+use crate::display_impls::generate_display_hook_impl;
+use crate::downcasts::{generate_view_impls, ViewedVariant};
+use crate::enum_parser::{MethodArm, ParsedImpl, ParsedMethod, ParsedVariant};
+use crate::eq_impls::generate_eq_hook_impl;
+use crate::erasure::{generate_hook_impls, ErasedVariant};
+use crate::hash_impls::generate_hash_hook_impl;
+use crate::helpers::{
+    add_static_bounds, cfg_attr_attrs, cfg_attrs, deprecation_attrs, derives_trait, doc_attrs,
+    field_members, fill_default_type_args, is_default_variant, merge_generics,
+    strip_pattern_generics, substitute_type_params, variant_derives,
+};
+use crate::iteration::{generate_iter_hook_impls, ListShape};
+use crate::options::EnumOptions;
+use crate::type_analysis::{
+    collect_all_type_param_names, collect_const_param_names, collect_lifetime_names,
+    collect_variant_type_params, extract_trait_type_from_attrs,
+};
+use crate::validation::{parse_arm_pat, pat_head_ident};
+
+/// Enum-level information shared by the code generated for every variant
+pub struct EnumContext<'a> {
+    pub enum_name: &'a Ident,
+    pub vis: &'a Visibility,
+    pub methods: &'a [ParsedMethod],
+    /// `impl Trait { .. }` sections of the enum
+    pub impls: &'a [ParsedImpl],
+    pub generics_with_static: &'a Generics,
+    pub all_type_params: &'a HashSet<String>,
+    pub all_type_params_ordered: &'a [String],
+    pub erased: &'a [ErasedVariant],
+    /// View hooks of borrowing enums, standing in for downcasts through `Any`
+    pub views: &'a [ViewedVariant],
+    pub options: &'a EnumOptions,
+    /// Set for list-shaped enums generating iterators
+    pub list: Option<&'a ListShape>,
+    /// Set when boxed trait objects can be cloned, holding the bound of the
+    /// boxes `clone_box` returns
+    pub clone_bound: Option<&'a TokenStream2>,
+    /// Set when trait objects can be compared with `eq_dyn`
+    pub compares_dyn: bool,
+    /// Set when trait objects can be hashed with `hash_dyn`
+    pub hashes_dyn: bool,
+    /// Set when trait objects are formatted with `display_fmt`, through the
+    /// `impl Display` section
+    pub displays_dyn: bool,
+    /// Set when the trait has a `variant_name` method to implement
+    pub names_variants: bool,
+    /// The variants `type_enum!` declared, numbered in order for `match_t!`
+    /// to dispatch on; unset for traits named with `#[implements(...)]`
+    pub discriminants: Option<&'a [ParsedVariant]>,
+}
+
+/// Extract type parameters used in a trait type (e.g., "Term<bool>" -> {}, "Term<T>" -> {"T"})
+pub fn extract_type_params_from_trait(
+    trait_type: &TokenStream2,
+    all_type_params: &HashSet<String>,
+) -> HashSet<String> {
+    use proc_macro2::TokenTree;
+    let mut used_params = HashSet::new();
+
+    for token in trait_type.clone() {
+        match token {
+            TokenTree::Ident(ident) => {
+                let ident_str = ident.to_string();
+                if all_type_params.contains(&ident_str) {
+                    used_params.insert(ident_str);
+                }
+            }
+            TokenTree::Group(group) => {
+                used_params.extend(extract_type_params_from_trait(
+                    &group.stream(),
+                    all_type_params,
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    used_params
+}
+
+/// Generate struct definition for a variant
+pub fn generate_variant_struct(
+    variant_name: &Ident,
+    variant_generics: &Generics,
+    fields: &Fields,
+    vis: &Visibility,
+) -> TokenStream2 {
+    let where_clause = &variant_generics.where_clause;
+    match fields {
+        Fields::Named(fields) => quote! {
+            #vis struct #variant_name #variant_generics #where_clause #fields
+        },
+        Fields::Unnamed(fields) => quote! {
+            #vis struct #variant_name #variant_generics #fields #where_clause;
+        },
+        Fields::Unit => quote! {
+            #vis struct #variant_name #variant_generics #where_clause;
+        },
+    }
+}
+
+/// Whether a method takes `self: Box<Self>`
+pub fn is_boxed_receiver(sig: &Signature) -> bool {
+    let Some(receiver) = sig.receiver() else {
+        return false;
+    };
+    if receiver.colon_token.is_none() {
+        return false;
+    }
+    let Type::Path(type_path) = &*receiver.ty else {
+        return false;
+    };
+    let Some(last) = type_path.path.segments.last() else {
+        return false;
+    };
+    let PathArguments::AngleBracketed(args) = &last.arguments else {
+        return false;
+    };
+    last.ident == "Box"
+        && matches!(
+            args.args.first(),
+            Some(GenericArgument::Type(Type::Path(p))) if p.path.is_ident("Self")
+        )
+}
+
+/// Whether a method takes `self: Pin<&mut Self>`
+pub fn is_pinned_receiver(sig: &Signature) -> bool {
+    let Some(receiver) = sig.receiver() else {
+        return false;
+    };
+    let Type::Path(type_path) = &*receiver.ty else {
+        return false;
+    };
+    let Some(last) = type_path.path.segments.last() else {
+        return false;
+    };
+    let PathArguments::AngleBracketed(args) = &last.arguments else {
+        return false;
+    };
+    last.ident == "Pin"
+        && matches!(
+            args.args.first(),
+            Some(GenericArgument::Type(Type::Reference(r)))
+                if r.mutability.is_some()
+                    && matches!(&*r.elem, Type::Path(p) if p.path.is_ident("Self"))
+        )
+}
+
+/// Whether an arm is the `_ => ..` default shared by variants without an arm
+/// of their own
+pub fn is_wildcard_arm(arm: &MethodArm) -> bool {
+    matches!(parse_arm_pat(&arm.pattern), Ok(Pat::Wild(_)))
+}
+
+/// Whether an arm's pattern is for `variant`, judged by the identifier the
+/// pattern names its variant with (`Num` in `Num(n)` and `all @ Num(_)`).
+/// Patterns that do not parse fall back to their leading identifier.
+pub fn arm_names_variant(arm: &MethodArm, variant: &Ident) -> bool {
+    match parse_arm_pat(&arm.pattern) {
+        Ok(pat) => pat_head_ident(&pat).is_some_and(|head| head == variant),
+        Err(_) => matches!(
+            arm.pattern.clone().into_iter().next(),
+            Some(proc_macro2::TokenTree::Ident(ident)) if ident == *variant
+        ),
+    }
+}
+
+/// Generate a single method implementation body for a variant
+pub fn generate_method_body(
+    variant: &ParsedVariant,
+    method: &ParsedMethod,
+    trait_type: &TokenStream2,
+    all_type_params_ordered: &[String],
+) -> Option<(TokenStream2, bool)> {
+    let variant_name = &variant.ident;
+
+    // Find all matching arms for this variant. The identifier naming the
+    // variant is compared exactly; matching anywhere in the pattern would also
+    // pick up the arms of `AddAssign` or `ToNum` for `Add` or `Num`. A `_` arm
+    // applies to every variant.
+    let matching_arms: Vec<_> = method
+        .arms
+        .iter()
+        .filter(|arm| is_wildcard_arm(arm) || arm_names_variant(arm, variant_name))
+        .collect();
+
+    if matching_arms.is_empty() {
+        return None;
+    }
+
+    let new_sig = substitute_type_params(&method.sig, trait_type, all_type_params_ordered);
+
+    // Docs and deprecations live on the trait declaration; everything else
+    // applies to each impl
+    let impl_attrs = method
+        .attrs
+        .iter()
+        .filter(|a| !a.path().is_ident("doc") && !a.path().is_ident("deprecated"));
+
+    // Without `self` there is nothing to match on; the first arm for the
+    // variant is its body
+    if method.sig.receiver().is_none() {
+        let body = &matching_arms[0].body;
+        return Some((quote! { #(#impl_attrs)* #new_sig { #body } }, false));
+    }
+
+    let is_boxed_self = is_boxed_receiver(&method.sig);
+    let is_pinned_self = is_pinned_receiver(&method.sig);
+    let members = field_members(&variant.fields);
+
+    // Every arm for this variant is kept, in the order the user wrote them, so
+    // refutable patterns fall through to the next arm like a regular `match`.
+    // Pinned receivers match on a tuple of pinned fields instead of `Self`.
+    let match_arms = matching_arms.iter().map(|arm| {
+        let body = &arm.body;
+        let guard = arm.guard.as_ref().map(|guard| quote! { if #guard });
+        let pattern = match parse_arm_pat(&arm.pattern) {
+            Ok(pat) if is_pinned_self => fields_tuple_pattern(&pat, &members),
+            _ => strip_pattern_generics(&arm.pattern),
+        };
+        // A `_` arm stands in for the arms of other variants, so it may be
+        // unreachable in this one
+        let allow = is_wildcard_arm(arm).then(|| quote! { #[allow(unreachable_patterns)] });
+        quote! { #allow #pattern #guard => #body, }
+    });
+
+    // Refutable arms of a `default fn` fall back to the shared body
+    let method_name = &method.sig.ident;
+    let fallback = match &method.default_body {
+        Some(body) => quote! {
+            #[allow(unreachable_patterns)]
+            _ => #body,
+        },
+        None => quote! {
+            #[allow(unreachable_patterns)]
+            _ => ::core::unreachable!(
+                ::core::concat!(
+                    "no arm of `", ::core::stringify!(#method_name),
+                    "` matched variant `", ::core::stringify!(#variant_name), "`"
+                )
+            ),
+        },
+    };
+
+    // `Self` is the concrete variant struct here, so a boxed receiver is
+    // destructured directly
+    let scrutinee = if is_pinned_self {
+        let bindings: Vec<_> = (0..members.len())
+            .map(|i| format_ident!("__field{}", i))
+            .collect();
+        quote! {
+            {
+                // SAFETY: every field is structurally pinned. The guards
+                // emitted next to the struct rule out `Drop` impls and
+                // `Unpin` impls that don't require every field to be `Unpin`,
+                // and no generated code moves out of a pinned variant.
+                let Self { #(#members: #bindings),* } =
+                    unsafe { ::core::pin::Pin::get_unchecked_mut(self) };
+                unsafe { ( #(::core::pin::Pin::new_unchecked(#bindings),)* ) }
+            }
+        }
+    } else if is_boxed_self {
+        quote! { *self }
+    } else {
+        quote! { self }
+    };
+    // Parameters that only other variants' arms use are touched here, so
+    // they aren't reported as unused in this variant's impl
+    let params = new_sig.inputs.iter().filter_map(|input| match input {
+        syn::FnArg::Typed(pat_type) => match &*pat_type.pat {
+            Pat::Ident(binding) => Some(&binding.ident),
+            _ => None,
+        },
+        syn::FnArg::Receiver(_) => None,
+    });
+    // Only the fallback may be unreachable; the user's own arms keep the lint
+    let mut match_expr = quote! {
+        #(let _ = &#params;)*
+        match #scrutinee {
+            #(#match_arms)*
+            #fallback
+        }
+    };
+    if method.is_async {
+        match_expr = boxed_future(&new_sig, match_expr);
+    }
+
+    let method_impl = quote! {
+        #(#impl_attrs)*
+        #new_sig {
+            #match_expr
+        }
+    };
+
+    Some((method_impl, is_boxed_self))
+}
+
+/// Generate a single trait impl block containing all methods for a variant
+pub fn generate_combined_trait_impl(
+    variant: &ParsedVariant,
+    ctx: &EnumContext,
+    impl_generics: &Generics,
+    variant_ty_generics: &TokenStream2,
+    where_clause: &TokenStream2,
+    trait_type: &TokenStream2,
+    hook_impls: Vec<TokenStream2>,
+) -> TokenStream2 {
+    let variant_name = &variant.ident;
+    // The impl holds the user's method bodies, which are linted as usual
+    let lint_attrs = ctx.options.configured_lint_attrs();
+    let typetag = ctx.options.typetag.then(|| quote! { #[::typetag::serde] });
+    // Errors about the impl as a whole, such as a method without an arm for
+    // this variant, point at the variant rather than the whole macro call
+    let span = variant_name.span();
+
+    // Build impl generics token stream
+    let (impl_generics_tokens, _, _) = impl_generics.split_for_impl();
+
+    let method_impls: Vec<_> = ctx
+        .methods
+        .iter()
+        .filter_map(|method| {
+            generate_method_body(variant, method, trait_type, ctx.all_type_params_ordered)
+                .map(|(method_impl, _)| method_impl)
+        })
+        .chain(hook_impls)
+        .collect();
+
+    if method_impls.is_empty() {
+        quote_spanned! {span=>
+            #[automatically_derived]
+            #lint_attrs
+            #typetag
+            impl #impl_generics_tokens #trait_type
+                for #variant_name #variant_ty_generics #where_clause {}
+        }
+    } else {
+        // Not `#[automatically_derived]`, which would hide the bodies from
+        // lints
+        quote_spanned! {span=>
+            #lint_attrs
+            #typetag
+            impl #impl_generics_tokens #trait_type
+                for #variant_name #variant_ty_generics #where_clause {
+                #(#method_impls)*
+            }
+        }
+    }
+}
+
+/// One impl per `impl Trait { .. }` section of the enum, with the section's
+/// arms for this variant
+fn generate_section_impls(
+    variant: &ParsedVariant,
+    ctx: &EnumContext,
+    struct_generics: &Generics,
+    variant_ty_generics: &TokenStream2,
+    trait_type: &TokenStream2,
+) -> Vec<TokenStream2> {
+    let variant_name = &variant.ident;
+    // As for the enum's trait, the section's arms are the user's own code
+    let lint_attrs = ctx.options.configured_lint_attrs();
+    // A section missing an arm for this variant is reported at the variant
+    let span = variant_name.span();
+    let (impl_generics_tokens, _, where_clause) = struct_generics.split_for_impl();
+
+    ctx.impls
+        .iter()
+        .map(|section| {
+            let attrs = &section.attrs;
+            let trait_path = &section.trait_path;
+            let method_impls = section.methods.iter().filter_map(|method| {
+                generate_method_body(variant, method, trait_type, ctx.all_type_params_ordered)
+                    .map(|(method_impl, _)| method_impl)
+            });
+            quote_spanned! {span=>
+                #lint_attrs
+                #(#attrs)*
+                impl #impl_generics_tokens #trait_path
+                    for #variant_name #variant_ty_generics #where_clause {
+                    #(#method_impls)*
+                }
+            }
+        })
+        .collect()
+}
+
+/// Guards that make pin projection of a variant struct's fields sound, in the
+/// style of `pin-project`: a user `Drop` impl or an unconditional `Unpin` impl
+/// on the struct would conflict with the impls generated here
+fn generate_pin_guards(variant: &ParsedVariant, struct_generics: &Generics) -> TokenStream2 {
+    let variant_name = &variant.ident;
+    let (impl_generics, ty_generics, where_clause) = struct_generics.split_for_impl();
+
+    let mut origin_generics = struct_generics.clone();
+    origin_generics.params.insert(0, syn::parse_quote!('__pin));
+    let (_, origin_ty_generics, origin_where_clause) = origin_generics.split_for_impl();
+    let mut unpin_generics = origin_generics.clone();
+    unpin_generics
+        .make_where_clause()
+        .predicates
+        .push(syn::parse_quote!(__Origin #origin_ty_generics: ::core::marker::Unpin));
+    let (unpin_impl_generics, _, unpin_where_clause) = unpin_generics.split_for_impl();
+    let origin_fields = variant.fields.iter().enumerate().map(|(i, field)| {
+        let ty = &field.ty;
+        let name = format_ident!("__field{}", i);
+        quote! { #name: #ty }
+    });
+
+    quote! {
+        const _: () = {
+            trait __MustNotImplDrop {}
+            #[allow(drop_bounds)]
+            impl<T: ::core::ops::Drop> __MustNotImplDrop for T {}
+            impl #impl_generics __MustNotImplDrop for #variant_name #ty_generics #where_clause {}
+
+            #[allow(dead_code)]
+            struct __Origin #origin_generics #origin_where_clause {
+                __pin: ::core::marker::PhantomData<&'__pin ()>,
+                #(#origin_fields,)*
+            }
+            impl #unpin_impl_generics ::core::marker::Unpin for #variant_name #ty_generics
+                #unpin_where_clause {}
+        };
+    }
+}
+
+/// Resolve the trait type a variant implements, e.g. `Expr<i32>` for `Lit(i32) : Expr<i32>`
+pub fn resolve_trait_type(
+    variant: &ParsedVariant,
+    enum_name: &Ident,
+    generics_with_static: &Generics,
+) -> TokenStream2 {
+    let trait_type = if let Some(ref tt) = variant.trait_type {
+        tt.clone()
+    } else if let Some(tt) = extract_trait_type_from_attrs(&variant.attrs) {
+        tt
+    } else {
+        let ty_generics = generics_with_static.split_for_impl().1;
+        quote! { #enum_name #ty_generics }
+    };
+    fill_default_type_args(&trait_type, generics_with_static)
+}
+
+/// Generics of the struct generated for a variant: its own generics (with
+/// `'static` bounds) followed by the enum generics its fields actually use
+pub fn variant_struct_generics(
+    variant: &ParsedVariant,
+    generics_with_static: &Generics,
+    all_type_params: &HashSet<String>,
+) -> Generics {
+    // Add 'static bounds to variant generics
+    let variant_generics_with_static = add_static_bounds(&variant.generics);
+
+    // Collect all available type params (variant-level + enum-level)
+    let mut combined_type_params = collect_all_type_param_names(&variant_generics_with_static);
+    combined_type_params.extend(all_type_params.iter().cloned());
+
+    // Collect type parameters and lifetimes used in variant fields (for struct definition)
+    let mut struct_type_params =
+        collect_variant_type_params(&variant.fields, &combined_type_params);
+    struct_type_params.extend(collect_lifetime_names(variant.fields.to_token_stream()));
+    // Const params show up in array lengths and generic arguments alike
+    struct_type_params.extend(extract_type_params_from_trait(
+        &variant.fields.to_token_stream(),
+        &collect_const_param_names(generics_with_static),
+    ));
+
+    // Build merged generics for the struct: variant generics + ONLY used enum generics
+    merge_generics(
+        &variant_generics_with_static,
+        generics_with_static,
+        &struct_type_params,
+    )
+}
+
+/// Generate complete code for a single variant (struct + trait impl + methods)
+pub fn generate_variant_code(variant: &ParsedVariant, ctx: &EnumContext) -> TokenStream2 {
+    let variant_name = &variant.ident;
+
+    let variant_generics_with_static = add_static_bounds(&variant.generics);
+    let struct_generics =
+        variant_struct_generics(variant, ctx.generics_with_static, ctx.all_type_params);
+    let struct_type_params = collect_all_type_param_names(&struct_generics);
+
+    let (_struct_impl_generics, variant_ty_generics, _struct_where_clause) =
+        struct_generics.split_for_impl();
+
+    // Generate struct definition using struct-specific generics
+    let docs = doc_attrs(&variant.attrs);
+    let struct_cfg_attrs = cfg_attr_attrs(&variant.attrs);
+    let deprecated = deprecation_attrs(&variant.attrs);
+    let derives = variant_derives(variant, ctx.options);
+    let derive_debug = (ctx.options.debug && !derives_trait(&derives, "Debug"))
+        .then(|| quote! { #[derive(::core::fmt::Debug)] });
+    // The `#[default]` variant derives `Default` unless it already does, or
+    // an `impl Default` section implements it
+    let derive_default = (is_default_variant(variant)
+        && !derives_trait(&derives, "Default")
+        && !ctx.impls.iter().any(|section| {
+            section
+                .trait_path
+                .segments
+                .last()
+                .is_some_and(|s| s.ident == "Default")
+        }))
+    .then(|| quote! { #[derive(::core::default::Default)] });
+    let derive_serde = (ctx.options.serde || ctx.options.typetag).then(|| {
+        let serialize = (!derives_trait(&derives, "Serialize"))
+            .then(|| quote! { #[derive(::serde::Serialize)] });
+        let deserialize = (!derives_trait(&derives, "Deserialize"))
+            .then(|| quote! { #[derive(::serde::Deserialize)] });
+        quote! { #serialize #deserialize }
+    });
+    let struct_def =
+        generate_variant_struct(variant_name, &struct_generics, &variant.fields, ctx.vis);
+    let lint_attrs = ctx.options.lint_attrs();
+
+    // For impl block, we need ALL type params from BOTH the struct AND the trait type
+    // Determine trait type first
+    let trait_type = resolve_trait_type(variant, ctx.enum_name, ctx.generics_with_static);
+
+    // Extract type params used in trait type
+    let trait_type_params = extract_type_params_from_trait(&trait_type, ctx.all_type_params);
+
+    // Combine struct params and trait params for impl
+    let mut impl_type_params = struct_type_params.clone();
+    impl_type_params.extend(trait_type_params);
+    impl_type_params.extend(struct_generics.lifetimes().map(|l| l.lifetime.to_string()));
+    impl_type_params.extend(collect_lifetime_names(trait_type.clone()));
+    impl_type_params.extend(extract_type_params_from_trait(
+        &trait_type,
+        &collect_const_param_names(ctx.generics_with_static),
+    ));
+
+    // Build impl generics: variant generics + ALL enum generics used in struct OR trait type
+    let impl_generics = merge_generics(
+        &variant_generics_with_static,
+        ctx.generics_with_static,
+        &impl_type_params,
+    );
+
+    let mut hook_impls = generate_hook_impls(
+        variant,
+        ctx.erased,
+        ctx.enum_name,
+        ctx.generics_with_static,
+        &trait_type,
+        ctx.all_type_params_ordered,
+    );
+    hook_impls.extend(generate_view_impls(
+        variant,
+        ctx.views,
+        &trait_type,
+        ctx.all_type_params_ordered,
+    ));
+    if let Some(list) = ctx.list {
+        hook_impls.push(generate_iter_hook_impls(
+            list,
+            ctx.enum_name,
+            &trait_type,
+            ctx.all_type_params_ordered,
+        ));
+    }
+
+    if ctx.names_variants {
+        let name = variant.name.to_string();
+        hook_impls.push(quote! {
+            fn variant_name(&self) -> &'static str {
+                #name
+            }
+        });
+    }
+
+    // Variants added by `extend_enum!` keep the trait's `usize::MAX`
+    let discriminant = ctx
+        .discriminants
+        .and_then(|declared| declared.iter().position(|v| v.ident == variant.ident));
+    if let Some(discriminant) = discriminant {
+        let hook = discriminant_hook_ident();
+        hook_impls.push(quote! {
+            fn #hook(&self) -> usize {
+                #discriminant
+            }
+        });
+    }
+
+    // `display_fmt` forwards to the variant's `impl Display` section, which
+    // every variant has under the same generics
+    if ctx.displays_dyn {
+        hook_impls.push(generate_display_hook_impl());
+    }
+
+    // `debug_fmt`, `clone_box` and `eq_dyn` forward to derived impls, which a
+    // generic variant only has when its parameters implement the trait too
+    let mut derived_bounds = Vec::new();
+    if ctx.options.debug {
+        hook_impls.push(generate_debug_hook_impl());
+        derived_bounds.push(quote! { ::core::fmt::Debug });
+    }
+    if let Some(object_bound) = ctx.clone_bound {
+        hook_impls.push(generate_clone_hook_impl(&trait_type, object_bound));
+        derived_bounds.push(quote! { ::core::clone::Clone });
+    }
+    if ctx.compares_dyn {
+        hook_impls.push(generate_eq_hook_impl(&trait_type));
+        derived_bounds.push(quote! { ::core::cmp::PartialEq });
+    }
+    if ctx.hashes_dyn {
+        hook_impls.push(generate_hash_hook_impl(variant_name));
+        derived_bounds.push(quote! { ::core::hash::Hash });
+    }
+    let mut trait_impl_generics = impl_generics.clone();
+    if !derived_bounds.is_empty() && struct_generics.type_params().next().is_some() {
+        trait_impl_generics
+            .make_where_clause()
+            .predicates
+            .push(syn::parse_quote!(
+                #variant_name #variant_ty_generics: #(#derived_bounds)+*
+            ));
+    }
+    let (_, _, trait_impl_where_clause) = trait_impl_generics.split_for_impl();
+
+    // Generate trait implementation
+    let trait_impl = generate_combined_trait_impl(
+        variant,
+        ctx,
+        &impl_generics,
+        &variant_ty_generics.to_token_stream(),
+        &trait_impl_where_clause.to_token_stream(),
+        &trait_type,
+        hook_impls,
+    );
+
+    // Section traits don't name the enum's index, so their impls only take
+    // the parameters the struct does
+    let section_impls = generate_section_impls(
+        variant,
+        ctx,
+        &struct_generics,
+        &variant_ty_generics.to_token_stream(),
+        &trait_type,
+    );
+
+    // Methods of `impl Trait { .. }` sections project pinned receivers too
+    let section_methods = ctx.impls.iter().flat_map(|section| &section.methods);
+    let pin_guards = if ctx
+        .methods
+        .iter()
+        .chain(section_methods)
+        .any(|m| is_pinned_receiver(&m.sig))
+    {
+        generate_pin_guards(variant, &struct_generics)
+    } else {
+        TokenStream2::new()
+    };
+
+    // Boxing items need the `alloc` feature, which the `of` option requires
+    let boxes = allocates();
+    let constructor = if ctx.options.of {
+        generate_boxing_constructor(variant, &struct_generics, ctx.vis, &lint_attrs)
+    } else {
+        TokenStream2::new()
+    };
+    let assertions = generate_impl_assertions(
+        variant,
+        &struct_generics,
+        &ctx.options.assert_impl,
+        &lint_attrs,
+    );
+    let getters = generate_positional_getters(variant, &struct_generics, ctx.vis, &lint_attrs);
+    let into_box = if boxes {
+        generate_into_box(
+            variant,
+            &trait_impl_generics,
+            &variant_ty_generics.to_token_stream(),
+            &trait_type,
+            &lint_attrs,
+        )
+    } else {
+        TokenStream2::new()
+    };
+    // Borrowing variants aren't `Any`, and a trait named with `#[implements]`
+    // may not be either
+    let try_from_box = if !boxes
+        || ctx.options.implements.is_some()
+        || ctx.generics_with_static.lifetimes().next().is_some()
+    {
+        TokenStream2::new()
+    } else {
+        generate_try_from_box(
+            variant,
+            &trait_impl_generics,
+            &variant_ty_generics.to_token_stream(),
+            &trait_type,
+            &lint_attrs,
+        )
+    };
+    let boxed_method = if !boxes || ctx.methods.iter().any(|m| m.sig.ident == "boxed") {
+        TokenStream2::new()
+    } else {
+        generate_boxed_method(
+            variant,
+            &struct_generics,
+            &trait_impl_generics,
+            &trait_type,
+            ctx.vis,
+            &lint_attrs,
+        )
+    };
+    let default_box = if boxes && is_default_variant(variant) {
+        generate_default_box(
+            variant,
+            &trait_impl_generics,
+            &variant_ty_generics.to_token_stream(),
+            &trait_type,
+            &lint_attrs,
+        )
+    } else {
+        TokenStream2::new()
+    };
+
+    let items = quote! {
+        #(#docs)*
+        #(#derives)*
+        #derive_debug
+        #derive_default
+        #derive_serde
+        #(#struct_cfg_attrs)*
+        #lint_attrs
+        #(#deprecated)*
+        #struct_def
+        #trait_impl
+        #(#section_impls)*
+        #pin_guards
+        #constructor
+        #getters
+        #into_box
+        #try_from_box
+        #boxed_method
+        #default_box
+        #assertions
+    };
+
+    with_variant_cfg(variant, items)
+}
+
+/// Put the variant's `#[cfg]` attributes on each item generated for it, so a
+/// variant compiled out takes its struct and impls along
+fn with_variant_cfg(variant: &ParsedVariant, items: TokenStream2) -> TokenStream2 {
+    let cfgs = cfg_attrs(&variant.attrs);
+    if cfgs.is_empty() {
+        return items;
+    }
+    match syn::parse2::<syn::File>(items.clone()) {
+        Ok(file) => file
+            .items
+            .iter()
+            .map(|item| quote! { #(#cfgs)* #item })
+            .collect(),
+        Err(_) => items,
+    }
+}
@@ -0,0 +1,38 @@
+//! A `#![no_std]` crate using enum-typer with only the `alloc` feature. The
+//! tests depend on it while enabling `std`, so it checks that generated code
+//! keeps compiling here whichever features the rest of the build turns on.
+
+#![no_std]
+
+extern crate alloc;
+
+use alloc::boxed::Box;
+use enum_typer::{match_t, type_enum};
+
+type_enum! {
+    #[type_enum(of, constructors)]
+    pub enum Expr<T> {
+        Num(pub i32) : Expr<i32>,
+        Add(pub Box<dyn Expr<i32>>, pub Box<dyn Expr<i32>>) : Expr<i32>,
+        IsZero(pub Box<dyn Expr<i32>>) : Expr<bool>,
+    }
+
+    fn eval(&self) -> T {
+        Num(n) => *n,
+        Add(a, b) => a.eval() + b.eval(),
+        IsZero(e) => e.eval() == 0,
+    }
+}
+
+/// Sum the numbers of an expression, taking it apart by value
+pub fn sum(expr: Box<dyn Expr<i32>>) -> i32 {
+    match_t!(move expr as Expr<i32> {
+        Num(n) => n,
+        Add(a, b) => sum(a) + sum(b),
+    })
+}
+
+/// `1 + 2`, boxed by the generated constructors
+pub fn one_plus_two() -> Box<dyn Expr<i32>> {
+    expr::add(Box::new(Num(1)), Add::of(Num(2), Num(0)).boxed())
+}
@@ -0,0 +1,19 @@
+//! Enums defined by `type_enum!` in a crate of their own, for the tests of
+//! using them from another crate. Neither is `extensible`, so their
+//! companion macros stay private to this crate.
+
+use enum_typer::type_enum;
+
+type_enum! {
+    pub enum Shape {
+        Circle(pub f64),
+        Square(pub f64),
+    }
+}
+
+type_enum! {
+    pub enum Sum<A, B> {
+        Inl(pub A),
+        Inr(pub B),
+    }
+}
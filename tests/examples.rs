@@ -1,4 +1,4 @@
-use enum_typer::{match_t, type_enum};
+use enum_typer::{extend_enum, match_t, type_enum, type_enum_impl};
 
 #[test]
 fn test_data() {
@@ -60,6 +60,7 @@ fn test_inductive() {
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn test_arith() {
     type_enum! {
         enum Arith<T> {
@@ -169,6 +170,7 @@ fn test_field_generics() {
 }
 
 #[test]
+#[allow(clippy::bool_assert_comparison)]
 fn test_field_generics_arith() {
     type_enum! {
         enum Arith<T> {
@@ -194,3 +196,3366 @@ fn test_field_generics_arith() {
 
     assert_eq!(expr2.eval(), true);
 }
+
+#[test]
+fn test_multiple_arms_per_variant() {
+    type_enum! {
+        enum Shape {
+            Circle(f64),
+            Square(f64),
+        }
+
+        fn describe(&self) -> String {
+            Circle(0.0) => "point".to_string(),
+            Circle(r) => format!("circle of radius {}", r),
+            Square(s) => format!("square of side {}", s),
+        }
+    }
+
+    assert_eq!(Circle(0.0).describe(), "point");
+    assert_eq!(Circle(2.0).describe(), "circle of radius 2");
+    assert_eq!(Square(1.5).describe(), "square of side 1.5");
+}
+
+#[test]
+fn test_method_bodies_with_commas() {
+    type_enum! {
+        enum Pair {
+            Both(i32, i32),
+            Single(i32),
+        }
+
+        fn parts(&self) -> (i32, Vec<i32>) {
+            Both(a, b) => (*a + *b, vec![*a, *b]),
+            Single(a) => (
+                *a,
+                vec![*a],
+            ),
+        }
+    }
+
+    assert_eq!(Both(1, 2).parts(), (3, vec![1, 2]));
+    assert_eq!(Single(4).parts(), (4, vec![4]));
+}
+
+#[test]
+fn test_match_t_bodies_with_commas() {
+    type_enum! {
+        enum Token {
+            Word(String),
+            Digits(u32, u32),
+        }
+    }
+
+    let token: Box<dyn Token> = Box::new(Digits(4, 2));
+    let parts = match_t!(&*token {
+        Word(w) => vec![w.len() as u32],
+        Digits(a, b) => vec![*a, *b],
+    });
+    assert_eq!(parts, vec![4, 2]);
+
+    let pair = match_t!(move token {
+        Word(w) => (w.len() as u32, 0),
+        Digits(a, b) => (a, b),
+    });
+    assert_eq!(pair, (4, 2));
+}
+
+#[test]
+// Generic methods warn that they stay off trait objects
+#[allow(deprecated)]
+fn test_index_transforming_methods() {
+    type_enum! {
+        enum Expr<T> {
+            Lit(i32) : Expr<i32>,
+            IsZero(Box<dyn Expr<i32>>) : Expr<bool>,
+            Hole : Expr<T>,
+        }
+
+        fn cast_bool(&self) -> Option<Box<dyn Expr<bool>>> {
+            Lit(i) => Some(Box::new(IsZero(Box::new(Lit(*i))))),
+            IsZero(_) => None,
+            Hole => Some(Box::new(Hole)),
+        }
+
+        fn erase<U>(&self) -> Box<dyn Expr<U>> {
+            Lit(_) => Box::new(Hole),
+            IsZero(_) => Box::new(Hole),
+            Hole => Box::new(Hole),
+        }
+
+        fn is_hole(&self) -> bool {
+            Hole => true,
+            Lit(_) => false,
+            IsZero(inner) => inner.is_hole(),
+        }
+    }
+
+    let lit: Box<dyn Expr<i32>> = Box::new(Lit(3));
+    let cast = lit.cast_bool().expect("literals cast to booleans");
+    assert!(!cast.is_hole());
+    assert!(cast.cast_bool().is_none());
+
+    let erased: Box<dyn Expr<String>> = Lit(3).erase::<String>();
+    assert!(erased.is_hole());
+}
+
+#[test]
+fn test_default_type_parameters() {
+    type_enum! {
+        enum Expr<T: Copy = i32> {
+            Lit(i32) : Expr<i32>,
+            BoolLit(bool) : Expr<bool>,
+            Neg(Box<dyn Expr>) : Expr,
+            Not(Box<dyn Expr<bool>>) : Expr<bool>,
+        }
+
+        fn eval(&self) -> T {
+            Lit(i) => *i,
+            BoolLit(b) => *b,
+            Neg(e) => -e.eval(),
+            Not(e) => !e.eval(),
+        }
+    }
+
+    let expr: Box<dyn Expr> = Box::new(Neg(Box::new(Lit(3))));
+    assert_eq!(expr.eval(), -3);
+
+    let flag: Box<dyn Expr<bool>> = Box::new(Not(Box::new(BoolLit(false))));
+    assert!(flag.eval());
+}
+
+#[test]
+fn test_match_t_move_erased_generics() {
+    type_enum! {
+        enum Nat {
+            Zero,
+            Succ<N: Nat>(N) : Nat,
+        }
+    }
+
+    fn to_u32(n: Box<dyn Nat>) -> u32 {
+        match_t!(move n as Nat {
+            Zero => 0,
+            Succ(pred) => 1 + to_u32(pred),
+        })
+    }
+
+    assert_eq!(to_u32(Box::new(Succ(Succ(Succ(Zero))))), 3);
+    assert_eq!(to_u32(Box::new(Zero)), 0);
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_match_t_ref_erased_generics() {
+    type_enum! {
+        enum Arith<T> {
+            Num(i32) : Arith<i32>,
+            Add<A: Arith<i32>, B: Arith<i32>>(A, B) : Arith<i32>,
+            Neg<A: Arith<i32>> { inner: A } : Arith<i32>,
+        }
+    }
+
+    fn eval(e: &dyn Arith<i32>) -> i32 {
+        match_t!(e as Arith<i32> {
+            Num(n) => *n,
+            Add(a, b) => eval(a) + eval(b),
+            Neg { inner } => -eval(inner),
+        })
+    }
+
+    let expr = Add(
+        Num(2),
+        Neg {
+            inner: Add(Num(3), Num(4)),
+        },
+    );
+    assert_eq!(eval(&expr), -5);
+    assert_eq!(eval(&Num(7)), 7);
+}
+
+#[test]
+fn test_by_value_sized_methods() {
+    type_enum! {
+        enum Pair<T> {
+            Both(T, T),
+            Single(T),
+        }
+
+        fn into_parts(self) -> (T, Option<T>) where Self: Sized {
+            Both(a, b) => (a, Some(b)),
+            Single(a) => (a, None),
+        }
+
+        fn first(&self) -> &T {
+            Both(a, _) => a,
+            Single(a) => a,
+        }
+    }
+
+    let both = Both("a".to_string(), "b".to_string());
+    assert_eq!(both.into_parts(), ("a".to_string(), Some("b".to_string())));
+    assert_eq!(Single(1).into_parts(), (1, None));
+
+    // The trait stays usable as a trait object
+    let boxed: Box<dyn Pair<i32>> = Box::new(Single(3));
+    assert_eq!(*boxed.first(), 3);
+}
+
+#[deny(warnings, clippy::all)]
+mod lint_clean {
+    use enum_typer::type_enum;
+
+    type_enum! {
+        #[type_enum(allow(dead_code))]
+        pub enum Token {
+            /// A run of letters
+            Word(String),
+            Number(i64),
+            eof,
+        }
+
+        fn describe(&self) -> String {
+            Word(w) => format!("word {}", w),
+            Number(n) => format!("number {}", n),
+            eof => String::from("end of input"),
+        }
+    }
+
+    pub fn sample() -> Vec<Box<dyn Token>> {
+        vec![Box::new(Number(3)), Box::new(eof)]
+    }
+}
+
+#[test]
+fn test_lint_clean_expansion() {
+    let described: Vec<_> = lint_clean::sample().iter().map(|t| t.describe()).collect();
+    assert_eq!(described, vec!["number 3", "end of input"]);
+}
+
+#[test]
+fn test_boxed_self_methods() {
+    type_enum! {
+        enum Stack<T> {
+            Empty,
+            Push<R: Stack<T>>(T, R),
+        }
+
+        fn into_vec(self: Box<Self>) -> Vec<T> {
+            Empty => Vec::new(),
+            Push<R>(top, rest) => {
+                let mut items = Box::new(rest).into_vec();
+                items.push(top);
+                items
+            }
+        }
+    }
+
+    let stack: Box<dyn Stack<String>> =
+        Box::new(Push("b".to_string(), Push("a".to_string(), Empty)));
+    assert_eq!(stack.into_vec(), vec!["a".to_string(), "b".to_string()]);
+}
+
+#[test]
+fn test_pinned_receivers() {
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::task::{Context, Poll, Waker};
+
+    type_enum! {
+        enum Step {
+            Ready(u32),
+            Delay { remaining: u32, value: u32 },
+            Then<F: Future<Output = u32>>(F),
+        }
+
+        fn poll_step(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<u32> {
+            Ready(value) => Poll::Ready(*value),
+            Delay { mut remaining, value } => {
+                if *remaining == 0 {
+                    Poll::Ready(*value)
+                } else {
+                    *remaining -= 1;
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+            }
+            Then<F>(future) => future.poll(cx),
+        }
+    }
+
+    fn run(mut step: Pin<Box<dyn Step>>) -> (u32, u32) {
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut polls = 1;
+        loop {
+            match step.as_mut().poll_step(&mut cx) {
+                Poll::Ready(value) => return (value, polls),
+                Poll::Pending => polls += 1,
+            }
+        }
+    }
+
+    assert_eq!(run(Box::pin(Ready(1))), (1, 1));
+    assert_eq!(
+        run(Box::pin(Delay {
+            remaining: 2,
+            value: 7
+        })),
+        (7, 3)
+    );
+    assert_eq!(run(Box::pin(Then(async { 42 }))), (42, 1));
+}
+
+#[test]
+fn test_trait_object_parameters() {
+    type_enum! {
+        enum Term<T> {
+            Number(i32) : Term<i32>,
+            Flag(bool) : Term<bool>,
+        }
+
+        fn value(&self) -> T {
+            Number(n) => *n,
+            Flag(b) => *b,
+        }
+
+        fn combine(&self, other: Box<dyn Term<T>>) -> T {
+            Number(n) => n + other.value(),
+            Flag(b) => *b && other.value(),
+        }
+
+        fn combine_all(&self, others: &[&dyn Term<T>], pick: fn(T, T) -> T) -> T {
+            Number(n) => others.iter().fold(*n, |acc, o| pick(acc, o.value())),
+            Flag(b) => others.iter().fold(*b, |acc, o| pick(acc, o.value())),
+        }
+
+        fn with(&self, other: T, fallback: Option<T>) -> T {
+            Number(n) => n + other + fallback.unwrap_or(0),
+            Flag(b) => *b || other || fallback.unwrap_or(false),
+        }
+    }
+
+    assert_eq!(Number(2).combine(Box::new(Number(3))), 5);
+    assert!(!Flag(true).combine(Box::new(Flag(false))));
+    assert_eq!(
+        Number(1).combine_all(&[&Number(5), &Number(3)], i32::max),
+        5
+    );
+    assert_eq!(Number(1).with(2, Some(3)), 6);
+    assert!(Flag(false).with(false, Some(true)));
+    let term: &dyn Term<i32> = &Number(4);
+    assert_eq!(term.with(1, None), 5);
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_operator_impls() {
+    type_enum! {
+        #[ops(Add = Sum, Mul = Product, Neg = Negate, Sub = minus)]
+        enum Expr<T> {
+            Lit(i32) : Expr<i32>,
+            Sum(Box<dyn Expr<i32>>, Box<dyn Expr<i32>>) : Expr<i32>,
+            Product { lhs: Box<dyn Expr<i32>>, rhs: Box<dyn Expr<i32>> } : Expr<i32>,
+            Negate(Box<dyn Expr<i32>>) : Expr<i32>,
+        }
+
+        fn eval(&self) -> T {
+            Lit(n) => *n,
+            Sum(a, b) => a.eval() + b.eval(),
+            Product { lhs, rhs } => lhs.eval() * rhs.eval(),
+            Negate(e) => -e.eval(),
+        }
+
+        fn minus(&self, other: Box<dyn Expr<T>>) -> i32 {
+            Lit(n) => *n - other.eval(),
+            Sum(a, b) => a.eval() + b.eval() - other.eval(),
+            Product { lhs, rhs } => lhs.eval() * rhs.eval() - other.eval(),
+            Negate(e) => -e.eval() - other.eval(),
+        }
+    }
+
+    fn lit(n: i32) -> Box<dyn Expr<i32>> {
+        Box::new(Lit(n))
+    }
+
+    let expr = -(lit(2) + lit(3) * lit(4));
+    assert_eq!(expr.eval(), -14);
+    assert_eq!(expr - lit(1), -15);
+}
+
+#[test]
+fn test_index_impls() {
+    type_enum! {
+        #[ops(Index = get, IndexMut = get_mut)]
+        enum Container<T> {
+            One(T),
+            Many(Vec<T>),
+        }
+
+        fn get(&self, index: usize) -> &T {
+            One(item) => {
+                assert_eq!(index, 0, "index out of bounds");
+                item
+            }
+            Many(items) => &items[index],
+        }
+
+        fn get_mut(&mut self, index: usize) -> &mut T {
+            One(item) => {
+                assert_eq!(index, 0, "index out of bounds");
+                item
+            }
+            Many(items) => &mut items[index],
+        }
+    }
+
+    let mut many: Box<dyn Container<&str>> = Box::new(Many(vec!["a", "b"]));
+    many[1] = "c";
+    assert_eq!(many[0], "a");
+    assert_eq!(many[1], "c");
+
+    let one: Box<dyn Container<i32>> = Box::new(One(5));
+    assert_eq!(one[0], 5);
+}
+
+#[test]
+fn test_list_iterators() {
+    struct Empty;
+    struct NonEmpty;
+
+    type_enum! {
+        #[type_enum(iter)]
+        enum SafeList<T, E> {
+            Nil : SafeList<T, Empty>,
+            Cons(T, Box<dyn SafeList<T, E>>) : SafeList<T, NonEmpty>,
+        }
+    }
+
+    let list: Box<dyn SafeList<String, NonEmpty>> = Box::new(Cons(
+        "a".to_string(),
+        Box::new(Cons("b".to_string(), Box::new(Nil))),
+    ));
+    let borrowed: Vec<&String> = list.iter().collect();
+    assert_eq!(borrowed, vec!["a", "b"]);
+    let owned: Vec<String> = list.into_iter().collect();
+    assert_eq!(owned, vec!["a".to_string(), "b".to_string()]);
+
+    type_enum! {
+        #[type_enum(iter)]
+        enum Stack<T> {
+            Bottom,
+            Push<R: Stack<T>>(T, R),
+        }
+    }
+
+    let stack = Push(3, Push(2, Push(1, Bottom)));
+    assert_eq!(stack.iter().copied().collect::<Vec<_>>(), vec![3, 2, 1]);
+    let boxed: Box<dyn Stack<i32>> = Box::new(stack);
+    assert_eq!(boxed.into_iter().sum::<i32>(), 6);
+}
+
+#[test]
+fn test_list_iterators_beside_user_items() {
+    // Names a `<Enum>Iter` would have taken
+    #[allow(dead_code)]
+    struct ListIter;
+    #[allow(dead_code)]
+    struct ListIntoIter;
+
+    type_enum! {
+        #[type_enum(iter)]
+        enum List<T> {
+            End,
+            Link(T, Box<dyn List<T>>),
+        }
+    }
+
+    let list: Box<dyn List<u8>> = Box::new(Link(1, Box::new(Link(2, Box::new(End)))));
+    assert_eq!(list.iter().count(), 2);
+    assert_eq!(list.into_iter().max(), Some(2));
+}
+
+#[test]
+fn test_boxing_constructors() {
+    type_enum! {
+        #[type_enum(of)]
+        enum Expr<T> {
+            Lit(i32) : Expr<i32>,
+            Add(Box<dyn Expr<i32>>, Box<dyn Expr<i32>>) : Expr<i32>,
+            Scale { factor: i32, inner: Box<dyn Expr<i32>> } : Expr<i32>,
+            IsZero(Box<dyn Expr<i32>>) : Expr<bool>,
+        }
+
+        fn eval(&self) -> T {
+            Lit(n) => *n,
+            Add(a, b) => a.eval() + b.eval(),
+            Scale { factor, inner } => factor * inner.eval(),
+            IsZero(e) => e.eval() == 0,
+        }
+    }
+
+    let expr = Add::of(Lit(1), Scale::of(3, Add::of(Lit(2), Lit(4))));
+    assert_eq!(expr.eval(), 19);
+    assert!(!IsZero::of(expr).eval());
+    assert!(IsZero::of(Add::of(Lit(2), Lit(-2))).eval());
+}
+
+#[test]
+fn test_into_boxed_trait_object() {
+    type_enum! {
+        enum Shape {
+            Circle(f64) : Shape,
+            Square { side: f64 } : Shape,
+            Scaled<S: Shape>(S, f64) : Shape,
+        }
+
+        fn area(&self) -> f64 {
+            Circle(r) => 3.0 * r * r,
+            Square { side } => side * side,
+            Scaled(s, k) => s.area() * k * k,
+        }
+    }
+
+    fn total(shapes: Vec<Box<dyn Shape>>) -> f64 {
+        shapes.iter().map(|s| s.area()).sum()
+    }
+    fn single(shape: impl Into<Box<dyn Shape>>) -> f64 {
+        shape.into().area()
+    }
+
+    assert_eq!(single(Circle(1.0)), 3.0);
+    assert_eq!(single(Scaled(Square { side: 1.0 }, 2.0)), 4.0);
+    assert_eq!(
+        total(vec![Circle(1.0).into(), Square { side: 2.0 }.into()]),
+        7.0
+    );
+}
+
+mod framework {
+    pub trait Area: std::any::Any {
+        fn area(&self) -> f64;
+        fn describe(&self) -> String {
+            format!("area {}", self.area())
+        }
+    }
+}
+
+#[test]
+fn test_implements_existing_trait() {
+    use enum_typer::match_t;
+    use std::fmt;
+
+    type_enum! {
+        #[implements(framework::Area)]
+        enum Shape {
+            Circle(f64) : Shape,
+            Rect { w: f64, h: f64 } : Shape,
+        }
+
+        fn area(&self) -> f64 {
+            Circle(r) => 3.0 * r * r,
+            Rect { w, h } => w * h,
+        }
+    }
+
+    type_enum! {
+        #[implements(fmt::Display)]
+        enum Token {
+            Word(&'static str) : Token,
+            Number(i64) : Token,
+        }
+
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            Word(w) => write!(f, "{}", w),
+            Number(n) => write!(f, "#{}", n),
+        }
+    }
+
+    let shapes: Vec<Box<dyn framework::Area>> =
+        vec![Circle(1.0).into(), Box::new(Rect { w: 2.0, h: 3.0 })];
+    assert_eq!(shapes.iter().map(|s| s.area()).sum::<f64>(), 9.0);
+    assert_eq!(shapes[1].describe(), "area 6");
+
+    let kind = match_t!(&*shapes[0] as Shape {
+        Circle(_) => "circle",
+        Rect { .. } => "rect",
+    });
+    assert_eq!(kind, "circle");
+
+    let tokens: Vec<Box<dyn fmt::Display>> = vec![Word("sum").into(), Number(3).into()];
+    let rendered: Vec<_> = tokens.iter().map(|t| t.to_string()).collect();
+    assert_eq!(rendered, ["sum", "#3"]);
+}
+
+#[test]
+fn test_assert_impl() {
+    type_enum! {
+        #[assert_impl(Send, Sync)]
+        enum Message<T> {
+            Text(String) : Message<String>,
+            Payload<P: std::fmt::Debug>(P, T),
+            Empty : Message<()>,
+        }
+
+        fn size(&self) -> usize {
+            Text(s) => s.len(),
+            Payload(_, _) => std::mem::size_of::<T>(),
+            Empty => 0,
+        }
+    }
+
+    fn on_thread<M: Send + 'static>(message: M) -> M {
+        std::thread::spawn(move || message).join().unwrap()
+    }
+
+    assert_eq!(on_thread(Text("hi".to_string())).size(), 2);
+    assert_eq!(on_thread(Payload(1u8, 7u64)).size(), 8);
+}
+
+#[test]
+fn test_index_type_id() {
+    use std::any::TypeId;
+
+    type_enum! {
+        #[type_enum(allow(dead_code), index_type_id)]
+        enum Expr<T> {
+            Num(i32) : Expr<i32>,
+            Flag(bool) : Expr<bool>,
+            IsZero(Box<dyn Expr<i32>>) : Expr<bool>,
+        }
+    }
+
+    type_enum! {
+        #[type_enum(allow(dead_code), index_type_id)]
+        enum Pair<A, B> {
+            Left(A) : Pair<A, ()>,
+            Both(A, B),
+        }
+    }
+
+    let ints: Vec<Box<dyn Expr<i32>>> = vec![Box::new(Num(1))];
+    let bools: Vec<Box<dyn Expr<bool>>> =
+        vec![Box::new(Flag(true)), Box::new(IsZero(Box::new(Num(0))))];
+    assert_eq!(ints[0].index_type_id(), TypeId::of::<i32>());
+    assert!(bools
+        .iter()
+        .all(|e| e.index_type_id() == TypeId::of::<bool>()));
+    assert_eq!(Left(1u8).index_type_id(), TypeId::of::<(u8, ())>());
+    assert_eq!(Both("a", 2.0).index_type_id(), TypeId::of::<(&str, f64)>());
+}
+
+#[test]
+fn test_index_type_id_left_to_user_traits() {
+    use std::any::TypeId;
+
+    trait IndexTypeId {
+        fn index_type_id(&self) -> TypeId;
+    }
+
+    type_enum! {
+        #[type_enum(allow(dead_code))]
+        enum Expr<T> {
+            Num(i32) : Expr<i32>,
+            Flag(bool) : Expr<bool>,
+        }
+    }
+
+    impl IndexTypeId for dyn Expr<i32> {
+        fn index_type_id(&self) -> TypeId {
+            TypeId::of::<u8>()
+        }
+    }
+
+    let num: Box<dyn Expr<i32>> = Box::new(Num(1));
+    assert_eq!(num.index_type_id(), TypeId::of::<u8>());
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_deprecated_variants_and_methods() {
+    type_enum! {
+        enum Shape {
+            Circle(f64) : Shape,
+            #[deprecated(note = "use `Rect`")]
+            Square(f64) : Shape,
+            Rect(f64, f64) : Shape,
+        }
+
+        fn area(&self) -> f64 {
+            Circle(r) => 3.0 * r * r,
+            Square(side) => side * side,
+            Rect(w, h) => w * h,
+        }
+
+        #[deprecated(note = "use `area`")]
+        fn size(&self) -> f64 {
+            Circle(r) => 3.0 * r * r,
+            Square(side) => side * side,
+            Rect(w, h) => w * h,
+        }
+    }
+
+    assert_eq!(Square(2.0).area(), 4.0);
+    assert_eq!(Rect(2.0, 3.0).size(), 6.0);
+}
+
+#[test]
+fn test_user_of_constructor() {
+    type_enum! {
+        enum Expr {
+            Lit(i32),
+            Neg(Box<dyn Expr>),
+        }
+
+        fn eval(&self) -> i32 {
+            Lit(n) => *n,
+            Neg(e) => -e.eval(),
+        }
+    }
+
+    impl Neg {
+        fn of(n: i32) -> Self {
+            Neg(Box::new(Lit(n)))
+        }
+    }
+
+    assert_eq!(Neg::of(3).eval(), -3);
+}
+
+#[test]
+fn test_named_positional_fields() {
+    type_enum! {
+        #[type_enum(of)]
+        enum Shape {
+            Rectangle(width: f64, height: f64) : Shape,
+            Labeled(label: String, Box<dyn Shape>) : Shape,
+        }
+
+        fn area(&self) -> f64 {
+            Rectangle(w, h) => w * h,
+            Labeled(_, inner) => inner.area(),
+        }
+    }
+
+    let rect = Rectangle(2.0, 3.0);
+    assert_eq!((*rect.width(), *rect.height()), (2.0, 3.0));
+    assert_eq!(rect.0, 2.0);
+
+    let labeled = Labeled::of("door".to_string(), rect);
+    assert_eq!(labeled.label(), "door");
+    assert_eq!(labeled.area(), 6.0);
+}
+
+#[test]
+fn test_try_cast_index() {
+    type_enum! {
+        enum Expr<T> {
+            Num(i32) : Expr<i32>,
+            Flag(bool) : Expr<bool>,
+            IsZero(Box<dyn Expr<i32>>) : Expr<bool>,
+        }
+
+        fn eval(&self) -> T {
+            Num(n) => *n,
+            Flag(b) => *b,
+            IsZero(e) => e.eval() == 0,
+        }
+    }
+
+    fn narrow<T: 'static>(expr: Box<dyn Expr<T>>) -> Option<i32> {
+        expr.try_cast_index::<i32>().ok().map(|e| e.eval())
+    }
+
+    assert_eq!(narrow(Box::new(Num(4))), Some(4));
+    assert_eq!(narrow(Box::new(Flag(true))), None);
+
+    let zero: Box<dyn Expr<bool>> = Box::new(IsZero(Box::new(Num(0))));
+    match zero.try_cast_index::<i32>() {
+        Ok(_) => panic!("a `bool` expression narrowed to `i32`"),
+        Err(back) => assert!(back.eval()),
+    }
+}
+
+#[test]
+fn test_match_t_across_enums() {
+    use std::any::Any;
+
+    type_enum! {
+        enum Shape<T> {
+            Circle(T) : Shape<T>,
+            Square { side: f64 } : Shape<f64>,
+        }
+    }
+
+    type_enum! {
+        enum Token {
+            Word(String) : Token,
+            Number(i64) : Token,
+        }
+    }
+
+    fn describe(value: &dyn Any) -> String {
+        match_t!(value as Shape<f64> | Token {
+            Circle(r) => format!("circle {}", r),
+            Square { side } => format!("square {}", side),
+            Word(w) => format!("word {}", w),
+            Number(n) => format!("number {}", n),
+        })
+    }
+
+    let store: Vec<Box<dyn Any>> = vec![
+        Box::new(Circle(1.5)),
+        Box::new(Number(7)),
+        Box::new(Square { side: 2.0 }),
+        Box::new(Word("hi".to_string())),
+    ];
+    let described: Vec<_> = store.iter().map(|v| describe(v.as_ref())).collect();
+    assert_eq!(described, ["circle 1.5", "number 7", "square 2", "word hi"]);
+
+    let owned: Box<dyn Any> = Box::new(Word("moved".to_string()));
+    let word = match_t!(move owned as Shape<f64> | Token {
+        Word(w) => w,
+        Number(n) => n.to_string(),
+        Circle(r) => r.to_string(),
+        Square { side } => side.to_string(),
+    });
+    assert_eq!(word, "moved");
+}
+
+#[test]
+fn test_static_borrowed_children() {
+    type_enum! {
+        enum Expr<T> {
+            Num(i32) : Expr<i32>,
+            Add(&'static dyn Expr<i32>, &'static dyn Expr<i32>) : Expr<i32>,
+            IsZero(&'static dyn Expr<i32>) : Expr<bool>,
+        }
+
+        fn eval(&self) -> T {
+            Num(n) => *n,
+            Add(a, b) => a.eval() + b.eval(),
+            IsZero(e) => e.eval() == 0,
+        }
+    }
+
+    static TWO: Num = Num(2);
+    let four: &'static Add = Box::leak(Box::new(Add(&TWO, &TWO)));
+    let sum: &dyn Expr<i32> = &Add(four, &TWO);
+    assert_eq!(sum.eval(), 6);
+
+    let product = match_t!(sum as Expr<i32> {
+        Num(n) => *n,
+        Add(a, b) => a.eval() * b.eval(),
+    });
+    assert_eq!(product, 8);
+    assert!(!IsZero(four).eval());
+}
+
+#[test]
+fn test_forwarded_derives() {
+    use std::sync::atomic::AtomicU32;
+
+    type_enum! {
+        #[derive(Debug, Clone, PartialEq)]
+        #[type_enum(allow(dead_code))]
+        enum Item {
+            Point(i32, i32) : Item,
+            Label { text: String } : Item,
+            #[derive(Debug)]
+            Counter(AtomicU32) : Item,
+        }
+    }
+
+    let point = Point(1, 2);
+    assert_eq!(point.clone(), point);
+    assert_eq!(format!("{:?}", point), "Point(1, 2)");
+    let label = Label {
+        text: "a".to_string(),
+    };
+    assert_eq!(format!("{:?}", label.clone()), "Label { text: \"a\" }");
+    assert_eq!(format!("{:?}", Counter(AtomicU32::new(3))), "Counter(3)");
+}
+
+#[test]
+fn test_lifetime_parameters() {
+    type_enum! {
+        enum Ref<'a, T> {
+            Borrowed(&'a T),
+            Owned(T),
+            Pair(&'a dyn Ref<'a, T>, &'a dyn Ref<'a, T>),
+            Len(&'a str) : Ref<'a, usize>,
+        }
+
+        fn get(&self) -> T where T: Clone {
+            Borrowed(r) => (*r).clone(),
+            Owned(t) => t.clone(),
+            Pair(a, _) => a.get(),
+            Len(s) => s.len(),
+        }
+
+        fn count(&self) -> usize {
+            Borrowed(_) => 1,
+            Owned(_) => 1,
+            Pair(a, b) => a.count() + b.count(),
+            Len(_) => 1,
+        }
+    }
+
+    let text = String::from("hello");
+    let five = 5;
+    let borrowed = Borrowed(&five);
+    let owned = Owned(7);
+    let pair = Pair(&borrowed, &owned);
+    assert_eq!((pair.get(), pair.count()), (5, 2));
+
+    let len: Box<dyn Ref<'_, usize>> = Len(&text).into();
+    assert_eq!(len.get(), 5);
+}
+
+#[test]
+fn test_const_generic_parameters() {
+    use std::any::TypeId;
+
+    type_enum! {
+        #[type_enum(index_type_id)]
+        enum Arr<T, const N: usize> {
+            Fixed([T; N]),
+            Empty : Arr<T, 0>,
+            Items(Vec<T>),
+            Chunk<const M: usize>([T; M]) : Arr<T, N>,
+        }
+
+        fn total(&self) -> usize {
+            Fixed(xs) => xs.len(),
+            Empty => 0,
+            Items(v) => v.len(),
+            Chunk(xs) => xs.len(),
+        }
+    }
+
+    fn describe<const N: usize>(arr: &dyn Arr<i32, N>) -> String {
+        match_t!(arr as Arr<i32, N> {
+            Fixed(xs) => format!("fixed {:?}", xs),
+            Empty => "empty".to_string(),
+            Items(v) => format!("items {:?}", v),
+            Chunk<2, i32>(xs) => format!("chunk {:?}", xs),
+        })
+    }
+
+    assert_eq!(describe(&Fixed([1, 2, 3])), "fixed [1, 2, 3]");
+    assert_eq!(describe::<0>(&Empty), "empty");
+    assert_eq!(describe::<4>(&Items(vec![5])), "items [5]");
+    assert_eq!(describe::<4>(&Chunk([6, 7])), "chunk [6, 7]");
+    let chunk: &dyn Arr<u8, 5> = &Chunk([1, 2, 3]);
+    assert_eq!(chunk.total(), 3);
+
+    let fixed: Box<dyn Arr<i32, 2>> = Box::new(Fixed([1, 2]));
+    assert_eq!(fixed.index_type_id(), TypeId::of::<(i32, [(); 2])>());
+    assert!(fixed.try_cast_index::<i32>().is_ok());
+}
+
+#[test]
+fn test_enum_where_clause() {
+    use std::fmt::Display;
+
+    type_enum! {
+        enum Expr<T> where T: Display + Clone {
+            Lit(T),
+            Len(String) : Expr<usize>,
+            Pair(Box<dyn Expr<T>>, Box<dyn Expr<T>>),
+        }
+
+        fn render(&self) -> String {
+            Lit(t) => t.to_string(),
+            Len(s) => format!("len({})", s),
+            Pair(a, b) => format!("({}, {})", a.render(), b.render()),
+        }
+    }
+
+    let pair: Box<dyn Expr<usize>> =
+        Box::new(Pair(Box::new(Lit(1)), Box::new(Len("ab".to_string()))));
+    assert_eq!(pair.render(), "(1, len(ab))");
+
+    let first = match_t!(move pair as Expr<usize> {
+        Pair(a, _) => a.render(),
+        Lit(_) => String::new(),
+        Len(_) => String::new(),
+    });
+    assert_eq!(first, "1");
+}
+
+#[test]
+fn test_variant_where_clauses() {
+    use std::fmt::Display;
+
+    type_enum! {
+        enum Doc {
+            Wrap<U>(U) where U: Display : Doc,
+            Pair<A, B> { a: A, b: B } where A: Display, B: Display,
+            Empty,
+        }
+
+        fn render(&self) -> String {
+            Wrap(u) => format!("[{}]", u),
+            Pair { a, b } => format!("{} {}", a, b),
+            Empty => String::new(),
+        }
+    }
+
+    let docs: Vec<Box<dyn Doc>> = vec![
+        Box::new(Wrap(1)),
+        Box::new(Pair { a: "x", b: 2.5 }),
+        Box::new(Empty),
+    ];
+    let rendered: Vec<_> = docs.iter().map(|d| d.render()).collect();
+    assert_eq!(rendered, ["[1]", "x 2.5", ""]);
+
+    let by_ref: Vec<_> = docs
+        .iter()
+        .map(|d| {
+            match_t!(d.as_ref() as Doc {
+                Wrap(u) => u.to_string(),
+                Pair { a, .. } => a.to_string(),
+                Empty => "-".to_string(),
+            })
+        })
+        .collect();
+    assert_eq!(by_ref, ["1", "x", "-"]);
+}
+
+#[test]
+fn test_supertraits() {
+    type_enum! {
+        #[derive(Debug)]
+        enum Shape: std::fmt::Debug + Send {
+            Circle(f64),
+            Square { side: f64 },
+        }
+
+        fn area(&self) -> f64 {
+            Circle(r) => 3.0 * r * r,
+            Square { side } => side * side,
+        }
+    }
+
+    fn assert_send<T: Send>(value: T) -> T {
+        value
+    }
+
+    let shapes: Vec<Box<dyn Shape>> = vec![Box::new(Circle(1.0)), Box::new(Square { side: 2.0 })];
+    let shapes = assert_send(shapes);
+    assert_eq!(
+        format!("{:?}", shapes),
+        "[Circle(1.0), Square { side: 2.0 }]"
+    );
+    assert_eq!(shapes.iter().map(|s| s.area()).sum::<f64>(), 7.0);
+}
+
+#[test]
+fn test_wildcard_method_arms() {
+    type_enum! {
+        enum Expr<T> {
+            Num(i32) : Expr<i32>,
+            Neg(Box<dyn Expr<i32>>) : Expr<i32>,
+            Flag(bool) : Expr<bool>,
+        }
+
+        fn eval(&self) -> T {
+            Num(n) => *n,
+            Neg(e) => -e.eval(),
+            Flag(b) => *b,
+        }
+
+        fn is_leaf(&self) -> bool {
+            Neg(_) => false,
+            _ => true,
+        }
+
+        fn describe(&self) -> &'static str {
+            Num(0) => "zero",
+            _ => "other",
+        }
+    }
+
+    let neg: Box<dyn Expr<i32>> = Box::new(Neg(Box::new(Num(2))));
+    assert_eq!(neg.eval(), -2);
+    assert!(!neg.is_leaf());
+    assert!(Num(1).is_leaf() && Flag(true).is_leaf());
+    assert_eq!((Num(0).describe(), Num(1).describe()), ("zero", "other"));
+}
+
+#[test]
+fn test_method_arm_guards() {
+    type_enum! {
+        enum Shape {
+            Num(i32),
+            Point { x: i32, y: i32 },
+        }
+
+        fn describe(&self) -> &'static str {
+            Num(i) if *i > 0 => "positive",
+            Num(i) if *i < 0 => "negative",
+            Num(_) => "zero",
+            Point { x, y } if x == y => "diagonal",
+            _ => "point",
+        }
+    }
+
+    let described: Vec<_> = [
+        Box::new(Num(3)) as Box<dyn Shape>,
+        Box::new(Num(-3)),
+        Box::new(Num(0)),
+        Box::new(Point { x: 1, y: 1 }),
+        Box::new(Point { x: 1, y: 2 }),
+    ]
+    .iter()
+    .map(|shape| shape.describe())
+    .collect();
+    assert_eq!(
+        described,
+        ["positive", "negative", "zero", "diagonal", "point"]
+    );
+}
+
+#[test]
+fn test_arms_match_variant_names_exactly() {
+    type_enum! {
+        enum Op {
+            Add(i32, i32),
+            AddAssign(i32),
+            Assign(i32),
+        }
+
+        fn name(&self) -> String {
+            AddAssign(n) => format!("add-assign {}", n),
+            Add(a, b) => format!("add {}", a + b),
+            whole @ Assign(_) => format!("assign {}", whole.0),
+        }
+    }
+
+    assert_eq!(Add(1, 2).name(), "add 3");
+    assert_eq!(AddAssign(1).name(), "add-assign 1");
+    assert_eq!(Assign(4).name(), "assign 4");
+}
+
+#[test]
+fn test_mut_self_methods() {
+    type_enum! {
+        enum Counter {
+            Num(i32),
+            Pair { a: i32, b: i32 },
+            Nested(Box<dyn Counter>),
+        }
+
+        fn bump(&mut self) {
+            Num(n) => *n += 1,
+            Pair { a, b } => {
+                *a += 1;
+                *b += 1;
+            }
+            Nested(inner) => inner.bump(),
+        }
+
+        fn total(&self) -> i32 {
+            Num(n) => *n,
+            Pair { a, b } => a + b,
+            Nested(inner) => inner.total(),
+        }
+    }
+
+    let mut counters: Vec<Box<dyn Counter>> = vec![
+        Box::new(Num(1)),
+        Box::new(Pair { a: 1, b: 2 }),
+        Box::new(Nested(Box::new(Num(5)))),
+    ];
+    counters.iter_mut().for_each(|c| c.bump());
+    let totals: Vec<_> = counters.iter().map(|c| c.total()).collect();
+    assert_eq!(totals, [2, 5, 6]);
+}
+
+#[test]
+// Generic methods warn that they stay off trait objects
+#[allow(deprecated)]
+fn test_generic_methods() {
+    type_enum! {
+        enum Expr<T> {
+            Num(i32) : Expr<i32>,
+            Flag(bool) : Expr<bool>,
+        }
+
+        fn value(&self) -> T {
+            Num(n) => *n,
+            Flag(b) => *b,
+        }
+
+        fn map<U: 'static>(&self, f: impl Fn(T) -> U) -> U {
+            Num(n) => f(*n),
+            Flag(b) => f(*b),
+        }
+
+        fn apply(&self, f: impl Fn(T) -> T) -> T {
+            Num(n) => f(*n),
+            Flag(b) => f(*b),
+        }
+    }
+
+    assert_eq!(Num(2).map(|n| n.to_string()), "2");
+    assert_eq!(Flag(true).map(|b| if b { 1 } else { 0 }), 1);
+    assert_eq!(Num(2).apply(|n| n * 10), 20);
+
+    // Still usable as a trait object for the methods in the vtable
+    let expr: Box<dyn Expr<i32>> = Box::new(Num(3));
+    assert_eq!(expr.value(), 3);
+}
+
+#[test]
+fn test_async_methods() {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    type_enum! {
+        enum Expr<T> {
+            Num(i32) : Expr<i32>,
+            Add(Box<dyn Expr<i32>>, Box<dyn Expr<i32>>) : Expr<i32>,
+            Flag(bool) : Expr<bool>,
+        }
+
+        async fn eval(&self) -> T {
+            Num(n) => *n,
+            Add(a, b) => a.eval().await + b.eval().await,
+            Flag(b) => *b,
+        }
+
+        async fn scaled(&self, by: &i32) -> T {
+            Num(n) => n * by,
+            Add(a, b) => a.scaled(by).await + b.scaled(by).await,
+            Flag(b) => *b,
+        }
+
+        async fn bump(&mut self) {
+            Num(n) => *n += 1,
+            Add(a, b) => {
+                a.bump().await;
+                b.bump().await;
+            }
+            Flag(_) => {}
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(value) = future.as_mut().poll(&mut cx) {
+                return value;
+            }
+        }
+    }
+
+    let mut expr: Box<dyn Expr<i32>> = Box::new(Add(Box::new(Num(1)), Box::new(Num(2))));
+    assert_eq!(block_on(expr.eval()), 3);
+    let by = 10;
+    assert_eq!(block_on(expr.scaled(&by)), 30);
+    block_on(expr.bump());
+    assert_eq!(block_on(expr.eval()), 5);
+    assert!(block_on(Flag(true).eval()));
+}
+
+#[test]
+fn test_send_async_methods() {
+    use std::future::Future;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Waker};
+
+    type_enum! {
+        #[type_enum(send_sync)]
+        enum Job {
+            Wait(u32),
+            Both(Box<dyn Job>, Box<dyn Job>),
+        }
+
+        async fn run(&self, scale: &u32) -> u32 {
+            Wait(n) => n * scale,
+            Both(a, b) => a.run(scale).await + b.run(scale).await,
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(value) = future.as_mut().poll(&mut cx) {
+                return value;
+            }
+        }
+    }
+
+    let job: Box<dyn Job> = Box::new(Both(Box::new(Wait(1)), Box::new(Wait(2))));
+    let scale = 3;
+    let future = job.run(&scale);
+    let total = std::thread::scope(|s| s.spawn(move || block_on(future)).join().unwrap());
+    assert_eq!(total, 9);
+}
+
+#[test]
+fn test_serde_roundtrip() {
+    type_enum! {
+        #[type_enum(serde)]
+        enum Expr<T> {
+            Lit(T) : Expr<T>,
+            Num(i32) : Expr<i32>,
+            Add(Box<dyn Expr<i32>>, Box<dyn Expr<i32>>) : Expr<i32>,
+            Not(Box<dyn Expr<bool>>) : Expr<bool>,
+            Cmp { lhs: Box<dyn Expr<i32>>, rhs: Box<dyn Expr<i32>> } : Expr<bool>,
+        }
+
+        fn eval(&self) -> T where T: Clone {
+            Lit(t) => t.clone(),
+            Num(n) => *n,
+            Add(a, b) => a.eval() + b.eval(),
+            Not(e) => !e.eval(),
+            Cmp { lhs, rhs } => lhs.eval() < rhs.eval(),
+        }
+    }
+
+    let expr: Box<dyn Expr<bool>> = Box::new(Not(Box::new(Cmp {
+        lhs: Box::new(Add(Box::new(Num(1)), Box::new(Lit(2)))),
+        rhs: Box::new(Num(3)),
+    })));
+    let json = serde_json::to_string(&*expr).unwrap();
+    assert_eq!(
+        json,
+        r#"{"Not":{"Cmp":{"lhs":{"Add":[{"Num":1},{"Lit":2}]},"rhs":{"Num":3}}}}"#
+    );
+    let back: Box<dyn Expr<bool>> = serde_json::from_str(&json).unwrap();
+    assert!(back.eval());
+    assert_eq!(serde_json::to_string(&*back).unwrap(), json);
+
+    // A variant at another index is an error, not a mistyped term
+    assert!(serde_json::from_str::<Box<dyn Expr<bool>>>(r#"{"Num":1}"#).is_err());
+    assert!(serde_json::from_str::<Box<dyn Expr<i32>>>(r#"{"Sub":1}"#).is_err());
+}
+
+#[test]
+fn test_serde_unit_variants() {
+    type_enum! {
+        #[type_enum(serde)]
+        enum List {
+            Nil,
+            Cons(i32, Box<dyn List>),
+        }
+
+        fn sum(&self) -> i32 {
+            Nil => 0,
+            Cons(head, tail) => head + tail.sum(),
+        }
+    }
+
+    let list: Box<dyn List> = Box::new(Cons(1, Box::new(Cons(2, Box::new(Nil)))));
+    let json = serde_json::to_string(&*list).unwrap();
+    assert_eq!(json, r#"{"Cons":[1,{"Cons":[2,"Nil"]}]}"#);
+    let back: Box<dyn List> = serde_json::from_str(&json).unwrap();
+    assert_eq!(back.sum(), 3);
+
+    let nil: Box<dyn List> = serde_json::from_str(r#""Nil""#).unwrap();
+    assert_eq!(serde_json::to_string(&*nil).unwrap(), r#""Nil""#);
+}
+
+mod tagged {
+    use enum_typer::type_enum;
+
+    type_enum! {
+        #[type_enum(typetag)]
+        pub enum Shape {
+            Circle(pub f64) : Shape,
+            Rect { pub w: f64, pub h: f64 } : Shape,
+        }
+
+        fn area(&self) -> f64 {
+            Circle(r) => 3.0 * r * r,
+            Rect { w, h } => w * h,
+        }
+    }
+}
+
+#[test]
+fn test_typetag_roundtrip() {
+    use tagged::*;
+
+    let shapes: Vec<Box<dyn Shape>> =
+        vec![Box::new(Circle(1.0)), Box::new(Rect { w: 2.0, h: 3.0 })];
+    let json = serde_json::to_string(&shapes).unwrap();
+    assert_eq!(json, r#"[{"Circle":1.0},{"Rect":{"w":2.0,"h":3.0}}]"#);
+    let back: Vec<Box<dyn Shape>> = serde_json::from_str(&json).unwrap();
+    assert_eq!(
+        back.iter().map(|s| s.area()).collect::<Vec<_>>(),
+        [3.0, 6.0]
+    );
+}
+
+#[test]
+fn test_static_functions() {
+    type_enum! {
+        enum Expr<T> {
+            Num(i32) : Expr<i32>,
+            Add(Box<dyn Expr<i32>>, Box<dyn Expr<i32>>) : Expr<i32>,
+            Flag(bool) : Expr<bool>,
+        }
+
+        fn eval(&self) -> T {
+            Num(n) => *n,
+            Add(a, b) => a.eval() + b.eval(),
+            Flag(b) => *b,
+        }
+
+        fn arity() -> usize {
+            Add => 2,
+            _ => 1,
+        }
+
+        fn zero() -> T {
+            Num => 0,
+            Add => 0,
+            Flag => false,
+        }
+    }
+
+    assert_eq!((Add::arity(), Num::arity(), Flag::arity()), (2, 1, 1));
+    assert_eq!(Num::zero(), 0);
+    assert!(!Flag::zero());
+
+    // The trait stays usable as a trait object
+    let expr: Box<dyn Expr<i32>> = Box::new(Add(Box::new(Num(1)), Box::new(Num(2))));
+    assert_eq!(expr.eval(), 3);
+}
+
+#[test]
+fn test_default_methods() {
+    type_enum! {
+        enum Shape {
+            Circle(f64),
+            Square(f64),
+            Dot,
+        }
+
+        fn area(&self) -> f64 {
+            Circle(r) => 3.0 * r * r,
+            Square(side) => side * side,
+            Dot => 0.0,
+        }
+
+        default fn describe(&self) -> String {
+            format!("a shape with area {}", self.area())
+        }
+
+        default fn name(&self) -> String {
+            Dot => "dot".to_string(),
+            Circle(r) if *r > 10.0 => "big circle".to_string(),
+            _ => "shape".to_string(),
+        }
+    }
+
+    let square: Box<dyn Shape> = Box::new(Square(2.0));
+    assert_eq!(square.describe(), "a shape with area 4");
+    assert_eq!(square.name(), "shape");
+    assert_eq!(Dot.name(), "dot");
+    assert_eq!(Circle(20.0).name(), "big circle");
+    assert_eq!(Circle(1.0).name(), "shape");
+}
+
+#[test]
+fn test_impl_sections() {
+    use std::fmt;
+
+    type_enum! {
+        enum Shape: fmt::Display {
+            Circle(f64),
+            Square(f64),
+            Dot,
+        }
+
+        impl fmt::Display {
+            Circle(r) => write!(f, "circle {}", r),
+            Square(side) => write!(f, "square {}", side),
+            _ => write!(f, "dot"),
+        }
+
+        impl Default {
+            fn default() -> Self {
+                Circle => Circle(1.0),
+                Square => Square(1.0),
+                Dot => Dot,
+            }
+        }
+    }
+
+    type_enum! {
+        enum Term<T> {
+            Num(i32) : Term<i32>,
+            Flag(bool) : Term<bool>,
+        }
+
+        impl PartialEq {
+            fn eq(&self, other: &Self) -> bool {
+                Num(n) => *n == other.0,
+                Flag(b) => *b == other.0,
+            }
+        }
+    }
+
+    let shape: Box<dyn Shape> = Box::new(Square(2.0));
+    assert_eq!(shape.to_string(), "square 2");
+    assert_eq!(Circle(1.5).to_string(), "circle 1.5");
+    assert_eq!(Dot.to_string(), "dot");
+    assert_eq!(Square::default().to_string(), "square 1");
+    assert!(Num(1) == Num(1));
+    assert!(Flag(true) != Flag(false));
+}
+
+#[test]
+fn test_cfg_variants() {
+    type_enum! {
+        enum Expr<T> {
+            IntLit(i32) : Expr<i32>,
+            #[cfg(any())]
+            FloatLit(f64) : Expr<f64>,
+            #[cfg(all())]
+            #[cfg_attr(all(), derive(Debug))]
+            BoolLit(bool) : Expr<bool>,
+        }
+
+        fn eval(&self) -> T {
+            IntLit(i) => *i,
+            FloatLit(f) => *f,
+            BoolLit(b) => *b,
+        }
+    }
+
+    let expr: Box<dyn Expr<i32>> = Box::new(IntLit(3));
+    assert_eq!(expr.eval(), 3);
+    assert_eq!(format!("{:?}", BoolLit(true)), "BoolLit(true)");
+}
+
+#[test]
+fn test_debug_option() {
+    type_enum! {
+        #[type_enum(debug)]
+        enum Expr<T> {
+            Lit(T) : Expr<T>,
+            Not(Box<dyn Expr<bool>>) : Expr<bool>,
+            #[derive(Debug, Clone)]
+            Pair { left: i32, right: i32 } : Expr<i32>,
+        }
+
+        fn size(&self) -> i32 {
+            Lit(_) => 1,
+            Not(inner) => 1 + inner.size(),
+            Pair { left, right } => left + right,
+        }
+    }
+
+    let expr: Box<dyn Expr<bool>> = Box::new(Not(Box::new(Lit(true))));
+    assert_eq!(format!("{:?}", expr), "Not(Lit(true))");
+    let pair: &dyn Expr<i32> = &Pair { left: 1, right: 2 };
+    assert_eq!(format!("{:?}", pair), "Pair { left: 1, right: 2 }");
+    assert_eq!(expr.size() + pair.size(), 5);
+}
+
+#[test]
+fn test_clone_boxed_trait_objects() {
+    type_enum! {
+        #[derive(Clone)]
+        enum Expr<T> {
+            Num(i32) : Expr<i32>,
+            Flag(bool) : Expr<bool>,
+            Not(Box<dyn Expr<bool>>) : Expr<bool>,
+            Twice<E: Expr<i32>>(E) : Expr<i32>,
+        }
+
+        fn eval(&self) -> T {
+            Num(n) => *n,
+            Flag(b) => *b,
+            Not(inner) => !inner.eval(),
+            Twice(inner) => 2 * inner.eval(),
+        }
+    }
+
+    let expr: Box<dyn Expr<bool>> = Box::new(Not(Box::new(Flag(true))));
+    let copy = expr.clone();
+    drop(expr);
+    assert!(!copy.eval());
+
+    let twice: Box<dyn Expr<i32>> = Box::new(Twice(Num(4)));
+    assert_eq!(twice.clone_box().eval(), 8);
+}
+
+#[test]
+fn test_trait_object_equality() {
+    type_enum! {
+        #[derive(PartialEq)]
+        enum Expr<T> {
+            Num(i32) : Expr<i32>,
+            Flag(bool) : Expr<bool>,
+            Add(Box<dyn Expr<i32>>, Box<dyn Expr<i32>>) : Expr<i32>,
+            Wrap<E: Expr<i32>>(E) : Expr<i32>,
+        }
+
+        fn eval(&self) -> T {
+            Num(n) => *n,
+            Flag(b) => *b,
+            Add(a, b) => a.eval() + b.eval(),
+            Wrap(inner) => inner.eval(),
+        }
+    }
+
+    let sum = |a, b| -> Box<dyn Expr<i32>> { Box::new(Add(Box::new(Num(a)), Box::new(Num(b)))) };
+    assert!(sum(1, 2) == sum(1, 2));
+    assert!(sum(1, 2) != sum(2, 1));
+    assert!(!sum(1, 2).eq_dyn(&Num(3)));
+    assert!(Flag(true).eq_dyn(&Flag(true)));
+    assert!(Wrap(Num(1)).eq_dyn(&Wrap(Num(1))));
+    assert!(!Wrap(Num(1)).eq_dyn(&Num(1)));
+    assert_eq!(sum(1, 2).eval(), 3);
+}
+
+#[test]
+fn test_trait_objects_as_hash_keys() {
+    use std::collections::HashMap;
+
+    type_enum! {
+        #[derive(PartialEq, Eq, Hash)]
+        enum Term<T> {
+            Num(i32) : Term<i32>,
+            Neg(i32) : Term<i32>,
+            Add(Box<dyn Term<i32>>, Box<dyn Term<i32>>) : Term<i32>,
+            Flag(bool) : Term<bool>,
+        }
+
+        fn eval(&self) -> T {
+            Num(n) => *n,
+            Neg(n) => -n,
+            Add(a, b) => a.eval() + b.eval(),
+            Flag(b) => *b,
+        }
+    }
+
+    let mut cache: HashMap<Box<dyn Term<i32>>, i32> = HashMap::new();
+    let term = || -> Box<dyn Term<i32>> { Box::new(Add(Box::new(Num(1)), Box::new(Neg(1)))) };
+    cache.insert(term(), term().eval());
+    cache.insert(Box::new(Num(1)), 1);
+    cache.insert(Box::new(Neg(1)), -1);
+    assert_eq!(cache.len(), 3);
+    assert_eq!(cache.get(&term()), Some(&0));
+    assert!(Flag(true).eval());
+}
+
+#[test]
+fn test_display_trait_objects() {
+    use std::fmt;
+
+    type_enum! {
+        enum Expr<T> {
+            Num(i32) : Expr<i32>,
+            Flag(bool) : Expr<bool>,
+            Not(Box<dyn Expr<bool>>) : Expr<bool>,
+            Less(Box<dyn Expr<i32>>, Box<dyn Expr<i32>>) : Expr<bool>,
+        }
+
+        impl fmt::Display {
+            Num(n) => write!(f, "{}", n),
+            Flag(b) => write!(f, "{}", b),
+            Not(inner) => write!(f, "!{}", inner),
+            Less(lhs, rhs) => write!(f, "{} < {}", lhs, rhs),
+        }
+    }
+
+    type_enum! {
+        enum Unit {
+            Meters(f64),
+            Feet(f64),
+        }
+
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            Meters(m) => write!(f, "{}m", m),
+            Feet(ft) => write!(f, "{}ft", ft),
+        }
+    }
+
+    let expr: Box<dyn Expr<bool>> =
+        Box::new(Not(Box::new(Less(Box::new(Num(1)), Box::new(Num(2))))));
+    assert_eq!(expr.to_string(), "!1 < 2");
+    assert_eq!(format!("{}", &*expr), "!1 < 2");
+    assert_eq!(Flag(true).to_string(), "true");
+
+    let units: Vec<Box<dyn Unit>> = vec![Box::new(Meters(1.5)), Box::new(Feet(3.0))];
+    let shown: Vec<_> = units.iter().map(|unit| unit.to_string()).collect();
+    assert_eq!(shown, ["1.5m", "3ft"]);
+}
+
+#[test]
+fn test_default_variant() {
+    type_enum! {
+        enum Level {
+            Quiet,
+            #[default]
+            Normal { verbosity: u8 },
+            Loud(u8),
+        }
+
+        fn verbosity(&self) -> u8 {
+            Quiet => 0,
+            Normal { verbosity } => *verbosity,
+            Loud(v) => *v,
+        }
+    }
+
+    type_enum! {
+        enum Expr<T: Clone> {
+            #[default]
+            Lit(T) : Expr<T>,
+            Not(Box<dyn Expr<bool>>) : Expr<bool>,
+        }
+
+        fn eval(&self) -> T {
+            Lit(value) => value.clone(),
+            Not(inner) => !inner.eval(),
+        }
+    }
+
+    let level: Box<dyn Level> = Default::default();
+    assert_eq!(level.verbosity(), 0);
+    assert!((level as Box<dyn std::any::Any>).is::<Normal>());
+    assert_eq!(Normal::default().verbosity, 0);
+    assert_eq!(Loud(3).verbosity(), 3);
+    assert_eq!(Quiet.verbosity(), 0);
+
+    let num: Box<dyn Expr<i32>> = Box::default();
+    assert_eq!(num.eval(), 0);
+    let flag: Box<dyn Expr<bool>> = Box::new(Not(Box::default()));
+    assert!(flag.eval());
+}
+
+#[test]
+fn test_kind_enum() {
+    use std::convert::TryFrom;
+
+    type_enum! {
+        #[type_enum(kind)]
+        #[derive(Debug)]
+        enum Shape<U> {
+            Circle(U),
+            Rect { width: U, height: U },
+            Dot,
+        }
+
+        fn describe(&self) -> &'static str {
+            Circle(_) => "circle",
+            Rect { .. } => "rect",
+            Dot => "dot",
+        }
+    }
+
+    struct Other;
+    impl Shape<f64> for Other {
+        fn describe(&self) -> &'static str {
+            "other"
+        }
+    }
+
+    let boxed: Box<dyn Shape<f64>> = ShapeKind::Rect {
+        width: 2.0,
+        height: 3.0,
+    }
+    .into();
+    assert_eq!(boxed.describe(), "rect");
+
+    let area = match ShapeKind::try_from(boxed).ok().unwrap() {
+        ShapeKind::Circle(r) => 3.0 * r * r,
+        ShapeKind::Rect { width, height } => width * height,
+        ShapeKind::Dot => 0.0,
+    };
+    assert_eq!(area, 6.0);
+
+    let dot = ShapeKind::try_from(Box::new(Dot) as Box<dyn Shape<f64>>).ok();
+    assert!(matches!(dot, Some(ShapeKind::Dot)));
+    let other = ShapeKind::try_from(Box::new(Other) as Box<dyn Shape<f64>>).unwrap_err();
+    assert_eq!(other.describe(), "other");
+}
+
+#[test]
+fn test_boxed_method() {
+    struct Empty;
+    struct NonEmpty;
+
+    type_enum! {
+        enum Arith<T> {
+            Num(i32) : Arith<i32>,
+            Bool(bool) : Arith<bool>,
+            Add(Box<dyn Arith<i32>>, Box<dyn Arith<i32>>) : Arith<i32>,
+        }
+
+        fn eval(&self) -> T {
+            Num(n) => *n,
+            Bool(b) => *b,
+            Add(lhs, rhs) => lhs.eval() + rhs.eval(),
+        }
+    }
+
+    type_enum! {
+        enum List<T, E> {
+            Nil : List<T, Empty>,
+            Cons(T, Box<dyn List<T, E>>) : List<T, NonEmpty>,
+        }
+
+        fn len(&self) -> usize {
+            Nil => 0,
+            Cons(_, tail) => 1 + tail.len(),
+        }
+    }
+
+    let sum = Add(Num(1).boxed(), Add(Num(2).boxed(), Num(3).boxed()).boxed());
+    assert_eq!(sum.eval(), 6);
+    assert!(Bool(true).boxed().eval());
+
+    // `Nil` doesn't carry `T`, so `boxed` takes it as a parameter
+    let list = Cons('a', Cons('b', Nil.boxed()).boxed()).boxed();
+    assert_eq!(list.len(), 2);
+    let empty = Nil.boxed::<u8>();
+    assert_eq!(empty.len(), 0);
+}
+
+#[test]
+fn test_variant_name() {
+    type_enum! {
+        enum Expr<T> {
+            Zero : Expr<i32>,
+            Pair<A, B>(A, B) : Expr<(A, B)>,
+        }
+    }
+
+    type_enum! {
+        enum Shape {
+            Circle,
+            Square,
+        }
+    }
+
+    struct Triangle;
+    impl Shape for Triangle {}
+
+    let num: Box<dyn Expr<i32>> = Box::new(Zero);
+    assert_eq!(num.variant_name(), "Zero");
+    let pair: Box<dyn Expr<(i32, char)>> = Box::new(Pair(1, 'a'));
+    assert_eq!(pair.variant_name(), "Pair");
+
+    let shapes: Vec<Box<dyn Shape>> = vec![Box::new(Circle), Box::new(Square)];
+    let names: Vec<_> = shapes.iter().map(|shape| shape.variant_name()).collect();
+    assert_eq!(names, ["Circle", "Square"]);
+    assert!(Triangle.variant_name().ends_with("Triangle"));
+}
+
+#[test]
+fn test_variant_consts() {
+    type_enum! {
+        enum Expr<T> {
+            Num(i32) : Expr<i32>,
+            Flag(bool) : Expr<bool>,
+            #[cfg(any())]
+            Gone(i32) : Expr<i32>,
+        }
+
+        fn eval(&self) -> T {
+            Num(n) => *n,
+            Flag(b) => *b,
+            #[cfg(any())]
+            Gone(n) => *n,
+        }
+    }
+
+    assert_eq!(<dyn Expr<i32>>::VARIANT_NAMES, ["Num", "Flag"]);
+    assert_eq!(<dyn Expr<bool>>::VARIANT_COUNT, 2);
+    assert_eq!(Num(4).eval() + Flag(true).eval() as i32, 5);
+}
+
+#[test]
+fn test_downcast_helpers() {
+    type_enum! {
+        enum Expr<T> {
+            Num(i32) : Expr<i32>,
+            Lit(T) : Expr<T>,
+            BinOp(Box<dyn Expr<i32>>, Box<dyn Expr<i32>>) : Expr<i32>,
+        }
+    }
+
+    let mut expr: Box<dyn Expr<i32>> = Box::new(Num(1));
+    assert!(expr.is_num());
+    assert!(!expr.is_bin_op());
+    assert!(expr.as_lit().is_none());
+    if let Some(num) = expr.as_num_mut() {
+        num.0 += 1;
+    }
+    assert_eq!(expr.as_num().map(|num| num.0), Some(2));
+
+    let lit: &dyn Expr<&str> = &Lit("a");
+    assert_eq!(lit.as_lit().map(|lit| lit.0), Some("a"));
+
+    let op: Box<dyn Expr<i32>> = Box::new(BinOp(Box::new(Num(1)), Box::new(Lit(2))));
+    let (lhs, rhs) = op.as_bin_op().map(|op| (&op.0, &op.1)).unwrap();
+    assert!(lhs.is_num() && rhs.is_lit());
+}
+
+#[test]
+fn test_try_from_box() {
+    use std::convert::TryFrom;
+
+    type_enum! {
+        enum Nat {
+            Zero,
+            Succ<N: Nat>(N) : Nat,
+        }
+    }
+
+    type_enum! {
+        enum Expr<T> {
+            Num(i32) : Expr<i32>,
+            Flag(bool) : Expr<bool>,
+            Neg(Box<dyn Expr<i32>>) : Expr<i32>,
+        }
+    }
+
+    let two: Box<dyn Nat> = Box::new(Succ(Succ(Zero)));
+    let two = Zero::try_from(two).err().unwrap();
+    let Succ(Succ(Zero)) = Succ::<Succ<Zero>>::try_from(two).ok().unwrap();
+
+    let expr: Box<dyn Expr<i32>> = Box::new(Neg(Box::new(Num(3))));
+    let expr = Num::try_from(expr).err().unwrap();
+    let Neg(inner) = Neg::try_from(expr).ok().unwrap();
+    assert_eq!(Num::try_from(inner).ok().map(|num| num.0), Some(3));
+    let flag: Box<dyn Expr<bool>> = Box::new(Flag(true));
+    assert!(Flag::try_from(flag).ok().unwrap().0);
+}
+
+mod impl_shapes {
+    use enum_typer::type_enum;
+
+    type_enum! {
+        pub enum Shape {
+            Circle(pub f64),
+            Square(pub f64),
+        }
+    }
+
+    type_enum! {
+        pub enum Term<T> {
+            Num(pub i32) : Term<i32>,
+            Flag(pub bool) : Term<bool>,
+        }
+    }
+}
+
+type_enum_impl! {
+    impl_shapes::Shape =>
+
+    pub fn area(&self) -> f64 {
+        Circle(r) => 3.0 * r * r,
+        Square(side) => side * side,
+    }
+
+    pub fn scale(&mut self, factor: f64) {
+        Circle(r) => *r *= factor,
+        Square(side) => *side *= factor,
+    }
+
+    fn sides() -> usize {
+        Circle => 0,
+        Square => 4,
+    }
+}
+
+type_enum_impl! {
+    impl_shapes::Term =>
+
+    fn value(&self) -> T {
+        Num(n) => *n,
+        Flag(b) => *b,
+    }
+}
+
+#[test]
+fn test_type_enum_impl() {
+    use impl_shapes::{Circle, Flag, Num, Shape, Square};
+
+    assert_eq!(Square(2.0).area(), 4.0);
+    assert_eq!(Circle::sides(), 0);
+    assert_eq!(Square::sides(), 4);
+    assert_eq!(Num(3).value(), 3);
+    assert!(Flag(true).value());
+
+    // Every variant has an arm, so the trait object forwards to them
+    let mut shape: Box<dyn Shape> = Box::new(Circle(1.0));
+    shape.scale(2.0);
+    assert_eq!(shape.area(), 12.0);
+}
+
+mod arith {
+    use enum_typer::type_enum;
+
+    type_enum! {
+        #[type_enum(constructors)]
+        pub enum Arith<T> {
+            Num(i32) : Arith<i32>,
+            Bool(bool) : Arith<bool>,
+            Add(Box<dyn Arith<i32>>, Box<dyn Arith<i32>>) : Arith<i32>,
+            IfThen { cond: Box<dyn Arith<bool>>, then: Box<dyn Arith<T>>, otherwise: Box<dyn Arith<T>> } : Arith<T>,
+        }
+
+        fn eval(&self) -> T {
+            Num(n) => *n,
+            Bool(b) => *b,
+            Add(lhs, rhs) => lhs.eval() + rhs.eval(),
+            IfThen { cond, then, otherwise } => {
+                if cond.eval() { then.eval() } else { otherwise.eval() }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_constructor_module() {
+    use arith::arith::{add, bool, if_then, num};
+
+    let term = if_then(bool(false), num(1), add(num(2), num(3)));
+    assert_eq!(term.eval(), 5);
+}
+
+mod open_shapes {
+    use enum_typer::type_enum;
+    use std::fmt::Display;
+
+    type_enum! {
+        #[derive(Clone, PartialEq)]
+        pub enum Shape {
+            Circle(pub f64),
+            Square(pub f64),
+        }
+
+        fn area(&self) -> f64 {
+            Circle(r) => 3.0 * r * r,
+            Square(side) => side * side,
+        }
+
+        impl Display {
+            Circle(r) => write!(f, "circle of radius {}", r),
+            Square(side) => write!(f, "square of side {}", side),
+        }
+    }
+}
+
+extend_enum! {
+    pub open_shapes::Shape {
+        Hexagon(pub f64),
+        Rect { width: f64, height: f64 },
+    }
+
+    fn area(&self) -> f64 {
+        Hexagon(side) => 2.5 * side * side,
+        Rect { width, height } => width * height,
+    }
+
+    impl std::fmt::Display {
+        Hexagon(side) => write!(f, "hexagon of side {}", side),
+        Rect { width, height } => write!(f, "{}x{} rectangle", width, height),
+    }
+}
+
+#[test]
+fn test_extend_enum() {
+    use open_shapes::{Circle, Shape};
+
+    let shapes: Vec<Box<dyn Shape>> = vec![
+        Box::new(Circle(1.0)),
+        Hexagon(2.0).boxed(),
+        Rect {
+            width: 2.0,
+            height: 3.0,
+        }
+        .into(),
+    ];
+    let areas: Vec<f64> = shapes.iter().map(|shape| shape.area()).collect();
+    assert_eq!(areas, [3.0, 10.0, 6.0]);
+    assert_eq!(shapes[1].to_string(), "hexagon of side 2");
+    assert_eq!(shapes[2].variant_name(), "Rect");
+
+    // The new variants take the enum's derives, so they clone and compare
+    // as trait objects like its own
+    let copy = shapes[1].clone();
+    assert!(copy == shapes[1]);
+    assert!(copy != shapes[0]);
+    assert!(Hexagon::try_from(copy).ok() == Some(Hexagon(2.0)));
+}
+
+mod namespaced {
+    use enum_typer::{match_t, type_enum};
+
+    // Both enums have a `Value` variant; each keeps its structs in a module
+    type_enum! {
+        #[type_enum(namespace)]
+        pub enum Token {
+            Value(pub i32),
+            Word(pub String),
+        }
+
+        fn width(&self) -> usize {
+            Value(v) => v.to_string().len(),
+            Word(w) => w.len(),
+        }
+    }
+
+    type_enum! {
+        #[type_enum(namespace = json, reexport)]
+        pub enum Json {
+            Value(pub f64),
+            Null,
+        }
+
+        fn as_f64(&self) -> Option<f64> {
+            Value(v) => Some(*v),
+            Null => None,
+        }
+    }
+
+    pub fn token_value(token: &dyn Token) -> i32 {
+        match_t!(token as Token {
+            Value(v) => *v,
+            Word(w) => w.len() as i32,
+        })
+    }
+}
+
+#[test]
+fn test_namespace() {
+    use namespaced::{json, token, token_value, Json, Token};
+
+    let tokens: Vec<Box<dyn Token>> = vec![
+        Box::new(token::Value(123)),
+        Box::new(token::Word("hi".to_string())),
+    ];
+    assert_eq!(tokens[0].width(), 3);
+    assert_eq!(tokens[1].width(), 2);
+    assert_eq!(token_value(&*tokens[0]), 123);
+    assert_eq!(token_value(&*tokens[1]), 2);
+
+    // `reexport` puts `Json`'s structs next to the trait as well
+    let values: Vec<Box<dyn Json>> = vec![Box::new(namespaced::Value(1.5)), Box::new(json::Null)];
+    assert_eq!(values[0].as_f64(), Some(1.5));
+    assert_eq!(values[1].as_f64(), None);
+}
+
+mod renamed {
+    use enum_typer::{match_t, type_enum};
+
+    // An existing `Circle` the variant struct would otherwise collide with
+    pub struct Circle;
+
+    type_enum! {
+        #[type_enum(rename_variants = "Shape{}", kind, constructors)]
+        pub enum Shape {
+            Circle(pub f64),
+            Square(pub f64),
+        }
+
+        fn area(&self) -> f64 {
+            Circle(r) => 3.0 * r * r,
+            Square(s) => s * s,
+        }
+    }
+
+    pub fn side(shape: &dyn Shape) -> Option<f64> {
+        match_t!(shape as Shape {
+            Square(s) => Some(*s),
+            Circle(_) => None,
+        })
+    }
+}
+
+#[test]
+fn test_rename_variants() {
+    use renamed::{shape, side, Shape, ShapeCircle, ShapeKind, ShapeSquare};
+
+    let _existing = renamed::Circle;
+    let circle: Box<dyn Shape> = Box::new(ShapeCircle(1.0));
+    let square = shape::square(2.0);
+    assert_eq!(circle.area(), 3.0);
+    assert_eq!(square.area(), 4.0);
+    assert_eq!(side(&*circle), None);
+    assert_eq!(side(&*square), Some(2.0));
+
+    // Names the user sees stay the declared ones
+    assert_eq!(square.variant_name(), "Square");
+    assert_eq!(<dyn Shape>::VARIANT_NAMES, &["Circle", "Square"]);
+    assert!(circle.is_circle());
+    assert_eq!(square.as_square().map(|s| s.0), Some(2.0));
+    let kind = ShapeKind::try_from(square).ok();
+    assert!(matches!(kind, Some(ShapeKind::Square(s)) if s == 2.0));
+    let back: Box<dyn Shape> = ShapeKind::Circle(1.0).into();
+    assert!(back.as_circle().is_some());
+    assert_eq!(ShapeSquare(3.0).area(), 9.0);
+}
+
+mod shared {
+    use enum_typer::type_enum;
+
+    type_enum! {
+        #[type_enum(send_sync)]
+        pub enum Job<T: Clone> {
+            Value(pub T): Job<T>,
+            Length(pub String): Job<usize>,
+            Both<A: Clone, B: Clone>(pub Box<dyn Job<A>>, pub Box<dyn Job<B>>): Job<(A, B)>,
+        }
+
+        fn run(&self) -> T {
+            Value(v) => v.clone(),
+            Length(s) => s.len(),
+            Both(a, b) => (a.run(), b.run()),
+        }
+    }
+}
+
+#[test]
+fn test_send_sync() {
+    use shared::{Both, Job, Length, Value};
+    use std::sync::Arc;
+
+    fn assert_send_sync<T: Send + Sync + ?Sized>() {}
+    assert_send_sync::<dyn Job<i32>>();
+
+    let job: Arc<Box<dyn Job<(usize, i32)>>> = Arc::new(Box::new(Both(
+        Box::new(Length("four".to_string())),
+        Box::new(Value(2)),
+    )));
+    let handles: Vec<_> = (0..2)
+        .map(|_| {
+            let job = Arc::clone(&job);
+            std::thread::spawn(move || job.run())
+        })
+        .collect();
+    for handle in handles {
+        assert_eq!(handle.join().unwrap(), (4, 2));
+    }
+}
+
+#[test]
+fn test_borrowed_match() {
+    type_enum! {
+        enum Buf<'a, T> {
+            Slice(bytes: &'a [u8]) : Buf<'a, u8>,
+            Text(text: &'a str, start: usize) : Buf<'a, char>,
+            Item(&'a T),
+            Pair(&'a dyn Buf<'a, T>, &'a dyn Buf<'a, T>),
+            Empty,
+        }
+
+        fn len(&self) -> usize {
+            Slice(b) => b.len(),
+            Text(t, start) => t.len() - start,
+            Item(_) => 1,
+            Pair(a, b) => a.len() + b.len(),
+            Empty => 0,
+        }
+    }
+
+    fn describe<'a>(buf: &dyn Buf<'a, u8>) -> String {
+        match_t!(buf as Buf<'a, u8> {
+            Slice(bytes) => format!("slice {:?}", bytes),
+            Item(x) => format!("item {}", x),
+            Pair(a, b) => format!("({}, {})", describe(*a), describe(*b)),
+            Empty => "empty".to_string(),
+        })
+    }
+
+    let data = vec![1u8, 2, 3];
+    let seven = 7;
+    let (slice, item) = (Slice(&data), Item(&seven));
+    assert_eq!(describe(&Pair(&slice, &item)), "(slice [1, 2, 3], item 7)");
+    assert_eq!(describe(&Empty), "empty");
+
+    let text = String::from("hello");
+    let mut view = Text(&text, 1);
+    let buf: &mut dyn Buf<'_, char> = &mut view;
+    assert!(buf.is_text() && !buf.is_empty());
+    assert_eq!(buf.as_text().map(|t| *t.text()), Some("hello"));
+    buf.as_text_mut().unwrap().1 = 2;
+    assert_eq!(buf.len(), 3);
+    match_t!(mut buf as Buf<'_, char> {
+        Text(_, start) => *start = 3,
+        _ => {}
+    });
+    assert_eq!(buf.len(), 2);
+}
+
+mod recursive {
+    use enum_typer::type_enum;
+
+    type_enum! {
+        #[type_enum(of)]
+        #[derive(Clone, PartialEq)]
+        pub enum Expr<T> {
+            Num(pub i32) : Expr<i32>,
+            Bool(pub bool) : Expr<bool>,
+            Add(pub Self, pub Self) : Expr<i32>,
+            If(pub Self<bool>, pub Self<T>, pub Self<T>),
+            Sum(pub Vec<Self<i32>>) : Expr<i32>,
+        }
+
+        fn eval(&self) -> T {
+            Num(n) => *n,
+            Bool(b) => *b,
+            Add(a, b) => a.eval() + b.eval(),
+            If(c, t, e) => if c.eval() { t.eval() } else { e.eval() },
+            Sum(xs) => xs.iter().map(|x| x.eval()).sum(),
+        }
+    }
+}
+
+#[test]
+fn test_self_fields() {
+    use recursive::*;
+
+    let sum = Sum(vec![Num(2).boxed(), Num(3).boxed()]);
+    let expr = If::of(Bool(true), Add::of(Num(1), sum), Num(0));
+    assert_eq!(expr.eval(), 6);
+    let add: Box<dyn Expr<i32>> = Add(Num(1).boxed(), Num(2).boxed()).into();
+    assert!(add.clone() == add);
+}
+
+#[test]
+fn test_bounds_on_enum_params() {
+    type_enum! {
+        #[type_enum(of)]
+        enum Num<T: Copy + Default> where T: std::ops::Add<Output = T> {
+            Lit(T),
+            Plus(Box<dyn Num<T>>, Box<dyn Num<T>>),
+            Wrap<N: Num<T>>(N),
+            Conv<U: Into<T> + Copy>(U),
+        }
+
+        fn value(&self) -> T {
+            Lit(t) => *t,
+            Plus(a, b) => a.value() + b.value(),
+            Wrap(n) => n.value(),
+            Conv(u) => (*u).into(),
+        }
+    }
+
+    let sum = Plus::of(Wrap(Lit(1i64)), Conv(2i32));
+    assert_eq!(sum.value(), 3);
+    let boxed: Box<dyn Num<i64>> = Wrap(Conv(4u8)).boxed();
+    assert_eq!(boxed.value(), 4);
+}
+
+#[test]
+fn test_match_t_discriminants() {
+    type_enum! {
+        enum Op {
+            Push(i32),
+            Pop,
+            Jump { to: usize },
+        }
+    }
+
+    fn describe(op: &dyn Op) -> String {
+        match_t!(op as Op {
+            Jump { to: 0 } => "restart".to_string(),
+            Push(n) => format!("push {}", n),
+            Jump { to } => format!("jump {}", to),
+            Pop => "pop".to_string(),
+        })
+    }
+
+    let program: Vec<Box<dyn Op>> = vec![
+        Push(1).into(),
+        Jump { to: 0 }.into(),
+        Pop.into(),
+        Jump { to: 3 }.into(),
+    ];
+    let described: Vec<_> = program.iter().map(|op| describe(op.as_ref())).collect();
+    assert_eq!(described, ["push 1", "restart", "pop", "jump 3"]);
+
+    let popped = match_t!(move program.into_iter().nth(2).unwrap() as Op {
+        Push(n) => n,
+        Pop => -1,
+        _ => 0,
+    });
+    assert_eq!(popped, -1);
+}
+
+#[no_implicit_prelude]
+mod no_prelude {
+    use ::enum_typer::{match_t, type_enum};
+    use ::std::boxed::Box;
+
+    type_enum! {
+        #[derive(Debug)]
+        #[type_enum(debug)]
+        pub enum Cell {
+            #[default]
+            Empty,
+            Full(pub u8),
+        }
+
+        fn count(&self) -> usize {
+            Empty => 0,
+            Full(_) => 1,
+        }
+    }
+
+    pub fn weigh(cell: Box<dyn Cell>) -> u8 {
+        let by_ref = match_t!(&*cell as Cell {
+            Empty => 0,
+            Full(n) => *n,
+        });
+        let by_value = match_t!(move cell as Cell {
+            Empty => 0,
+            Full(n) => n,
+        });
+        by_ref + by_value
+    }
+}
+
+#[test]
+fn test_no_implicit_prelude() {
+    use no_prelude::*;
+
+    assert_eq!(weigh(Full(3).into()), 6);
+    assert_eq!(weigh(Box::<dyn Cell>::default()), 0);
+    assert_eq!(Full(1).count(), 1);
+}
+
+#[test]
+fn test_impl_sections_on_variants_without_the_index() {
+    type_enum! {
+        enum Node<T> {
+            Leaf(T),
+            Empty,
+            Wrap<N: Node<T>>(N),
+        }
+
+        impl std::fmt::Display {
+            Leaf(_) => f.write_str("leaf"),
+            Empty => f.write_str("empty"),
+            Wrap(_) => f.write_str("wrap"),
+        }
+    }
+
+    assert_eq!(Leaf(1).to_string(), "leaf");
+    assert_eq!(Empty.to_string(), "empty");
+    assert_eq!(Wrap(Leaf("x")).to_string(), "wrap");
+}
+
+#[test]
+fn test_match_t_hygiene() {
+    type_enum! {
+        enum Slot {
+            Open(u8),
+            Closed,
+        }
+    }
+
+    // Locals named like the ones `match_t!` expands to stay visible to the
+    // scrutinee and the arm bodies
+    let __expr: Box<dyn Slot> = Open(2).into();
+    let __value = 10u8;
+    let __matched_idx = 100u8;
+    let by_ref = match_t!(&*__expr as Slot {
+        Open(n) => *n + __value,
+        Closed => __matched_idx,
+    });
+    assert_eq!(by_ref, 12);
+    let by_value = match_t!(move __expr as Slot {
+        Open(n) => n + __value + __matched_idx,
+        Closed => 0,
+    });
+    assert_eq!(by_value, 112);
+}
+
+#[test]
+fn test_match_t_early_return() {
+    type_enum! {
+        enum Shape {
+            Circle(f64),
+            Square(Option<f64>),
+        }
+    }
+
+    // `?` and `return` in an arm leave the enclosing function, not the match
+    fn side(shape: &dyn Shape) -> Option<f64> {
+        let side = match_t!(shape as Shape {
+            Circle(r) => *r,
+            Square(s) => (*s)?,
+        });
+        Some(side)
+    }
+
+    fn radius(shape: &dyn Shape) -> Option<f64> {
+        match_t!(try shape as Shape {
+            Circle(r) => *r,
+            Square(_) => return Some(0.0),
+        })
+    }
+
+    fn total(lhs: &dyn Shape, rhs: &dyn Shape) -> Option<f64> {
+        let total = match_t!((lhs, rhs) as Shape {
+            (Square(None), _) | (_, Square(None)) => return None,
+            (Circle(a) | Square(Some(a)), Circle(b) | Square(Some(b))) => a + b,
+        });
+        Some(total)
+    }
+
+    assert_eq!(side(&Circle(1.0)), Some(1.0));
+    assert_eq!(side(&Square(Some(2.0))), Some(2.0));
+    assert_eq!(side(&Square(None)), None);
+    assert_eq!(radius(&Circle(1.0)), Some(1.0));
+    assert_eq!(radius(&Square(None)), Some(0.0));
+    assert_eq!(total(&Circle(1.0), &Square(Some(2.0))), Some(3.0));
+    assert_eq!(total(&Circle(1.0), &Square(None)), None);
+}
+
+#[test]
+fn test_match_t_fallback() {
+    type_enum! {
+        enum Term<T> {
+            Int(i64) : Term<i64>,
+            Bool(bool) : Term<bool>,
+            Neg(Box<dyn Term<i64>>) : Term<i64>,
+            Pair(T, T),
+        }
+    }
+
+    // `Bool` can't be a `Term<i64>`, and the literal arm is backed by the
+    // one binding `n`, so these arms cover every value
+    fn sign(term: &dyn Term<i64>) -> i64 {
+        match_t!(term as Term<i64> {
+            Int(0) => 0,
+            Int(n) => n.signum(),
+            Neg(inner) => -sign(inner.as_ref()),
+            Pair(a, b) => (a + b).signum(),
+        })
+    }
+
+    fn is_int(term: Box<dyn Term<i64>>) -> bool {
+        match_t!(move term as Term<i64> {
+            Int(_) => true,
+            _ => false,
+        })
+    }
+
+    fn describe(term: &dyn Term<bool>) -> &'static str {
+        match_t!(term as Term<bool> {
+            Bool(true) => "true",
+            _ => "other",
+        })
+    }
+
+    assert_eq!(sign(&Neg(Box::new(Int(3)))), -1);
+    assert_eq!(sign(&Pair(2, -5)), -1);
+    assert!(is_int(Box::new(Int(1))));
+    assert!(!is_int(Box::new(Pair(1, 2))));
+    assert_eq!(describe(&Bool(true)), "true");
+    assert_eq!(describe(&Pair(true, false)), "other");
+}
+
+#[test]
+fn test_match_t_open_fallback() {
+    use std::any::Any;
+
+    type_enum! {
+        enum Unit {
+            Meter(f64),
+            Foot(f64),
+        }
+    }
+
+    // Any other value may sit behind `dyn Any`; `_` takes it instead of
+    // panicking
+    fn meters(value: &dyn Any) -> Option<f64> {
+        match_t!(value as Unit {
+            Meter(m) => Some(*m),
+            Foot(f) => Some(f * 0.3048),
+            _ => None,
+        })
+    }
+
+    fn consume(value: Box<dyn Any>) -> &'static str {
+        match_t!(move value {
+            Meter(_) => "meter",
+            _ => "unknown",
+        })
+    }
+
+    assert_eq!(meters(&Meter(2.0)), Some(2.0));
+    assert_eq!(meters(&"two meters"), None);
+    assert_eq!(consume(Box::new(Meter(1.0))), "meter");
+    assert_eq!(consume(Box::new(Foot(1.0))), "unknown");
+    assert_eq!(consume(Box::new(3u8)), "unknown");
+}
+
+#[test]
+fn test_try_match_t() {
+    type_enum! {
+        enum Instr {
+            Load(String),
+            Jump { to: usize },
+            Halt,
+        }
+    }
+
+    fn target(instr: &dyn Instr) -> Option<usize> {
+        match_t!(try instr as Instr {
+            Jump { to } => *to,
+        })
+    }
+
+    // Refutable arms are tried in order on the value still in its box, and a
+    // value none of them match is handed back
+    fn take_load(instr: Box<dyn Instr>) -> Result<String, Box<dyn Instr>> {
+        match_t!(try move instr as Instr {
+            Jump { to: 0 } => "restart".to_string(),
+            Load(name) => name,
+        })
+    }
+
+    assert_eq!(target(&Jump { to: 4 }), Some(4));
+    assert_eq!(target(&Halt), None);
+    assert_eq!(
+        take_load(Box::new(Load("r1".to_string()))).ok(),
+        Some("r1".to_string())
+    );
+    assert_eq!(
+        take_load(Box::new(Jump { to: 0 })).ok().as_deref(),
+        Some("restart")
+    );
+    match take_load(Box::new(Jump { to: 7 })) {
+        Ok(_) => panic!("`Jump {{ to: 7 }}` matched no arm"),
+        Err(back) => assert_eq!(target(back.as_ref()), Some(7)),
+    }
+}
+
+#[test]
+fn test_match_t_else() {
+    type_enum! {
+        enum Job {
+            Print(String),
+            Sleep { ms: u64 },
+            Stop,
+        }
+    }
+
+    // The box no arm takes is handed to the `else` clause still owned, so
+    // it can be queued again
+    fn run(job: Box<dyn Job>, queue: &mut Vec<Box<dyn Job>>) -> Option<String> {
+        match_t!(move job as Job {
+            Print(text) => Some(text),
+            Stop => None,
+        } else |pending| {
+            queue.push(pending);
+            None
+        })
+    }
+
+    fn delay(job: &dyn Job) -> u64 {
+        match_t!(job as Job {
+            Sleep { ms } => *ms,
+        } else { 0 })
+    }
+
+    let mut queue = Vec::new();
+    assert_eq!(
+        run(Box::new(Print("hi".to_string())), &mut queue).as_deref(),
+        Some("hi")
+    );
+    assert_eq!(run(Box::new(Sleep { ms: 5 }), &mut queue), None);
+    assert_eq!(queue.len(), 1);
+    assert_eq!(delay(queue[0].as_ref()), 5);
+    assert_eq!(delay(&Stop), 0);
+}
+
+#[test]
+fn test_match_t_or_patterns() {
+    type_enum! {
+        enum Shape {
+            Rectangle(f64, f64),
+            Square(f64),
+            Circle { r: f64 },
+            Dot,
+        }
+    }
+
+    fn corners(shape: Box<dyn Shape>) -> u32 {
+        match_t!(move shape as Shape {
+            Rectangle(..) | Square(..) => 4,
+            | Circle { .. } | Dot => 0,
+        })
+    }
+
+    // Each case of an or-pattern is tested for its own type, with the
+    // bindings they share handed to one body
+    fn width(shape: &dyn Shape) -> f64 {
+        match_t!(shape as Shape {
+            Rectangle(w, _) | Square(w) => *w,
+            Circle { r } => r * 2.0,
+            Dot => 0.0,
+        })
+    }
+
+    fn height(shape: &dyn Shape) -> f64 {
+        match_t!(shape as Shape {
+            Rectangle(_, h) | Square(h) => *h,
+            Circle { r } => r * 2.0,
+            Dot => 0.0,
+        })
+    }
+
+    assert_eq!(corners(Box::new(Square(1.0))), 4);
+    assert_eq!(corners(Box::new(Rectangle(1.0, 2.0))), 4);
+    assert_eq!(corners(Box::new(Dot)), 0);
+    assert_eq!(width(&Rectangle(3.0, 4.0)), 3.0);
+    assert_eq!(height(&Rectangle(3.0, 4.0)), 4.0);
+    assert_eq!(height(&Square(2.0)), 2.0);
+    assert_eq!(width(&Circle { r: 1.0 }), 2.0);
+}
+
+#[test]
+fn test_match_t_at_bindings() {
+    type_enum! {
+        enum Shape {
+            Circle(f64),
+            Square { side: f64 },
+        }
+    }
+
+    fn diameter(circle: &Circle) -> f64 {
+        circle.0 * 2.0
+    }
+
+    fn size(shape: &dyn Shape) -> f64 {
+        match_t!(shape as Shape {
+            Circle(r @ 0.0..=1.0) => *r,
+            c @ Circle(_) => diameter(c),
+            Square { side } => *side,
+        })
+    }
+
+    // Under `move` the binding takes the struct out of the box
+    fn into_circle(shape: Box<dyn Shape>) -> Result<Circle, Box<dyn Shape>> {
+        match_t!(try move shape as Shape {
+            c @ Circle(_) => c,
+        })
+    }
+
+    assert_eq!(size(&Circle(0.5)), 0.5);
+    assert_eq!(size(&Circle(2.0)), 4.0);
+    assert_eq!(size(&Square { side: 3.0 }), 3.0);
+    assert_eq!(
+        into_circle(Box::new(Circle(1.0))).ok().map(|c| c.0),
+        Some(1.0)
+    );
+    assert!(into_circle(Box::new(Square { side: 1.0 })).is_err());
+}
+
+#[test]
+fn test_match_t_literal_and_range_arms() {
+    type_enum! {
+        enum Token {
+            Num(i32),
+            Op(char),
+        }
+    }
+
+    // Arms of one variant are told apart by their fields, in order, both
+    // by reference and when moving out of the box
+    fn by_ref(token: &dyn Token) -> &'static str {
+        match_t!(token as Token {
+            Num(0) => "zero",
+            Num(1..=9) => "digit",
+            Num(_) => "number",
+            Op('+' | '-') => "additive",
+            Op(_) => "operator",
+        })
+    }
+
+    fn by_move(token: Box<dyn Token>) -> &'static str {
+        match_t!(move token as Token {
+            Num(0) => "zero",
+            Num(1..=9) => "digit",
+            Num(_) => "number",
+            Op('+' | '-') => "additive",
+            Op(_) => "operator",
+        })
+    }
+
+    for (token, expected) in [
+        (Box::new(Num(0)) as Box<dyn Token>, "zero"),
+        (Box::new(Num(7)), "digit"),
+        (Box::new(Num(42)), "number"),
+        (Box::new(Op('-')), "additive"),
+        (Box::new(Op('*')), "operator"),
+    ] {
+        assert_eq!(by_ref(token.as_ref()), expected);
+        assert_eq!(by_move(token), expected);
+    }
+}
+
+#[test]
+fn test_match_t_nested_box_patterns() {
+    type_enum! {
+        enum Expr<T> {
+            Num(i32) : Expr<i32>,
+            Neg(Box<dyn Expr<i32>>) : Expr<i32>,
+            Add(Box<dyn Expr<i32>>, Box<dyn Expr<i32>>) : Expr<i32>,
+            Bool(bool) : Expr<bool>,
+        }
+
+        fn eval(&self) -> T {
+            Num(n) => *n,
+            Bool(b) => *b,
+            Neg(e) => -e.eval(),
+            Add(lhs, rhs) => lhs.eval() + rhs.eval(),
+        }
+    }
+
+    // `box P` downcasts the boxed field and matches it against `P`; an arm
+    // whose nested pattern doesn't match falls through to the next one
+    fn describe(expr: &dyn Expr<i32>) -> String {
+        match_t!(expr as Expr<i32> {
+            Add(box Num(0), rhs) | Add(rhs, box Num(0)) => format!("0 + {}", rhs.eval()),
+            Add(box Num(a), box Neg(box Num(b))) => format!("{} - {}", a, b),
+            Add(lhs, rhs) => format!("{} + {}", lhs.eval(), rhs.eval()),
+            Neg(box Neg(e)) => format!("{}", e.eval()),
+            Neg(e) => format!("-{}", e.eval()),
+            Num(n) => format!("{}", n),
+        })
+    }
+
+    // Under `move` the nested boxes are only consumed once the whole pattern
+    // has matched, and their fields are moved out
+    fn simplify(expr: Box<dyn Expr<i32>>) -> Box<dyn Expr<i32>> {
+        match_t!(move expr as Expr<i32> {
+            Add(box Num(0), e) | Add(e, box Num(0)) => simplify(e),
+            Neg(box Neg(e)) => simplify(e),
+            Add(lhs, rhs) => Box::new(Add(simplify(lhs), simplify(rhs))),
+            Neg(e) => Box::new(Neg(simplify(e))),
+            n @ Num(_) => Box::new(n),
+        })
+    }
+
+    let zero_plus = Add(Box::new(Num(0)), Box::new(Num(5)));
+    let minus = Add(Box::new(Num(7)), Box::new(Neg(Box::new(Num(2)))));
+    let plus_neg = Add(
+        Box::new(Num(7)),
+        Box::new(Neg(Box::new(Neg(Box::new(Num(2)))))),
+    );
+    assert_eq!(describe(&zero_plus), "0 + 5");
+    assert_eq!(describe(&Add(Box::new(Num(5)), Box::new(Num(0)))), "0 + 5");
+    assert_eq!(describe(&minus), "7 - 2");
+    assert_eq!(describe(&plus_neg), "7 + 2");
+    assert_eq!(describe(&Neg(Box::new(Neg(Box::new(Num(3)))))), "3");
+    assert_eq!(describe(&Neg(Box::new(Num(3)))), "-3");
+
+    let simplified = simplify(Box::new(Neg(Box::new(Neg(Box::new(zero_plus))))));
+    assert_eq!(describe(simplified.as_ref()), "5");
+    assert_eq!(simplify(Box::new(plus_neg)).eval(), 9);
+    assert_eq!(describe(simplify(Box::new(minus)).as_ref()), "7 - 2");
+}
+
+#[test]
+fn test_match_t_mut() {
+    type_enum! {
+        enum Shape {
+            Circle(f64),
+            Square { side: f64 },
+            Framed(Box<dyn Shape>),
+            Group(Vec<Box<dyn Shape>>),
+        }
+    }
+
+    // Fields are bound by mutable reference, so arms change them in place
+    fn scale(shape: &mut dyn Shape, factor: f64) {
+        match_t!(mut shape as Shape {
+            Circle(r) => *r *= factor,
+            Square { side } => *side *= factor,
+            Framed(inner) => scale(inner.as_mut(), factor),
+            Group(shapes) => {
+                for shape in shapes {
+                    scale(shape.as_mut(), factor);
+                }
+            }
+        })
+    }
+
+    // Nested `box` patterns reach into boxed fields mutably too
+    fn grow_framed_circle(shape: &mut dyn Shape) -> bool {
+        match_t!(try mut shape as Shape {
+            Framed(box Circle(r)) => *r += 1.0,
+        })
+        .is_some()
+    }
+
+    fn size(shape: &dyn Shape) -> f64 {
+        match_t!(shape as Shape {
+            Circle(r) => *r,
+            Square { side } => *side,
+            Framed(inner) => size(inner.as_ref()),
+            Group(shapes) => shapes.iter().map(|shape| size(shape.as_ref())).sum(),
+        })
+    }
+
+    let mut group: Box<dyn Shape> = Box::new(Group(vec![
+        Box::new(Circle(1.0)),
+        Box::new(Framed(Box::new(Square { side: 2.0 }))),
+    ]));
+    scale(group.as_mut(), 2.0);
+    assert_eq!(size(group.as_ref()), 6.0);
+
+    let mut framed: Box<dyn Shape> = Box::new(Framed(Box::new(Circle(1.0))));
+    assert!(grow_framed_circle(framed.as_mut()));
+    assert_eq!(size(framed.as_ref()), 2.0);
+    // A box is matched in place, without giving it up
+    match_t!(mut framed as Shape {
+        Framed(inner) => *inner = Box::new(Square { side: 3.0 }),
+        _ => {}
+    });
+    assert_eq!(size(framed.as_ref()), 3.0);
+    assert!(!grow_framed_circle(group.as_mut()));
+    assert!(!grow_framed_circle(&mut Framed(Box::new(Square {
+        side: 1.0
+    }))));
+}
+
+#[test]
+fn test_match_t_move_rc_arc() {
+    use std::rc::Rc;
+    use std::sync::Arc;
+
+    type_enum! {
+        #[derive(Clone)]
+        enum Token {
+            Word(String),
+            Num(i64),
+        }
+    }
+
+    fn into_text(token: Rc<dyn Token>) -> String {
+        match_t!(move token as Token {
+            Word(w) => w,
+            Num(n) => n.to_string(),
+        })
+    }
+
+    // An unshared `Rc` gives up its value; a shared one hands out a clone
+    let word: Rc<dyn Token> = Rc::new(Word("hi".to_string()));
+    assert_eq!(into_text(word), "hi");
+    let num: Rc<dyn Token> = Rc::new(Num(7));
+    let kept = Rc::clone(&num);
+    assert_eq!(into_text(num), "7");
+    assert_eq!(Rc::strong_count(&kept), 1);
+
+    // `try move` hands the `Arc` back when no arm matches
+    fn into_word(token: Arc<dyn Token>) -> Result<String, Arc<dyn Token>> {
+        match_t!(try move token as Token {
+            Word(w) => w,
+        })
+    }
+
+    let word: Arc<dyn Token> = Arc::new(Word("yo".to_string()));
+    assert_eq!(into_word(word).ok().as_deref(), Some("yo"));
+    let num: Arc<dyn Token> = Arc::new(Num(1));
+    assert!(into_word(num).is_err());
+}
+
+#[test]
+fn test_match_t_tuples() {
+    type_enum! {
+        enum Value {
+            Int(i64),
+            Str(String),
+            Nil,
+        }
+    }
+
+    // Each arm matches every value against its own pattern; `_` elements
+    // match without a type check
+    fn add(lhs: &dyn Value, rhs: &dyn Value) -> String {
+        match_t!((lhs, rhs) as Value {
+            (Int(a), Int(b)) => (a + b).to_string(),
+            (Str(a), Str(b)) => format!("{}{}", a, b),
+            (Int(0), _) | (_, Int(0)) => "zero".to_string(),
+            (Nil, _) | (_, Nil) => "nil".to_string(),
+            _ => "mismatch".to_string(),
+        })
+    }
+
+    assert_eq!(add(&Int(1), &Int(2)), "3");
+    assert_eq!(add(&Str("a".into()), &Str("b".into())), "ab");
+    assert_eq!(add(&Str("a".into()), &Int(0)), "zero");
+    assert_eq!(add(&Nil, &Int(3)), "nil");
+    assert_eq!(add(&Int(3), &Str("a".into())), "mismatch");
+
+    // Under `move` no value is consumed before the whole arm matches, and
+    // `try` hands both back when none does
+    type Pair = (Box<dyn Value>, Box<dyn Value>);
+
+    fn concat(lhs: Box<dyn Value>, rhs: Box<dyn Value>) -> Result<String, Pair> {
+        match_t!(try move (lhs, rhs) as Value {
+            (Str(a), Str(b)) => a + &b,
+            (Str(a), Int(n)) => format!("{}{}", a, n),
+        })
+    }
+
+    assert_eq!(
+        concat(Box::new(Str("a".into())), Box::new(Int(1)))
+            .ok()
+            .as_deref(),
+        Some("a1")
+    );
+    let (lhs, rhs) = concat(Box::new(Int(1)), Box::new(Str("b".into()))).unwrap_err();
+    assert!(lhs.is_int() && rhs.is_str());
+
+    // Under `mut` every value is bound by mutable reference
+    fn swap(lhs: &mut dyn Value, rhs: &mut dyn Value) -> bool {
+        match_t!(try mut (lhs, rhs) as Value {
+            (Int(a), Int(b)) => std::mem::swap(a, b),
+            (Str(a), Str(b)) => std::mem::swap(a, b),
+        })
+        .is_some()
+    }
+
+    let (mut lhs, mut rhs): (Box<dyn Value>, Box<dyn Value>) = (Box::new(Int(1)), Box::new(Int(2)));
+    assert!(swap(lhs.as_mut(), rhs.as_mut()));
+    assert_eq!(lhs.as_int().map(|i| i.0), Some(2));
+    assert!(!swap(lhs.as_mut(), &mut Nil));
+}
+
+#[test]
+fn test_match_t_move_ref_bindings() {
+    type_enum! {
+        enum Doc {
+            Text(String),
+            Quote(Box<dyn Doc>, String),
+        }
+    }
+
+    fn words(doc: &dyn Doc) -> usize {
+        match_t!(doc as Doc {
+            Text(s) => s.split_whitespace().count(),
+            Quote(inner, _) => words(inner.as_ref()),
+        })
+    }
+
+    // `ref` looks at a field while others move out, and `ref mut` changes
+    // the moved-out value in place, nested boxes included
+    fn render(doc: Box<dyn Doc>) -> String {
+        match_t!(move doc as Doc {
+            Text(s) => s,
+            Quote(box Text(ref mut text), ref mut by) => {
+                text.make_ascii_uppercase();
+                by.insert(0, '-');
+                format!("\"{}\" {}", text, by)
+            }
+            Quote(ref inner, by) => format!("{} words {}", words(inner.as_ref()), by),
+        })
+    }
+
+    assert_eq!(render(Box::new(Text("hi".into()))), "hi");
+    let quote = Quote(Box::new(Text("hi".into())), "me".into());
+    assert_eq!(render(Box::new(quote)), "\"HI\" -me");
+    let nested = Quote(
+        Box::new(Quote(Box::new(Text("a b".into())), "x".into())),
+        "y".into(),
+    );
+    assert_eq!(render(Box::new(nested)), "2 words y");
+}
+
+#[test]
+fn test_rest_patterns() {
+    pub trait Weight {
+        fn weight(&self) -> f64;
+    }
+
+    impl Weight for f64 {
+        fn weight(&self) -> f64 {
+            *self
+        }
+    }
+
+    type_enum! {
+        enum Shape {
+            Triangle { base: f64, height: f64 },
+            Rectangle(f64, f64),
+            Circle(f64),
+            Weighted<W: Weight>(W, f64),
+        }
+
+        fn width(&self) -> f64 {
+            Triangle { base, .. } => *base,
+            Rectangle(w, ..) => *w,
+            Circle(..) => 0.0,
+            Weighted(.., w) => *w,
+        }
+    }
+
+    // `..` works the same for every variant shape in `match_t!`, whether the
+    // variant is reached by downcast or through the hooks of `Weighted`
+    fn height(shape: &dyn Shape) -> f64 {
+        match_t!(shape as Shape {
+            Triangle { height, .. } => *height,
+            Rectangle(.., h) => *h,
+            Circle(..) => 0.0,
+            Weighted(w, ..) => w.weight(),
+        })
+    }
+
+    fn into_width(shape: Box<dyn Shape>) -> f64 {
+        match_t!(move shape as Shape {
+            Triangle { base, .. } => base,
+            Rectangle { 0: w, .. } => w,
+            Circle(..) => 0.0,
+            Weighted(.., w) => w,
+        })
+    }
+
+    // In a tuple arm, `..` stands for the values it leaves out
+    fn first_circle(a: &dyn Shape, b: &dyn Shape, c: &dyn Shape) -> Option<f64> {
+        match_t!((a, b, c) as Shape {
+            (Circle(r), ..) => Some(*r),
+            (_, Circle(r), _) => Some(*r),
+            (.., Circle(r)) => Some(*r),
+            _ => None,
+        })
+    }
+
+    let triangle = Triangle {
+        base: 3.0,
+        height: 4.0,
+    };
+    assert_eq!(triangle.width(), 3.0);
+    assert_eq!(height(&triangle), 4.0);
+    assert_eq!(height(&Rectangle(1.0, 2.0)), 2.0);
+    assert_eq!(height(&Weighted(5.0, 1.0)), 5.0);
+    assert_eq!(Weighted(5.0, 1.0).width(), 1.0);
+    assert_eq!(into_width(Box::new(Rectangle(1.0, 2.0))), 1.0);
+    assert_eq!(into_width(Box::new(Weighted(5.0, 1.0))), 1.0);
+    assert_eq!(
+        first_circle(&triangle, &Rectangle(1.0, 2.0), &Circle(7.0)),
+        Some(7.0)
+    );
+    assert_eq!(first_circle(&triangle, &triangle, &triangle), None);
+}
+
+#[test]
+fn test_match_t_mismatch_messages() {
+    use std::any::Any;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    type_enum! {
+        enum Pet {
+            Cat(String),
+            Dog(String),
+            Fish,
+        }
+    }
+
+    fn message(f: impl FnOnce()) -> String {
+        let payload = catch_unwind(AssertUnwindSafe(f)).unwrap_err();
+        payload.downcast::<String>().map(|s| *s).unwrap()
+    }
+
+    // The panic names the variant no arm matched and what the arms tried
+    fn name(pet: &dyn Pet) -> &str {
+        match_t!(pet {
+            Cat(n) | Dog(n) => n,
+        })
+    }
+
+    assert_eq!(
+        message(|| {
+            name(&Fish);
+        }),
+        "No match_t! arm matches a value of type `examples::test_match_t_mismatch_messages::Fish`; \
+         arms tried `Cat`, `Dog`"
+    );
+
+    fn into_name(pet: Box<dyn Pet>) -> String {
+        match_t!(move pet {
+            Cat(n) => n,
+        })
+    }
+
+    let msg = message(|| {
+        into_name(Box::new(Dog("rex".into())));
+    });
+    assert!(msg.contains("::Dog`; arms tried `Cat`"), "{}", msg);
+
+    // A value seen through `dyn Any` is named as that
+    fn any_name(value: &dyn Any) -> &str {
+        match_t!(value {
+            Cat(n) => n,
+        })
+    }
+
+    let msg = message(|| {
+        any_name(&1u8);
+    });
+    assert!(msg.contains("type `dyn core::any::Any`"), "{}", msg);
+
+    // Tuples name every value, and each arm's types in order
+    fn both(a: &dyn Pet, b: &dyn Pet) -> bool {
+        match_t!((a, b) as Pet {
+            (Cat(_), Cat(_)) => true,
+            (Dog(_) | Cat(_), _) => false,
+        })
+    }
+
+    let msg = message(|| {
+        both(&Fish, &Fish);
+    });
+    assert!(
+        msg.ends_with("::Fish`); arms tried `(Cat, Cat)`, `(Dog | Cat, _)`"),
+        "{}",
+        msg
+    );
+}
+
+#[test]
+fn test_match_t_ascribed_scrutinee() {
+    type_enum! {
+        enum Sum<A, B> {
+            Inl(A),
+            Inr(B),
+        }
+    }
+
+    // The scrutinee's written type hints the enum, and its arguments reach
+    // arms naming generic variants
+    fn fold_sum<A: 'static, B: 'static, R>(
+        sum: Box<dyn Sum<A, B>>,
+        left: impl FnOnce(A) -> R,
+        right: impl FnOnce(B) -> R,
+    ) -> R {
+        match_t!(move sum: Box<dyn Sum<A, B>> {
+            Inl(a) => left(a),
+            Inr(b) => right(b),
+        })
+    }
+
+    let inl: Box<dyn Sum<i32, String>> = Box::new(Inl(2));
+    assert_eq!(fold_sum(inl, |a| a * 2, |b| b.len() as i32), 4);
+    let inr: Box<dyn Sum<i32, String>> = Box::new(Inr("abc".to_string()));
+    assert_eq!(fold_sum(inr, |a| a * 2, |b| b.len() as i32), 3);
+
+    // By reference, the value is coerced to the written type
+    fn is_left<A: 'static, B: 'static>(sum: &dyn Sum<A, B>) -> bool {
+        match_t!(sum: &dyn Sum<A, B> {
+            Inl(_) => true,
+            Inr(_) => false,
+        })
+    }
+
+    assert!(is_left::<u8, ()>(&Inl(1u8)));
+    assert!(!is_left::<u8, ()>(&Inr(())));
+}
+
+mod trait_only {
+    pub mod shapes {
+        use enum_typer::type_enum;
+
+        type_enum! {
+            pub enum Shape {
+                Circle(pub f64),
+                Square(pub f64),
+            }
+        }
+    }
+
+    use enum_typer::match_t;
+    // Only the trait: its companion macro comes along under the same name
+    use shapes::Shape;
+
+    pub fn area(shape: &dyn Shape) -> f64 {
+        match_t!(shape as Shape {
+            shapes::Circle(r) => 3.0 * r * r,
+            shapes::Square(s) => s * s,
+        })
+    }
+}
+
+#[test]
+fn test_match_t_trait_only_import() {
+    assert_eq!(trait_only::area(&trait_only::shapes::Square(2.0)), 4.0);
+    assert_eq!(trait_only::area(&trait_only::shapes::Circle(1.0)), 3.0);
+}
+
+#[test]
+fn test_match_t_plain_trait_hint() {
+    use std::any::Any;
+
+    // A trait `type_enum!` didn't generate is matched by downcasting alone
+    trait Animal: Any {}
+
+    struct Dog;
+    struct Cat(u8);
+    impl Animal for Dog {}
+    impl Animal for Cat {}
+
+    fn lives(animal: &dyn Animal) -> u8 {
+        match_t!(animal as Animal {
+            Cat(lives) => *lives,
+            _ => 1,
+        })
+    }
+
+    assert_eq!(lives(&Cat(9)), 9);
+    assert_eq!(lives(&Dog), 1);
+}
+
+#[test]
+fn test_match_t_cross_crate_hints() {
+    use enum_typer_shapes::{Circle, Inl, Inr, Shape, Square, Sum};
+
+    // The companion macros are private to the defining crate, so these
+    // match without the definitions
+    fn area(shape: &dyn Shape) -> f64 {
+        match_t!(shape as Shape {
+            Circle(r) => 3.0 * r * r,
+            Square(s) => s * s,
+        })
+    }
+
+    assert_eq!(area(&Square(2.0)), 4.0);
+    assert_eq!(area(&Circle(1.0)), 3.0);
+
+    fn fold_sum<A: 'static, B: 'static, R>(
+        sum: Box<dyn Sum<A, B>>,
+        left: impl FnOnce(A) -> R,
+        right: impl FnOnce(B) -> R,
+    ) -> R {
+        match_t!(move sum: Box<dyn Sum<A, B>> {
+            Inl<A>(a) => left(a),
+            Inr<B>(b) => right(b),
+        })
+    }
+
+    let inl: Box<dyn Sum<i32, String>> = Box::new(Inl(2));
+    assert_eq!(fold_sum(inl, |a| a * 2, |b| b.len() as i32), 4);
+    let inr: Box<dyn Sum<i32, String>> = Box::new(Inr("abc".to_string()));
+    assert_eq!(fold_sum(inr, |a| a * 2, |b| b.len() as i32), 3);
+}
+
+#[test]
+fn test_no_std_crate_beside_std() {
+    use enum_typer_no_std::{one_plus_two, sum, Expr, IsZero};
+
+    // Built with `std` enabled here and only `alloc` there
+    assert_eq!(sum(one_plus_two()), 3);
+    assert!(!IsZero(one_plus_two()).eval());
+}
@@ -0,0 +1,66 @@
+//! Generated code builds in crates that forbid `unsafe` code
+#![forbid(unsafe_code)]
+
+use enum_typer::{match_t, type_enum};
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::task::{Context, Poll, Waker};
+
+type_enum! {
+    #[type_enum(send_sync)]
+    enum Job {
+        Sleep(u32),
+        Print(String),
+    }
+}
+
+type_enum! {
+    enum Step {
+        Ready(u32),
+        Then<F: Future<Output = u32>>(F),
+    }
+
+    fn poll_step(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<u32> {
+        Ready(value) => Poll::Ready(*value),
+        Then<F>(future) => future.poll(cx),
+    }
+}
+
+fn describe(job: Box<dyn Job>) -> String {
+    match_t!(move job as Job {
+        Sleep(secs) => format!("sleep {}", secs),
+        Print(text) => text,
+    })
+}
+
+#[test]
+fn test_move_out_of_pointers() {
+    assert_eq!(describe(Box::new(Sleep(2))), "sleep 2");
+
+    let print: Rc<dyn Job> = Rc::new(Print("rc".to_string()));
+    let text = match_t!(move print as Job {
+        Print(text) => text,
+        _ => String::new(),
+    });
+    assert_eq!(text, "rc");
+
+    // `Job` is `Send + Sync`, so an unshared `Arc` gives up its value, which
+    // isn't `Clone`
+    let print: Arc<dyn Job> = Arc::new(Print("arc".to_string()));
+    let text = match_t!(move print as Job {
+        Print(text) => text,
+        _ => String::new(),
+    });
+    assert_eq!(text, "arc");
+}
+
+#[test]
+fn test_pinned_receivers() {
+    let mut cx = Context::from_waker(Waker::noop());
+    let mut step: Pin<Box<dyn Step>> = Box::pin(Then(async { 42 }));
+    assert_eq!(step.as_mut().poll_step(&mut cx), Poll::Ready(42));
+    let mut step: Pin<Box<dyn Step>> = Box::pin(Ready(1));
+    assert_eq!(step.as_mut().poll_step(&mut cx), Poll::Ready(1));
+}
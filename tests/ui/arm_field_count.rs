@@ -0,0 +1,15 @@
+use enum_typer::type_enum;
+
+type_enum! {
+    enum Shape {
+        Circle(f64),
+        Rect(f64, f64),
+    }
+
+    fn area(&self) -> f64 {
+        Circle(r) => 3.14 * r * r,
+        Rect(w) => w * w,
+    }
+}
+
+fn main() {}
@@ -0,0 +1,42 @@
+use enum_typer::{match_t, type_enum};
+
+type_enum! {
+    enum Ref<'a, T> {
+        Borrowed(&'a T),
+        Owned(T),
+    }
+}
+
+type_enum! {
+    enum Token {
+        Word(String),
+    }
+}
+
+fn take<'a>(r: Box<dyn Ref<'a, i32> + 'a>) -> i32 {
+    match_t!(move r as Ref<'a, i32> {
+        Borrowed(x) => *x,
+        Owned(x) => x,
+    })
+}
+
+fn across(value: &dyn std::any::Any) -> usize {
+    match_t!(value as Ref | Token {
+        Owned<usize>(n) => *n,
+        Word(w) => w.len(),
+        _ => 0,
+    })
+}
+
+// By reference and in place, the variants are reached through view hooks
+fn peek<'a>(r: &mut dyn Ref<'a, i32>) -> i32 {
+    match_t!(mut r as Ref<'a, i32> {
+        Borrowed(x) => **x,
+        Owned(x) => {
+            *x += 1;
+            *x
+        }
+    })
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use enum_typer::type_enum;
+
+type_enum! {
+    enum Token {
+        Word(String),
+        Num(i64),
+        Word(char),
+    }
+}
+
+fn main() {}
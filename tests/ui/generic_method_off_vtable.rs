@@ -0,0 +1,20 @@
+#![deny(deprecated)]
+
+use enum_typer::type_enum;
+
+type_enum! {
+    enum Expr {
+        Num(i32),
+    }
+
+    fn map<F: Fn(i32) -> i32>(&self, f: F) -> i32 {
+        Num(n) => f(*n),
+    }
+
+    // Said out loud, it doesn't warn
+    fn apply<F: Fn(i32)>(&self, f: F) where Self: Sized {
+        Num(n) => f(*n),
+    }
+}
+
+fn main() {}
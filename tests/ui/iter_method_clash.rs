@@ -0,0 +1,16 @@
+use enum_typer::type_enum;
+
+type_enum! {
+    #[type_enum(iter)]
+    enum List<T> {
+        End,
+        Link(T, Box<dyn List<T>>),
+    }
+
+    fn iter(&self) -> usize {
+        End => 0,
+        Link(_, rest) => 1 + rest.iter(),
+    }
+}
+
+fn main() {}
@@ -0,0 +1,26 @@
+#![deny(unused_variables, non_snake_case, deprecated)]
+
+use enum_typer::type_enum;
+
+#[deprecated]
+fn old() -> i32 {
+    1
+}
+
+type_enum! {
+    enum Expr {
+        Num(i32),
+        Neg(Box<dyn Expr>),
+    }
+
+    // Method bodies are the user's code and are linted like it
+    fn eval(&self) -> i32 {
+        Num(n) => {
+            let Unused = 2;
+            *n + old()
+        },
+        Neg(inner) => -inner.eval(),
+    }
+}
+
+fn main() {}
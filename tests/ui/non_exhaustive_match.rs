@@ -0,0 +1,17 @@
+use enum_typer::{match_t, type_enum};
+
+type_enum! {
+    enum Shape {
+        Circle(f64),
+        Square(f64),
+        Point,
+    }
+}
+
+fn area(shape: &dyn Shape) -> f64 {
+    match_t!(shape as Shape {
+        Circle(r) => 3.14 * r * r,
+    })
+}
+
+fn main() {}
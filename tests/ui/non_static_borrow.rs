@@ -0,0 +1,10 @@
+use enum_typer::type_enum;
+
+type_enum! {
+    enum Expr<T> {
+        Num(i32) : Expr<i32>,
+        Neg(&dyn Expr<i32>) : Expr<i32>,
+    }
+}
+
+fn main() {}
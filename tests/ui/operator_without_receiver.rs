@@ -0,0 +1,25 @@
+use enum_typer::type_enum;
+
+type_enum! {
+    #[ops(Neg = zero)]
+    enum Num {
+        Int(i32),
+    }
+
+    fn zero() -> i32 {
+        Int => 0,
+    }
+}
+
+type_enum! {
+    #[ops(Index = get)]
+    enum List {
+        Items(Vec<u8>),
+    }
+
+    fn get(index: usize) -> &'static u8 {
+        Items => &0,
+    }
+}
+
+fn main() {}
@@ -0,0 +1,19 @@
+use enum_typer::{type_enum, type_enum_impl};
+
+type_enum! {
+    enum Slot {
+        Held(u32),
+        Empty,
+    }
+}
+
+type_enum_impl! {
+    Slot =>
+
+    fn poke(self: std::pin::Pin<&mut Self>) {
+        Held(_) => {}
+        Empty => {}
+    }
+}
+
+fn main() {}
@@ -0,0 +1,31 @@
+use enum_typer::type_enum;
+use std::marker::PhantomPinned;
+use std::pin::Pin;
+
+trait Poke {
+    fn poke(self: Pin<&mut Self>);
+}
+
+type_enum! {
+    enum Slot {
+        Held(PhantomPinned),
+        Empty,
+    }
+
+    impl Poke {
+        fn poke(self: Pin<&mut Self>) {
+            Held(_) => {}
+            Empty => {}
+        }
+    }
+}
+
+// Pinned receivers of an `impl Trait { .. }` section are projected like the
+// enum's own methods, so the variant structs may not be moved out of a pin
+impl Unpin for Held {}
+
+impl Drop for Empty {
+    fn drop(&mut self) {}
+}
+
+fn main() {}
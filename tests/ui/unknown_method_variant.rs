@@ -0,0 +1,15 @@
+use enum_typer::type_enum;
+
+type_enum! {
+    enum Shape {
+        Circle(f64),
+        Square(f64),
+    }
+
+    fn side(&self) -> f64 {
+        Circle(r) => *r,
+        Sqare(s) => *s,
+    }
+}
+
+fn main() {}
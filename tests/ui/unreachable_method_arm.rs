@@ -0,0 +1,18 @@
+#![deny(unreachable_patterns)]
+
+use enum_typer::type_enum;
+
+type_enum! {
+    enum Shape {
+        Circle(f64),
+        Square(f64),
+    }
+
+    fn size(&self) -> f64 {
+        Circle(r) => *r,
+        Circle(_) => 0.0,
+        _ => 1.0,
+    }
+}
+
+fn main() {}
@@ -0,0 +1,20 @@
+#![deny(deprecated)]
+
+use enum_typer::type_enum;
+
+// `Flag` was meant to be at `Expr<bool>`, so `T` is only ever `i32`
+type_enum! {
+    enum Expr<T> {
+        Num(i32) : Expr<i32>,
+        Flag(bool) : Expr<i32>,
+    }
+}
+
+// `U` is left to the variants, so it is in use
+type_enum! {
+    enum Pair<U> {
+        Both(U, U),
+    }
+}
+
+fn main() {}
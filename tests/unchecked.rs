@@ -0,0 +1,37 @@
+//! Reachable fallbacks stay checked under the `unchecked` feature, which only
+//! drops branches in release builds:
+//! `cargo test --release --features unchecked --test unchecked`
+
+use enum_typer::{match_t, type_enum};
+
+type_enum! {
+    enum Shape {
+        Circle(f64),
+        Square(Option<f64>),
+    }
+}
+
+fn side(shape: &dyn Shape) -> Option<f64> {
+    let side = match_t!(shape as Shape {
+        Circle(r) => *r,
+        Square(s) => (*s)?,
+    });
+    Some(side)
+}
+
+fn total(lhs: &dyn Shape, rhs: &dyn Shape) -> Option<f64> {
+    let total = match_t!((lhs, rhs) as Shape {
+        (Circle(a), Circle(b)) => a + b,
+        (Square(s), _) | (_, Square(s)) => (*s)?,
+    });
+    Some(total)
+}
+
+#[test]
+fn test_early_return_from_arms() {
+    assert_eq!(side(&Circle(1.0)), Some(1.0));
+    assert_eq!(side(&Square(Some(2.0))), Some(2.0));
+    assert_eq!(side(&Square(None)), None);
+    assert_eq!(total(&Circle(1.0), &Circle(2.0)), Some(3.0));
+    assert_eq!(total(&Circle(1.0), &Square(None)), None);
+}